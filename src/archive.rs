@@ -0,0 +1,171 @@
+use crate::cli::Cli;
+use crate::entry::{EntryType, RawEntry};
+use crate::kind;
+use chrono::{Local, NaiveDate, TimeZone};
+use flate2::read::GzDecoder;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+// Known by name, otherwise only under --archive by their first bytes
+fn format(path: &Path, cli: &Cli) -> Option<Format> {
+    if !path.is_file() {
+        return None;
+    }
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    if name.ends_with(".zip") {
+        Some(Format::Zip)
+    } else if name.ends_with(".tar") {
+        Some(Format::Tar)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Some(Format::TarGz)
+    } else if cli.archive {
+        match kind::detect(path)?.as_str() {
+            "zip archive" => Some(Format::Zip),
+            "tar archive" => Some(Format::Tar),
+            "gzip archive" => Some(Format::TarGz),
+            _ => None,
+        }
+    } else {
+        None
+    }
+}
+
+/// Whether `path` is an archive whose members are listed in place of a directory.
+pub fn opens(path: &Path, cli: &Cli) -> bool {
+    format(path, cli).is_some()
+}
+
+/// The members of the archive at `path`, named by their paths inside it without a
+/// trailing slash, in the order the archive has them. Only headers are read, nothing is
+/// extracted, though a compressed tar has to be decompressed on the way through.
+pub fn members(path: &Path, cli: &Cli) -> io::Result<Vec<RawEntry>> {
+    let file = BufReader::new(File::open(path)?);
+    let mut members = match format(path, cli) {
+        Some(Format::Zip) => zip_members(file),
+        Some(Format::Tar) => tar_members(file),
+        Some(Format::TarGz) => tar_members(GzDecoder::new(file)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a zip or tar archive",
+        )),
+    }?;
+    // The archive's own root, written as `./` by some tars
+    members.retain(|raw| !raw.name.is_empty() && raw.name != ".");
+    Ok(members)
+}
+
+/// The members of the archive at `path` grouped by the directory they are in, keyed by
+/// `path` joined with that directory and named by their last component, for a tree.
+/// Directories only implied by the paths of their members are made up.
+pub fn directories(path: &Path, cli: &Cli) -> io::Result<HashMap<PathBuf, Vec<RawEntry>>> {
+    let mut by_name = BTreeMap::new();
+    for raw in members(path, cli)? {
+        by_name.insert(raw.name.clone(), raw);
+    }
+    let implied: Vec<String> = by_name
+        .keys()
+        .flat_map(|name| {
+            name.match_indices('/')
+                .map(|(end, _)| name[..end].to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for dir in implied {
+        by_name
+            .entry(dir.clone())
+            .or_insert_with(|| RawEntry::member(dir, EntryType::Dir, 0, 0o755));
+    }
+
+    let mut directories: HashMap<PathBuf, Vec<RawEntry>> = HashMap::new();
+    for (name, mut raw) in by_name {
+        let (dir, base) = match name.rsplit_once('/') {
+            Some((dir, base)) => (path.join(dir), base),
+            None => (path.to_path_buf(), name.as_str()),
+        };
+        raw.name = base.to_string();
+        directories.entry(dir).or_default().push(raw);
+    }
+    Ok(directories)
+}
+
+fn zip_members(file: BufReader<File>) -> io::Result<Vec<RawEntry>> {
+    let mut archive = zip::ZipArchive::new(file).map_err(invalid)?;
+    let mut members = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        // Raw access reads the header only, encrypted members need no password for it
+        let member = archive.by_index_raw(index).map_err(invalid)?;
+        let e_type = if member.is_dir() {
+            EntryType::Dir
+        } else if member.is_symlink() {
+            EntryType::Symlink
+        } else {
+            EntryType::File
+        };
+        let mode = member.unix_mode().unwrap_or(if e_type == EntryType::Dir {
+            0o755
+        } else {
+            0o644
+        });
+        let name = member_name(&member.name().map_err(invalid)?);
+        let mut raw = RawEntry::member(name, e_type, member.size(), mode);
+        raw.modified = member.last_modified().and_then(|t| {
+            // Zip times are written in whatever zone the archiver was in
+            let date = NaiveDate::from_ymd_opt(t.year().into(), t.month().into(), t.day().into())?;
+            let time = date.and_hms_opt(t.hour().into(), t.minute().into(), t.second().into())?;
+            Local
+                .from_local_datetime(&time)
+                .earliest()
+                .map(SystemTime::from)
+        });
+        members.push(raw);
+    }
+    Ok(members)
+}
+
+fn tar_members(reader: impl Read) -> io::Result<Vec<RawEntry>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut members = Vec::new();
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        let e_type = match header.entry_type() {
+            tar::EntryType::Directory => EntryType::Dir,
+            tar::EntryType::Symlink => EntryType::Symlink,
+            tar::EntryType::Fifo => EntryType::Fifo,
+            tar::EntryType::Block => EntryType::BlockDevice,
+            tar::EntryType::Char => EntryType::CharDevice,
+            // Hard links are files under another name
+            _ => EntryType::File,
+        };
+        let name = member_name(&entry.path()?.to_string_lossy());
+        let mut raw = RawEntry::member(name, e_type, header.size()?, header.mode()?);
+        raw.modified = header
+            .mtime()
+            .ok()
+            .map(|t| UNIX_EPOCH + Duration::from_secs(t));
+        raw.uid = header.uid().unwrap_or_default() as u32;
+        raw.gid = header.gid().unwrap_or_default() as u32;
+        members.push(raw);
+    }
+    Ok(members)
+}
+
+// `./` in front and `/` behind directories are how archivers write paths, not part of them
+fn member_name(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    path.strip_prefix("./").unwrap_or(path).to_string()
+}
+
+fn invalid(e: zip::result::ZipError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
@@ -0,0 +1,303 @@
+use crate::RawEntry;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs,
+    hash::{BuildHasher, RandomState},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Bumped whenever the layout of the cache file changes, older files are discarded.
+const CACHE_VERSION: u32 = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CacheMode {
+    /// Serve unchanged directories from the cache
+    Use,
+    /// Rescan and rewrite the cached copy
+    Refresh,
+    /// Delete the cache file
+    Clear,
+}
+
+/// The directory's own timestamps, which change whenever an entry is added, removed or renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirStamp {
+    mtime: (i64, i64),
+    ctime: (i64, i64),
+}
+
+impl DirStamp {
+    #[cfg(unix)]
+    pub fn of(path: &Path) -> Option<DirStamp> {
+        use std::os::unix::fs::MetadataExt;
+        let meta = fs::metadata(path).ok()?;
+        Some(DirStamp {
+            mtime: (meta.mtime(), meta.mtime_nsec()),
+            ctime: (meta.ctime(), meta.ctime_nsec()),
+        })
+    }
+
+    // There is no ctime outside of Unix, the mtime alone has to do
+    #[cfg(not(unix))]
+    pub fn of(path: &Path) -> Option<DirStamp> {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+        let mtime = (
+            since_epoch.as_secs() as i64,
+            since_epoch.subsec_nanos() as i64,
+        );
+        Some(DirStamp {
+            mtime,
+            ctime: mtime,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedDir {
+    stamp: DirStamp,
+    entries: Vec<RawEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    dirs: HashMap<String, CachedDir>,
+}
+
+pub struct DirCache {
+    file: CacheFile,
+}
+
+impl DirCache {
+    /// Loads the current user's cache, starting empty when it is missing, unreadable,
+    /// from another format version or not owned by the current user.
+    pub fn open() -> DirCache {
+        let file = cache_file()
+            .filter(|path| fs::metadata(path).is_ok_and(|meta| owned_by_current_user(&meta)))
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|file| file.version == CACHE_VERSION)
+            .unwrap_or_else(|| CacheFile {
+                version: CACHE_VERSION,
+                dirs: HashMap::new(),
+            });
+
+        DirCache { file }
+    }
+
+    pub fn lookup(&self, dir: &Path, stamp: &DirStamp) -> Option<&[RawEntry]> {
+        self.file
+            .dirs
+            .get(&cache_key(dir)?)
+            .filter(|cached| cached.stamp == *stamp)
+            .map(|cached| cached.entries.as_slice())
+    }
+
+    pub fn store(&mut self, dir: &Path, stamp: DirStamp, entries: Vec<RawEntry>) {
+        if let Some(key) = cache_key(dir) {
+            self.file.dirs.insert(key, CachedDir { stamp, entries });
+        }
+    }
+
+    /// An empty cache that is never loaded from or saved to the user's file.
+    #[cfg(test)]
+    pub fn empty() -> DirCache {
+        DirCache {
+            file: CacheFile {
+                version: CACHE_VERSION,
+                dirs: HashMap::new(),
+            },
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        self.save_to(&cache_file().ok_or(io::ErrorKind::NotFound)?)
+    }
+
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            let mut builder = fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder.create(parent)?;
+        }
+
+        // Write to a temporary file of this run's own first, so a concurrent run never
+        // reads half a cache and two runs saving at once don't write into the same file
+        let suffix = RandomState::new().hash_one(SystemTime::now());
+        let tmp = path.with_extension(format!("{}-{:016x}.tmp", std::process::id(), suffix));
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let written = options.open(&tmp).and_then(|mut out| {
+            out.write_all(serde_json::to_string(&self.file)?.as_bytes())?;
+            fs::rename(&tmp, path)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        written
+    }
+
+    pub fn clear() -> io::Result<()> {
+        match cache_file().map(fs::remove_file) {
+            Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn cache_key(dir: &Path) -> Option<String> {
+    Some(fs::canonicalize(dir).ok()?.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+fn owned_by_current_user(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    meta.uid() == users::get_effective_uid()
+}
+
+// The cache lives in the user's own profile there
+#[cfg(not(unix))]
+fn owned_by_current_user(_: &fs::Metadata) -> bool {
+    true
+}
+
+#[cfg(unix)]
+fn cache_file_name() -> String {
+    format!("metadata-{}.json", users::get_effective_uid())
+}
+
+#[cfg(not(unix))]
+fn cache_file_name() -> String {
+    "metadata.json".to_string()
+}
+
+// One file per user so cached owner and permission data never crosses accounts
+fn cache_file() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+    Some(base.join("better-ls").join(cache_file_name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    // The user's real cache file is never touched
+    fn empty() -> DirCache {
+        DirCache::empty()
+    }
+
+    fn read(dir: &Path) -> Vec<RawEntry> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|file| RawEntry::from_dir_entry(&file.unwrap()).unwrap())
+            .collect()
+    }
+
+    fn fixture() -> Fixture {
+        let fixture = Fixture::new();
+        fixture.file("dir/a.txt", b"one").dir("dir");
+        fixture
+    }
+
+    #[test]
+    fn a_hit_serves_what_was_stored() {
+        let fixture = fixture();
+        let dir = fixture.join("dir");
+        let mut cache = empty();
+        cache.store(&dir, DirStamp::of(&dir).unwrap(), read(&dir));
+
+        // Rewriting a file leaves its directory alone, so the stale size is what shows it
+        // came from the cache rather than another stat
+        fs::write(fixture.join("dir/a.txt"), b"one two").unwrap();
+        let entries = cache.lookup(&dir, &DirStamp::of(&dir).unwrap()).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a.txt");
+        assert_eq!(entries[0].len, 3);
+    }
+
+    #[test]
+    fn a_changed_directory_misses() {
+        let fixture = fixture();
+        let dir = fixture.join("dir");
+        let mut cache = empty();
+        cache.store(&dir, DirStamp::of(&dir).unwrap(), read(&dir));
+
+        fs::write(fixture.join("dir/b.txt"), b"").unwrap();
+        assert!(cache.lookup(&dir, &DirStamp::of(&dir).unwrap()).is_none());
+    }
+
+    #[test]
+    fn directories_are_keyed_by_their_real_path() {
+        let fixture = fixture();
+        let dir = fixture.join("dir");
+        let mut cache = empty();
+        let stamp = DirStamp::of(&dir).unwrap();
+        cache.store(&dir, stamp, read(&dir));
+
+        assert!(cache.lookup(&fixture.join("dir/../dir"), &stamp).is_some());
+        assert!(cache.lookup(fixture.path(), &stamp).is_none());
+        assert!(cache.lookup(&fixture.join("gone"), &stamp).is_none());
+    }
+
+    #[test]
+    fn entries_survive_the_file_format() {
+        let fixture = fixture();
+        let dir = fixture.join("dir");
+        let mut cache = empty();
+        let stamp = DirStamp::of(&dir).unwrap();
+        cache.store(&dir, stamp, read(&dir));
+
+        let json = serde_json::to_string(&cache.file).unwrap();
+        let file: CacheFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(file.version, CACHE_VERSION);
+        let loaded = DirCache { file };
+        let entries = loaded.lookup(&dir, &stamp).unwrap();
+        assert_eq!(entries[0].name, "a.txt");
+        assert_eq!(entries[0].len, 3);
+        assert_eq!(
+            entries[0].modified,
+            cache.lookup(&dir, &stamp).unwrap()[0].modified
+        );
+    }
+
+    #[test]
+    fn runs_saving_at_once_leave_a_whole_file() {
+        let fixture = fixture();
+        let dir = fixture.join("dir");
+        let mut cache = empty();
+        cache.store(&dir, DirStamp::of(&dir).unwrap(), read(&dir));
+        let path = fixture.join("cache/metadata.json");
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..20 {
+                        cache.save_to(&path).unwrap();
+                    }
+                });
+            }
+        });
+        let file: CacheFile = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(file.dirs.len(), 1);
+        // Every temporary file was renamed into place
+        let left: Vec<_> = fs::read_dir(fixture.join("cache"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(left, ["metadata.json"]);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{Cli, columns, sort::SortKey};
+use clap::{CommandFactory, ValueEnum};
+use serde::Serialize;
+
+/// Bumped whenever the shape of the document changes.
+const VERSION: u32 = 2;
+
+pub const FORMATS_HEADING: &str = "Output formats";
+pub const FILTERS_HEADING: &str = "Filters";
+
+// Cargo features compiled into this binary
+const FEATURES: &[&str] = &[];
+
+#[derive(Debug, Serialize)]
+struct Capabilities {
+    version: u32,
+    columns: &'static [columns::ColumnSpec],
+    layouts: Layouts,
+    sort_keys: Vec<String>,
+    output_formats: Vec<OptionSpec>,
+    filters: Vec<OptionSpec>,
+    features: &'static [&'static str],
+}
+
+#[derive(Debug, Serialize)]
+struct Layouts {
+    short: &'static [columns::Field],
+    long: &'static [columns::Field],
+}
+
+/// A command line option, `value` is the grammar of its argument if it takes one.
+#[derive(Debug, Serialize)]
+struct OptionSpec {
+    flag: String,
+    short: Option<String>,
+    value: Option<String>,
+    possible_values: Vec<String>,
+    help: String,
+}
+
+/// Builds the capability document from the column registry and the clap definition,
+/// on a single line when `compact`.
+pub fn dump(compact: bool) -> serde_json::Result<String> {
+    let mut command = Cli::command();
+    // Arguments only know how many values they take once the command is built
+    command.build();
+    let options_under = |heading: &str| -> Vec<OptionSpec> {
+        command
+            .get_arguments()
+            .filter(|arg| arg.get_help_heading() == Some(heading))
+            .filter_map(|arg| {
+                let takes_value = arg.get_num_args().is_some_and(|n| n.takes_values());
+                Some(OptionSpec {
+                    flag: format!("--{}", arg.get_long()?),
+                    short: arg.get_short().map(|s| format!("-{}", s)),
+                    value: arg
+                        .get_value_names()
+                        .filter(|_| takes_value)
+                        .map(|names| names.join(" ")),
+                    possible_values: arg
+                        .get_possible_values()
+                        .iter()
+                        .filter(|_| takes_value)
+                        .map(|v| v.get_name().to_string())
+                        .collect(),
+                    help: arg.get_help().map(|h| h.to_string()).unwrap_or_default(),
+                })
+            })
+            .collect()
+    };
+
+    let capabilities = Capabilities {
+        version: VERSION,
+        columns: columns::COLUMNS,
+        layouts: Layouts {
+            short: columns::SHORT_LAYOUT,
+            long: columns::LONG_LAYOUT,
+        },
+        sort_keys: SortKey::value_variants()
+            .iter()
+            .filter_map(|key| key.to_possible_value())
+            .map(|v| v.get_name().to_string())
+            .collect(),
+        output_formats: options_under(FORMATS_HEADING),
+        filters: options_under(FILTERS_HEADING),
+        features: FEATURES,
+    };
+
+    if compact {
+        serde_json::to_string(&capabilities)
+    } else {
+        serde_json::to_string_pretty(&capabilities)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    // Regenerate with `best-ls --dump-capabilities > tests/data/capabilities.json` after
+    // checking that the difference is intended, and bump VERSION if the shape changed
+    const SNAPSHOT: &str = include_str!("../tests/data/capabilities.json");
+
+    #[test]
+    fn matches_the_snapshot() {
+        assert_eq!(dump(false).unwrap(), SNAPSHOT.trim_end());
+    }
+
+    #[test]
+    fn compact_is_the_same_document() {
+        let compact = dump(true).unwrap();
+        assert!(!compact.contains('\n'));
+        let compact: Value = serde_json::from_str(&compact).unwrap();
+        let pretty: Value = serde_json::from_str(&dump(false).unwrap()).unwrap();
+        assert_eq!(compact, pretty);
+        assert_eq!(compact["version"], VERSION);
+    }
+
+    #[test]
+    fn columns_come_from_the_registry() {
+        let document: Value = serde_json::from_str(&dump(true).unwrap()).unwrap();
+        let ids: Vec<_> = document["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|column| column["id"].as_str().unwrap())
+            .collect();
+        let registry: Vec<_> = columns::COLUMNS
+            .iter()
+            .map(|c| c.field.to_string())
+            .collect();
+        assert_eq!(ids, registry);
+    }
+
+    #[test]
+    fn options_with_values_carry_their_grammar() {
+        let document: Value = serde_json::from_str(&dump(true).unwrap()).unwrap();
+        let option = |flag: &str| {
+            document["filters"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|option| option["flag"] == flag)
+                .unwrap_or_else(|| panic!("{} is missing", flag))
+                .clone()
+        };
+        assert_eq!(option("--min-size")["value"], "SIZE");
+        assert_eq!(option("--files-only")["value"], Value::Null);
+        assert_eq!(option("--all")["short"], "-a");
+    }
+}
@@ -0,0 +1,241 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The parts of a `--json` entry that `--changed` compares, older listings without
+/// `dev`/`ino` still load but can't have their renames detected.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestEntry {
+    pub name: String,
+    #[serde(default)]
+    pub len_bytes: u64,
+    #[serde(default)]
+    pub modified: Option<String>,
+    #[serde(default)]
+    pub dev: Option<u64>,
+    #[serde(default)]
+    pub ino: Option<u64>,
+}
+
+impl ManifestEntry {
+    fn inode(&self) -> Option<(u64, u64)> {
+        Some((self.dev?, self.ino?))
+    }
+
+    // Offsets may differ between the runs, so instants are compared rather than strings
+    fn modified_epoch(&self) -> Option<i64> {
+        let modified = self.modified.as_deref()?;
+        DateTime::parse_from_rfc3339(modified)
+            .ok()
+            .map(|d| d.timestamp())
+    }
+
+    // The inodes are the links' own, so a symlink made again with `ln -sfn` differs even
+    // when the new target has the same size and time
+    fn differs_from(&self, other: &ManifestEntry) -> bool {
+        self.len_bytes != other.len_bytes
+            || self.modified_epoch() != other.modified_epoch()
+            || self.inode().zip(other.inode()).is_some_and(|(a, b)| a != b)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum Change {
+    Added { name: String },
+    Removed { name: String },
+    Modified { name: String },
+    Renamed { from: String, name: String },
+}
+
+// Inodes of deleted files get reused, a rename also keeps size and modification time
+fn take_renamed<'a>(
+    removed: &mut HashMap<(u64, u64), &'a ManifestEntry>,
+    inode: (u64, u64),
+    entry: &ManifestEntry,
+) -> Option<&'a ManifestEntry> {
+    removed
+        .get(&inode)
+        .filter(|before| !before.differs_from(entry))?;
+    removed.remove(&inode)
+}
+
+/// Compares two listings of the same directory by name. An entry that disappeared and one
+/// that appeared with the same device, inode, size and modification time are reported as
+/// a rename. Names that are in both listings are never paired up this way, so a new hard
+/// link to a file that is still there shows up as added, and a name that now stands for
+/// another inode shows up as modified.
+pub fn compare(old: &[ManifestEntry], new: &[ManifestEntry]) -> Vec<Change> {
+    let old_by_name: HashMap<&str, &ManifestEntry> =
+        old.iter().map(|e| (e.name.as_str(), e)).collect();
+    let new_by_name: HashMap<&str, &ManifestEntry> =
+        new.iter().map(|e| (e.name.as_str(), e)).collect();
+
+    let mut removed_by_inode: HashMap<(u64, u64), &ManifestEntry> = HashMap::new();
+    let mut removed = Vec::new();
+    for entry in old {
+        if new_by_name.contains_key(entry.name.as_str()) {
+            continue;
+        }
+        if let Some(inode) = entry.inode() {
+            removed_by_inode.insert(inode, entry);
+        }
+        removed.push(entry);
+    }
+
+    let mut changes = Vec::new();
+    for entry in new {
+        match old_by_name.get(entry.name.as_str()) {
+            Some(before) if before.differs_from(entry) => changes.push(Change::Modified {
+                name: entry.name.clone(),
+            }),
+            Some(_) => {}
+            None => match entry
+                .inode()
+                .and_then(|i| take_renamed(&mut removed_by_inode, i, entry))
+            {
+                Some(before) => changes.push(Change::Renamed {
+                    from: before.name.clone(),
+                    name: entry.name.clone(),
+                }),
+                None => changes.push(Change::Added {
+                    name: entry.name.clone(),
+                }),
+            },
+        }
+    }
+
+    let renamed: Vec<_> = changes
+        .iter()
+        .filter_map(|c| match c {
+            Change::Renamed { from, .. } => Some(from.clone()),
+            _ => None,
+        })
+        .collect();
+    for entry in removed {
+        if !renamed.contains(&entry.name) {
+            changes.push(Change::Removed {
+                name: entry.name.clone(),
+            });
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODIFIED: &str = "2023-11-14T22:13:20Z";
+
+    fn entry(name: &str, len_bytes: u64, ino: u64) -> ManifestEntry {
+        ManifestEntry {
+            name: name.to_string(),
+            len_bytes,
+            modified: Some(MODIFIED.to_string()),
+            dev: Some(2049),
+            ino: Some(ino),
+        }
+    }
+
+    fn added(name: &str) -> Change {
+        Change::Added { name: name.into() }
+    }
+
+    fn removed(name: &str) -> Change {
+        Change::Removed { name: name.into() }
+    }
+
+    fn modified(name: &str) -> Change {
+        Change::Modified { name: name.into() }
+    }
+
+    fn renamed(from: &str, name: &str) -> Change {
+        Change::Renamed {
+            from: from.into(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn unchanged_listings_have_no_changes() {
+        let listing = [entry("a", 1, 10), entry("b", 2, 11)];
+        assert_eq!(compare(&listing, &listing), []);
+    }
+
+    #[test]
+    fn a_moved_inode_is_a_rename() {
+        let old = [entry("draft.txt", 120, 10), entry("keep", 1, 11)];
+        let new = [entry("keep", 1, 11), entry("final.txt", 120, 10)];
+        assert_eq!(compare(&old, &new), [renamed("draft.txt", "final.txt")]);
+    }
+
+    #[test]
+    fn a_new_hard_link_is_added() {
+        let old = [entry("data", 64, 10)];
+        let new = [entry("data", 64, 10), entry("data.link", 64, 10)];
+        assert_eq!(compare(&old, &new), [added("data.link")]);
+    }
+
+    #[test]
+    fn one_removed_inode_pairs_with_one_new_name() {
+        let old = [entry("a", 64, 10)];
+        let new = [entry("b", 64, 10), entry("c", 64, 10)];
+        assert_eq!(compare(&old, &new), [renamed("a", "b"), added("c")]);
+    }
+
+    #[test]
+    fn a_reused_inode_is_not_a_rename() {
+        let old = [entry("deleted", 64, 10)];
+        let new = [entry("created", 65, 10)];
+        assert_eq!(compare(&old, &new), [added("created"), removed("deleted")]);
+
+        let mut touched = entry("created", 64, 10);
+        touched.modified = Some("2024-01-01T00:00:00Z".into());
+        assert_eq!(
+            compare(&old, &[touched]),
+            [added("created"), removed("deleted")]
+        );
+    }
+
+    #[test]
+    fn a_retargeted_name_is_modified() {
+        // `ln -sfn` replaces the link, same size and time but another inode
+        let old = [entry("current", 7, 10)];
+        let new = [entry("current", 7, 12)];
+        assert_eq!(compare(&old, &new), [modified("current")]);
+        assert_eq!(
+            compare(&old, &[entry("current", 8, 10)]),
+            [modified("current")]
+        );
+    }
+
+    #[test]
+    fn offsets_dont_count_as_changes() {
+        let old = [entry("a", 1, 10)];
+        let mut new = entry("a", 1, 10);
+        new.modified = Some("2023-11-15T03:43:20+05:30".into());
+        assert_eq!(compare(&old, &[new]), []);
+    }
+
+    #[test]
+    fn listings_without_inodes_see_no_renames() {
+        let json = r#"[{"name": "old.txt", "len_bytes": 5}]"#;
+        let old: Vec<ManifestEntry> = serde_json::from_str(json).unwrap();
+        assert_eq!(old[0].inode(), None);
+        assert_eq!(old[0].modified_epoch(), None);
+        let mut new = entry("new.txt", 5, 10);
+        new.modified = None;
+        assert_eq!(
+            compare(&old, &[new]),
+            [added("new.txt"), removed("old.txt")]
+        );
+    }
+
+    #[test]
+    fn changes_serialize_with_their_kind() {
+        let json = serde_json::to_string(&renamed("a", "b")).unwrap();
+        assert_eq!(json, r#"{"change":"renamed","from":"a","name":"b"}"#);
+    }
+}
@@ -0,0 +1,1412 @@
+use crate::cache::CacheMode;
+use crate::color::ColorChoice;
+use crate::columns::Field;
+use crate::entry::{JsonSchema, RawEntry};
+use crate::hash::HashAlgorithm;
+use crate::hyperlink::HyperlinkMode;
+use crate::icons::IconsMode;
+use crate::pager::PagingMode;
+use crate::pattern::NamePattern;
+use crate::permissions::PermissionStyle;
+use crate::size::SizeStyle;
+use crate::sort::{SortCase, SortKey};
+use crate::style::TableStyle;
+use crate::time_style::{TimeKind, TimeStyle};
+use crate::{
+    capabilities, columns, config, cutoff, duration, mounts, owners, pattern, size, time_style,
+};
+use clap::Parser;
+use clap::builder::ArgPredicate;
+use clap_complete::Shell;
+use std::{
+    ffi::OsString,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about = "List directory contents as tables, trees, JSON, YAML or CSV",
+    long_about = "Best ls command ever.\n\n\
+        Lists a directory as a colored table by default, or as a tree, JSON, YAML, CSV or \
+        Markdown. Entries can be filtered by name, type, size, age and owner, sorted, and \
+        shown with the columns picked with --fields. Defaults can be set in \
+        $XDG_CONFIG_HOME/better-ls/config.toml.\n\n\
+        Exits with 0 on success, 1 when the listing is incomplete (entries that couldn't \
+        be read, --max-entries), 2 when nothing could be listed, 3 on --timeout and 4 \
+        when --stale-after found entries older than its duration.\n\n\
+        Options in $BETTER_LS_OPTS are read before those of the command line, which win \
+        when both give the same option. --no-all, --no-long and --sort undo the ones \
+        that don't take a value, such as -a, -l and -t. --no-config ignores the variable like the config file.",
+    args_override_self = true
+)]
+pub struct Cli {
+    #[arg(
+        value_name = "PATH",
+        help = "Directories to list, the current one by default",
+        long_help = "Directories to list, the current one by default.\n\n\
+            Several directories are listed one after the other, each under a header with \
+            its path and a count of its entries. --json and --yaml print an object keyed \
+            by path instead. Trees can be drawn for several directories too, the other \
+            modes take a single one.\n\n\
+            A path starting with a dash has to follow `--`, e.g. `best-ls -- -l`, \
+            or be written as ./-l."
+    )]
+    pub paths: Vec<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        help = "Print entries as JSON",
+        long_help = "Print entries as a JSON array.\n\n\
+            Without --long each entry has the short schema: \
+            name, e_type, len_bytes, allocated_bytes, modified, dev, ino.\n\
+            With --long each entry has the long schema: \
+            permissions, nlink, owner, name, e_type, len_bytes, allocated_bytes, modified, \
+            dev, ino.\n\
+            e_type is one of File, Dir, Symlink, Fifo, Socket, BlockDevice or CharDevice, \
+            symlinks are only reported as what they point to under --dereference, where \
+            dangling ones keep e_type Symlink and get an `error`.\n\
+            Entries whose metadata couldn't be read have e_type Unknown and an `error` \
+            with the reason, their other values are zero or empty.\n\
+            --json-schema v2 writes e_type in lowercase and adds numeric fields.\n\
+            Both also carry accessed, changed and created when the platform has them, \
+            and name_bytes for names that aren't valid UTF-8: their bytes with \\xNN escapes, \
+            while name has U+FFFD in place of the invalid parts.\n\
+            Timestamps are RFC 3339 with the local UTC offset (Z under --utc), \
+            whatever --time-style is set to.\n\
+            With --summary the array becomes the `entries` of an object that also \
+            has a `summary` with the directory and file counts and total_bytes."
+    )]
+    pub json: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "tree"],
+        help = "Print one compact JSON object per line as entries are read",
+        long_help = "Print one compact JSON object per line as entries are read.\n\n\
+            Objects use the same schema as --json (short or long depending on --long) \
+            and are written as soon as each entry is read, so the output can be piped \
+            into line based tools without waiting for the whole directory. \
+            --max-entries does not apply since nothing is buffered."
+    )]
+    pub json_lines: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "tree"],
+        help = "Print entries as CSV with a header row (ISO 8601 timestamps)",
+        long_help = "Print entries as CSV with a header row (ISO 8601 timestamps).\n\n\
+            Names double their backslashes and spell bytes that aren't UTF-8 as \\xNN, \
+            so every name can be decoded back to its bytes."
+    )]
+    pub csv: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "csv"],
+        help = "Print entries as YAML, using the same schema as --json"
+    )]
+    pub yaml: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = JsonSchema::V1,
+        help_heading = capabilities::FORMATS_HEADING,
+        help = "Schema of JSON and YAML entries",
+        long_help = "Schema of JSON and YAML entries.\n\n\
+            v1 is the schema described under --json. \
+            v2 writes e_type in lowercase (file, dir, symlink, ...) and adds fields for \
+            programs: modified_iso (RFC 3339) and modified_epoch (seconds since 1970) to \
+            every entry, and the numeric mode, uid and gid to long entries."
+    )]
+    pub json_schema: JsonSchema,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["csv", "yaml"],
+        help = "Print JSON on a single line instead of indented"
+    )]
+    pub compact: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "csv", "yaml", "tree"],
+        help = "Print a GitHub flavored Markdown table, without colors"
+    )]
+    pub markdown: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "csv", "yaml", "markdown", "tree"],
+        help = "Print only the names, each terminated by a NUL byte (for xargs -0)",
+        long_help = "Print only the names, each terminated by a NUL byte.\n\n\
+            The safe way to feed names to other programs, since no filename can contain \
+            NUL: `best-ls --print0 | xargs -0 rm --`. Pass `--` to the receiving command \
+            so names such as `-l` or `--json` aren't taken for options."
+    )]
+    pub print0: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "csv", "yaml", "markdown", "tree", "print0"],
+        help = "Print only the names, one per line, quoted for a POSIX shell",
+        long_help = "Print only the names, one per line, quoted for a POSIX shell.\n\n\
+            Every line evaluates back to the exact name in sh, bash or zsh, whatever \
+            spaces, quotes or newlines it contains. Names starting with a dash are still \
+            options to the command they end up in, so put `--` in front of them.\n\n\
+            A directory literally named like a flag is listed with `best-ls -- -l`."
+    )]
+    pub shell_quote: bool,
+
+    #[arg(
+        short = '1',
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json", "json_lines", "csv", "yaml", "markdown", "tree", "print0", "shell_quote"],
+        help = "Print only the names, one per line",
+        long_help = "Print only the names, one per line.\n\n\
+            Unless --sort asks for an order, names are written as soon as they are read, \
+            so `best-ls -1 /huge | head` returns right away. --max-entries does not apply \
+            then, since nothing is buffered."
+    )]
+    pub oneline: bool,
+
+    #[arg(
+        long,
+        help = "Show control characters in names as C escapes (\\n, \\x1b) instead of '?'"
+    )]
+    pub escape: bool,
+
+    #[arg(
+        long,
+        help = "Quote names with spaces or shell metacharacters in tables and trees, for copy and paste"
+    )]
+    pub quote_names: bool,
+
+    #[arg(
+        short,
+        long,
+        help_heading = capabilities::FILTERS_HEADING,
+        overrides_with = "no_all",
+        help = "Show hidden files, and . and .. in listings"
+    )]
+    pub all: bool,
+
+    #[arg(
+        short = 'A',
+        long,
+        help_heading = capabilities::FILTERS_HEADING,
+        overrides_with = "no_all",
+        help = "Show hidden files, but not . and .."
+    )]
+    pub almost_all: bool,
+
+    #[arg(
+        long,
+        overrides_with_all = ["all", "almost_all"],
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Hide hidden files again, undoing -a and -A"
+    )]
+    pub no_all: bool,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        value_parser = pattern::parse_regex,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries whose name matches the regular expression"
+    )]
+    pub regex: Option<NamePattern>,
+
+    #[arg(
+        long = "match",
+        value_name = "GLOB",
+        value_parser = pattern::parse_glob,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries whose name matches the glob (e.g. '*.rs')",
+        long_help = "Only list entries whose name matches the glob (e.g. '*.rs').\n\n\
+            Supports *, ? and [...] classes. With --tree directories are always \
+            shown so matches further down stay reachable, the same goes for --regex."
+    )]
+    pub name_match: Option<NamePattern>,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FILTERS_HEADING,
+        conflicts_with = "dirs_only",
+        help = "Only list files, symlinks included (--tree still shows directories, dimmed)"
+    )]
+    pub files_only: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list directories"
+    )]
+    pub dirs_only: bool,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = size::parse_size,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries of at least SIZE (e.g. 100M, 4Ki), directories only under --du"
+    )]
+    pub min_size: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = size::parse_size,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries of at most SIZE, directories only under --du"
+    )]
+    pub max_size: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_parser = cutoff::parse_cutoff,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries modified at or after WHEN: a duration back (30d) or a date (2024-01-01)",
+        long_help = "Only list entries modified at or after WHEN, a duration back (30d) or a \
+            date (2024-01-01) as with --older-than. --time compares another timestamp \
+            instead, --time accessed the last read."
+    )]
+    pub newer_than: Option<SystemTime>,
+
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_parser = cutoff::parse_cutoff,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries modified at or before WHEN, same forms as --newer-than",
+        long_help = "Only list entries modified at or before WHEN.\n\n\
+            WHEN is a duration back from now (90m, 12h, 30d, 1y), a date (2024-01-01, \
+            local midnight) or a timestamp (2024-01-01 13:37, 2024-01-01T13:37:00Z). \
+            Together with --newer-than only the window between both is listed. \
+            --time compares another timestamp instead, --time accessed the last read. \
+            With --tree directories are always shown."
+    )]
+    pub older_than: Option<SystemTime>,
+
+    #[arg(
+        long,
+        value_name = "USER",
+        value_parser = owners::parse_owner,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries owned by USER, a name or a uid"
+    )]
+    pub owner: Option<u32>,
+
+    #[arg(
+        long,
+        conflicts_with = "owner",
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries owned by the current user"
+    )]
+    pub mine: bool,
+
+    #[arg(
+        long,
+        value_name = "GROUP",
+        value_parser = owners::parse_group,
+        help_heading = capabilities::FILTERS_HEADING,
+        help = "Only list entries belonging to GROUP, a name or a gid"
+    )]
+    pub group: Option<u32>,
+
+    #[arg(
+        short,
+        long,
+        overrides_with = "no_long",
+        help = "Use a long listing format"
+    )]
+    pub long: bool,
+
+    #[arg(long, help = "Use the short listing format, undoing --long")]
+    pub no_long: bool,
+
+    #[arg(
+        short = 'L',
+        long,
+        help = "Show what symlinks point to instead of the links, and descend into linked directories",
+        long_help = "Show the type, size, permissions and times of what symlinks point to \
+            instead of the links themselves, like `ls -L`. Names stay the links' names, \
+            sorting and the size filters use the targets' sizes. Without it every column \
+            describes the link itself.\n\n\
+            Dangling symlinks are listed with an error and make the listing incomplete. \
+            Trees and -R descend into symlinked directories, except into ones that lead \
+            back to a directory already being listed. Bypasses --cache."
+    )]
+    pub dereference: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Show inode numbers, in front of each row or after tree names, those of the links for symlinks"
+    )]
+    pub inode: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Show owners as numeric uids instead of looking up their names"
+    )]
+    pub numeric_ids: bool,
+
+    #[arg(
+        short = 'F',
+        long,
+        help = "Append an indicator to names: / directories, * executables, @ symlinks, | FIFOs, = sockets"
+    )]
+    pub classify: bool,
+
+    #[arg(
+        short = 's',
+        long,
+        help = "Show the space allocated on disk, entries using less than half their size are marked sparse"
+    )]
+    pub allocated: bool,
+
+    #[arg(
+        long,
+        help = "Show what regular files contain, detected from their first bytes (opens every file)",
+        long_help = "Show what regular files contain in a Kind column and a `kind` field, \
+            detected from their first 512 bytes rather than their extension: ELF binary, \
+            scripts with their interpreter, images, archives, PDF, UTF-8 text, binary data \
+            or empty.\n\n\
+            Every file is opened to read them, so this is only done when asked for. Symlinks \
+            aren't followed and files that can't be opened have no kind."
+    )]
+    pub kind: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "ALGORITHM",
+        help = "Show a checksum of every regular file, shortened in tables unless --hash-full",
+        long_help = "Show a checksum of every regular file in a Hash column and a `hash` field.\n\n\
+            Files are read in chunks and hashed in parallel. Tables show the first 12 hex \
+            digits unless --hash-full is given, JSON and CSV always carry the whole digest. \
+            Directories and special files show `-`, files that can't be read `error`."
+    )]
+    pub hash: Option<HashAlgorithm>,
+
+    #[arg(
+        long,
+        help = "Show complete checksums in tables instead of their first 12 digits"
+    )]
+    pub hash_full: bool,
+
+    #[arg(
+        long,
+        help = "Show how many entries directories hold instead of their size, `empty` for none",
+        long_help = "Show how many entries directories hold instead of their size.\n\n\
+            Each directory is read once without descending, hidden entries only count \
+            with --all. Empty directories show a dimmed `empty`, in trees too, and \
+            directories that can't be read `?`. JSON gains an `entry_count` field."
+    )]
+    pub dir_counts: bool,
+
+    #[arg(
+        long,
+        help = "List the names of extended attributes under each entry of the long listing",
+        long_help = "List the names of extended attributes under each entry of the long \
+            listing, and as an `xattrs` array in JSON. Implies --long.\n\n\
+            The long listing always marks entries with an ACL by a `+` after the \
+            permissions and entries with other extended attributes by `@`, like ls. \
+            Only the names are read, never the values."
+    )]
+    pub xattrs: bool,
+
+    #[arg(
+        short = 'Z',
+        long,
+        help = "Show the SELinux security context of every entry in the long listing",
+        long_help = "Show the SELinux security context of every entry in a Context column \
+            of the long listing and a `context` field in JSON, e.g. \
+            `system_u:object_r:httpd_sys_content_t:s0`. Implies --long.\n\n\
+            The context is read from the `security.selinux` attribute of the entry itself, \
+            symlinks aren't followed. Entries without one, or on systems without SELinux, \
+            show `-`."
+    )]
+    pub context: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        help = "List files in a tree-like format",
+        long_help = "List files in a tree-like format.\n\n\
+            Combined with --json or --yaml the tree is printed as nested nodes, \
+            the root node carries the listed `path` and directories have `children`. \
+            With --long the permissions, owner, size and time of every node are aligned \
+            to the right of the tree, or the columns picked with --fields. --style ascii \
+            draws the branches with ASCII characters."
+    )]
+    pub tree: bool,
+
+    #[arg(
+        long,
+        help = "Show every entry as its path from the listed directory (e.g. src/main.rs), in all formats",
+        long_help = "Show every entry as its path from the listed directory, e.g. src/main.rs \
+            rather than main.rs, in every format so the output can be fed to other commands. \
+            JSON keeps the bare name and gains a `path` next to it. \
+            Combined with --absolute the paths are canonical absolute paths."
+    )]
+    pub full_path: bool,
+
+    #[arg(
+        long,
+        help = "Show the listed path as a canonical absolute path, also the paths of --full-path"
+    )]
+    pub absolute: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHICH",
+        default_value_t = TimeKind::Modified,
+        help = "Timestamp shown in the time column, used by --sort time, --newer-than and --older-than"
+    )]
+    pub time: TimeKind,
+
+    #[arg(
+        short = 't',
+        overrides_with_all = ["sort", "no_sort"],
+        help = "Sort by time, newest first (same as --sort time)"
+    )]
+    pub sort_by_time: bool,
+
+    #[arg(
+        long,
+        help = "Show and serialize timestamps in UTC instead of local time"
+    )]
+    pub utc: bool,
+
+    #[arg(
+        long,
+        value_name = "STYLE",
+        value_parser = time_style::parse_time_style,
+        help = "Timestamp format for tables and CSV: iso, long-iso, full-iso or +FORMAT (strftime)"
+    )]
+    pub time_style: Option<TimeStyle>,
+
+    #[arg(
+        long,
+        help = "Show times in tables as e.g. '35m ago', dates over ten years back stay absolute"
+    )]
+    pub relative_time: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = ColorChoice::Auto,
+        help = "Color the output, by default only on a terminal and when NO_COLOR isn't set",
+        long_help = "Color the output: always, never, or by default only when it goes to a \
+            terminal and NO_COLOR isn't set.\n\n\
+            Without colors tables, trees and messages on stderr are plain text, for pipes \
+            and files. stdout and stderr are checked separately. --hyperlink links are \
+            kept, they have a switch of their own."
+    )]
+    pub color: ColorChoice,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto",
+        help = "Show a nerd font icon in front of each name [default: auto, needs a terminal]"
+    )]
+    pub icons: Option<IconsMode>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto",
+        help = "Make names clickable file:// links in terminals that support OSC 8 [default: auto, needs a terminal]",
+        long_help = "Make names clickable in terminals that support OSC 8 hyperlinks, \
+            e.g. iTerm2, WezTerm, kitty or GNOME Terminal. Each name in tables, -1 and \
+            trees links to the absolute file:// URL of its entry, including the host \
+            name. JSON, YAML, CSV and Markdown never carry the links."
+    )]
+    pub hyperlink: Option<HyperlinkMode>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        default_value_t = PermissionStyle::Octal,
+        help = "How the permission column writes modes",
+        long_help = "How the permission column writes modes: octal like 755, or symbolic \
+            like ls -l's drwxr-xr-x, with setuid, setgid and sticky shown as s and t. \
+            Tables color symbolic modes character by character, like eza: the type in \
+            blue, read bits yellow, write bits red, execute bits green and unset bits \
+            dimmed. JSON, YAML and CSV get the same string without colors."
+    )]
+    pub permissions: PermissionStyle,
+
+    #[arg(
+        short = 'H',
+        long,
+        help = "Print sizes in human readable units (e.g. 4.2 MiB)"
+    )]
+    pub human_readable: bool,
+
+    #[arg(
+        long,
+        help = "Print sizes in powers of 1000 (e.g. 4.4 MB) rather than 1024",
+        long_help = "Print sizes in powers of 1000 with the units KB, MB, GB and so on, the \
+            way disk vendors and most web dashboards count, rather than -H's powers of 1024. \
+            Implies -H. Applies to the size column, --du sums, totals and summaries alike; \
+            JSON, YAML and CSV keep byte counts."
+    )]
+    pub si: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["human_readable", "si"],
+        help = "Print sizes as exact byte counts with thousands separators (e.g. 1,234,567)",
+        long_help = "Print sizes as exact byte counts with thousands separators, like \
+            1,234,567, everywhere sizes are shown: the size column, --du sums, totals and \
+            summaries. JSON, YAML and CSV keep plain numbers."
+    )]
+    pub bytes: bool,
+
+    #[arg(
+        long,
+        help = "Show the total size of each directory's contents instead of its own size",
+        long_help = "Show the total size of each directory's contents instead of its own size.\n\n\
+            Directories are walked recursively and in parallel, summing the sizes of \
+            everything below them. Symlinks count with their own size and aren't followed, \
+            entries that can't be read are skipped. The sums also replace `len_bytes` in \
+            JSON and decide the order of --sort size."
+    )]
+    pub du: bool,
+
+    #[arg(
+        short = 'x',
+        long,
+        help = "Don't descend into directories on other file systems",
+        long_help = "Don't descend into directories on other file systems, like du -x and \
+            find -xdev.\n\n\
+            Trees, -R, --find, --du, --diff and --duplicates stay on the device of the \
+            directory they started from. Mount points of other file systems are still \
+            listed, trees show them dimmed as `name [mounted]` and --du counts only their \
+            own size. Bind mounts of directories on the same device are entered."
+    )]
+    pub one_file_system: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        help = "List mounted filesystems and their disk usage",
+        long_help = "List mounted filesystems and their disk usage.\n\n\
+            Rows are ordered by mount point, --sort size puts the largest first and --sort \
+            none keeps the order of the mount table. --reverse turns either around."
+    )]
+    pub mounts: bool,
+
+    #[arg(
+        long,
+        requires = "mounts",
+        help = "Include pseudo filesystems (proc, sysfs, ...) in --mounts"
+    )]
+    pub all_mounts: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1_000_000,
+        help = "Stop reading a directory after N entries and exit with status 1 (0 disables)"
+    )]
+    pub max_entries: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        conflicts_with_all = ["json_lines", "tree"],
+        help = "Only show the first N entries after filtering and sorting, e.g. with --sort size (0 shows all)",
+        long_help = "Only show the first N entries after filtering and sorting, so \
+            `--limit 20 --sort size` shows the 20 largest. 0 shows all.\n\n\
+            Tables end with a dimmed line counting the entries left out, the summary \
+            still covers all of them. JSON and YAML arrays are cut short, --summary adds \
+            an `omitted` count next to the summary."
+    )]
+    pub limit: usize,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Show the N most recently modified entries above the table",
+        long_help = "Show the N most recently modified entries above the table, with their \
+            names, how long ago they changed and their sizes. --time picks another \
+            timestamp. The entries come from the listing itself, so --all and the filters \
+            apply; equal times are ordered by name.\n\n\
+            Only tables on a terminal get the section, and only when the directory has at \
+            least N entries."
+    )]
+    pub recent: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        conflicts_with_all = ["largest", "sort", "sort_by_time", "tree"],
+        help = "Show only the N most recently modified entries [default: 1]",
+        long_help = "Show only the N most recently modified entries, newest first, instead of \
+            the whole listing [default: 1]. --all and the filters apply, and with -R every \
+            directory below is searched, the entries then named by their paths. Any format \
+            works: -1 prints just the paths, --json an array of entries.\n\n\
+            N follows after a space or `=`, a directory named like a number is passed as \
+            ./N. Exits with status 1 and prints nothing when no entry is left to choose from."
+    )]
+    pub newest: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "1",
+        conflicts_with_all = ["sort", "sort_by_time", "tree"],
+        help = "Show only the N largest entries [default: 1]",
+        long_help = "Show only the N largest entries, largest first, instead of the whole \
+            listing [default: 1]. Directories count with their own size unless --du sums \
+            their contents. Otherwise like --newest."
+    )]
+    pub largest: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Print a JSON description of the supported columns, formats and filters"
+    )]
+    pub dump_capabilities: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "SHELL",
+        hide = true,
+        help = "Print the completion script for SHELL, e.g. `best-ls --generate-completions zsh > _best-ls`"
+    )]
+    pub generate_completions: Option<Shell>,
+
+    #[arg(
+        long,
+        hide = true,
+        help = "Print the man page as roff, e.g. `best-ls --generate-man > best-ls.1`"
+    )]
+    pub generate_man: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "use",
+        help = "Reuse cached entry metadata while a directory is unchanged",
+        long_help = "Reuse cached entry metadata while a directory is unchanged.\n\n\
+            Entries are cached per directory in $XDG_CACHE_HOME/better-ls (one file per user) \
+            and reused as long as the directory's own mtime and ctime are unchanged. \
+            Those only change when entries are added, removed or renamed, so a file \
+            rewritten in place keeps showing its old size and timestamp until the \
+            directory changes or the cache is refreshed. Meant for slow network \
+            filesystems where stat latency dominates.\n\n\
+            --cache=refresh rescans and rewrites the cached copy, \
+            --cache=clear deletes the cache file and lists without it."
+    )]
+    pub cache: Option<CacheMode>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "365d",
+        value_parser = duration::parse_duration,
+        help = "Show a Last Accessed column and mark entries not read within DURATION [default: 365d]",
+        long_help = "Show a Last Accessed column and mark entries not read within DURATION \
+            [default: 365d].\n\n\
+            --sort atime orders by the last read and --time accessed makes --older-than and \
+            --newer-than compare it, so `--unused --sort atime -r --time accessed --older-than \
+            1y` lists what nobody read in a year, the longest unread first."
+    )]
+    pub unused: Option<Duration>,
+
+    #[arg(
+        long,
+        help = "Show an Age column with the time since each entry was modified (e.g. 3h, 12d, 2y)",
+        long_help = "Show an Age column with the time since each entry was modified, in its \
+            largest whole unit: 45s, 3h, 12d, 2y. Modification times in the future are 0s. \
+            JSON gets the age in seconds as age_seconds."
+    )]
+    pub age: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "dereference",
+        help = "Show the size of each symlink itself followed by its target's, like 12 -> 4.2 MiB",
+        long_help = "Show the size of each symlink itself, the length of the path it holds, \
+            followed by the size of its target, like `12 -> 4209153`, with `?` when the link \
+            dangles. Unlike with -L the entry stays a symlink. --sort size and --largest go \
+            by the target's size. In JSON len_bytes is the link's own size and \
+            target_len_bytes its target's. Targets are read with the links anyway, to tell \
+            linked directories apart, so this costs no extra stat calls."
+    )]
+    pub link_sizes: bool,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = duration::parse_duration,
+        help = "Show the Age column, red for entries modified longer than DURATION ago, and exit with status 4 if any were",
+        long_help = "Show the Age column and mark entries modified longer than DURATION ago \
+            (e.g. 30d or 1y) in red. When any listed entry is that old the exit status is 4, \
+            for cron jobs to alert on, unless the listing failed or timed out, which keep \
+            their own statuses."
+    )]
+    pub stale_after: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "FIELDS",
+        value_delimiter = ',',
+        value_parser = columns::parse_field,
+        help = "Comma separated columns to show, in order (e.g. name,size,modified)"
+    )]
+    pub fields: Vec<Field>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "KEY",
+        default_value_t = SortKey::Name,
+        // -t and -U pick the key when they come last, each one overrides the others
+        default_value_ifs = [
+            ("sort_by_time", ArgPredicate::Equals("true".into()), Some("time")),
+            ("no_sort", ArgPredicate::Equals("true".into()), Some("none")),
+        ],
+        overrides_with_all = ["sort_by_time", "no_sort"],
+        help = "Order of the entries, ties are broken by name (not applied to --json-lines, which streams)"
+    )]
+    pub sort: SortKey,
+
+    #[arg(
+        short = 'U',
+        long,
+        overrides_with_all = ["sort", "sort_by_time"],
+        help = "Keep the order the filesystem returned, faster for huge directories (same as --sort none)"
+    )]
+    pub no_sort: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Reverse the order --sort gives, . and .. still come first"
+    )]
+    pub reverse: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CASE",
+        default_value_t = SortCase::Insensitive,
+        help = "Whether sorting by name tells uppercase from lowercase, for --sort and trees",
+        long_help = "Whether sorting by name tells uppercase from lowercase, for --sort and \
+            the order of trees.\n\n\
+            insensitive orders names like file managers do: case and accents are only \
+            looked at when names are otherwise the same, so `apple`, `Ärger` and `main.rs` \
+            come before `README.md`. sensitive orders by the bytes of names, uppercase first."
+    )]
+    pub sort_case: SortCase,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = TREE_MAX_DEPTH,
+        help = "How many levels deep --tree descends"
+    )]
+    pub depth: usize,
+
+    #[arg(
+        long,
+        help = "Descend into .git, node_modules, target and the other directories trees collapse"
+    )]
+    pub no_ignore: bool,
+
+    #[arg(
+        long,
+        help = "Don't leave out the entries .betterls-ignore files list",
+        long_help = "Don't leave out the entries .betterls-ignore files list.\n\n\
+            A .betterls-ignore file in a directory lists, in gitignore syntax, entries to \
+            leave out of that directory and everything below it: one glob per line, `#` \
+            comments, `!` in front to bring back what an earlier pattern left out and `/` \
+            at the end to only match directories. Patterns without a `/` match names at any \
+            depth, the others paths from the file's directory. Tables, trees, -R and --find \
+            all skip what the files list; those in subdirectories add to the ones above, \
+            and the deepest matching pattern wins. Files above the listed directory aren't \
+            read. Hidden entries still need --all."
+    )]
+    pub no_ignore_files: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        help = "Also collapse directories named NAME in trees, even with --no-ignore (comma separated or repeated)"
+    )]
+    pub exclude: Vec<String>,
+
+    /// Directories trees show as a single dimmed line, TREE_SKIPPED_DIRS unless
+    /// `tree_skip` in the config file replaces them.
+    #[arg(skip = TREE_SKIPPED_DIRS.iter().map(|name| name.to_string()).collect::<Vec<_>>())]
+    pub tree_skip: Vec<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = TableStyle::Rounded,
+        help = "Border style of tables, ascii also draws trees with ASCII characters"
+    )]
+    pub style: TableStyle,
+
+    #[arg(long, help = "Leave out the header row of tables and CSV output")]
+    pub no_header: bool,
+
+    #[arg(
+        long,
+        help = "Leave out the path above each listing of several directories or -R"
+    )]
+    pub no_header_path: bool,
+
+    #[arg(
+        long,
+        help = "Show names in full even when the table is wider than the terminal"
+    )]
+    pub no_truncate: bool,
+
+    #[arg(
+        long,
+        help = "Leave out the line with entry counts and total size under tables and trees"
+    )]
+    pub no_summary: bool,
+
+    #[arg(
+        long,
+        help = "Leave out the total line above long tables, with the size of the entries and the free space of their filesystem"
+    )]
+    pub no_total: bool,
+
+    #[arg(
+        long,
+        help = "Wrap --json and --yaml output as {entries, summary}, adding the counts and total size"
+    )]
+    pub summary: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json_lines", "csv", "markdown", "tree", "mounts"],
+        help = "Sum up the listing per owner: file and directory counts, total size and share"
+    )]
+    pub by_owner: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json_lines", "csv", "markdown", "tree", "mounts", "by_owner"],
+        help = "Sum up the files of the listing per extension: file count, total size and share",
+        long_help = "Sum up the files of the listing per extension: how many there are, their \
+            total size and its share of the size of all files, largest first.\n\n\
+            Extensions are compared in lowercase, files without one are counted as (none). \
+            Directories don't count, -R walks into them like it does for listings. --all, \
+            the excludes, ignore files and the size and time filters apply. --json and \
+            --yaml print an array of rows."
+    )]
+    pub by_extension: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json_lines", "csv", "markdown", "tree", "mounts", "by_owner", "by_extension", "changed"],
+        help = "Group files with identical contents and show how many bytes removing the copies frees",
+        long_help = "Group files with identical contents and show how many bytes removing the \
+            copies frees.\n\n\
+            Files are compared by size first, only those sharing their size with another \
+            file are hashed (sha256 unless --hash picks another algorithm). Names of the \
+            same inode are reported as hardlinks rather than duplicates, empty files are \
+            left out. -R also looks in subdirectories. --json prints an array of groups \
+            with their paths, size and hash."
+    )]
+    pub duplicates: bool,
+
+    #[arg(
+        short = 'R',
+        long,
+        help = "List subdirectories too, each under its own header, symlinked ones only with -L",
+        long_help = "List the subdirectories of the directory too, and theirs, each under \
+            a header with its path like with several directories. Symlinked directories \
+            are only entered with -L.\n\n\
+            --duplicates and --summary-only look at everything below the directory \
+            instead of only its entries."
+    )]
+    pub recursive: bool,
+
+    #[arg(
+        long,
+        help_heading = capabilities::FORMATS_HEADING,
+        conflicts_with_all = ["json_lines", "csv", "markdown", "tree", "mounts", "by_owner", "by_extension", "changed", "duplicates", "print0", "shell_quote", "oneline"],
+        help = "Print only the totals: counts, size and the largest and newest entry",
+        long_help = "Print only the totals of the listing: how many directories, files and \
+            symlinks it has, their total size and its largest and newest entry.\n\n\
+            Hidden entries only count with --all and every filter applies. Directories \
+            count with the size of their contents under --du, -R counts everything below \
+            the directory instead. --json and --yaml print a single object."
+    )]
+    pub summary_only: bool,
+
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help_heading = capabilities::FILTERS_HEADING,
+        conflicts_with_all = ["tree", "mounts", "by_owner", "by_extension", "changed", "duplicates", "summary_only"],
+        help = "List the entries below the directory whose names contain TEXT, down to --depth levels",
+        long_help = "List the entries below the directory whose names contain TEXT, with \
+            their paths from the listed directory, in the order of those paths.\n\n\
+            Case is ignored unless TEXT has uppercase letters. Subdirectories are searched \
+            down to --depth levels, hidden ones only with --all, symlinked ones only with \
+            -L. Every other filter and format applies to the matches. Matches are written \
+            as they are found with --json-lines, -1, --print0 and --shell-quote, unless \
+            --sort or --limit needs all of them first."
+    )]
+    pub find: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = duration::parse_duration,
+        help = "Stop reading after DURATION (e.g. 2s), print what was found and exit with status 3"
+    )]
+    pub timeout: Option<Duration>,
+
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["json", "json_lines", "csv", "yaml", "markdown", "print0", "shell_quote", "mounts", "changed", "timeout"],
+        help = "Redraw the table or tree whenever entries are created, removed or modified, until Ctrl-C",
+        long_help = "Redraw the table or tree whenever entries are created, removed or modified, \
+            until interrupted with Ctrl-C.\n\n\
+            The same filters, sorting and --depth apply as without --watch. Changes arriving \
+            in a burst cause a single redraw. Trees watch every directory below the listed one."
+    )]
+    pub watch: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        default_value_t = PagingMode::Auto,
+        help = "Show output taller than the terminal in $PAGER (less -RFX by default)",
+        long_help = "Show output taller than the terminal in $PAGER, `less -RFX` by default.\n\n\
+            auto pages only when stdout is a terminal and never pages JSON, YAML, CSV or \
+            NUL separated names, always pages any output. An empty $PAGER or `cat` turns \
+            paging off, as does a pager that can't be started."
+    )]
+    pub paging: PagingMode,
+
+    #[arg(
+        long,
+        help = "Ignore the config file ($XDG_CONFIG_HOME/better-ls/config.toml), for reproducible output"
+    )]
+    pub no_config: bool,
+
+    #[arg(
+        long,
+        visible_alias = "against",
+        value_name = "MANIFEST",
+        help = "Report what changed since a listing saved with --json",
+        long_help = "Report what changed since a listing saved with --json.\n\n\
+            Save a manifest with `best-ls --json DIR > manifest.json` and later run \
+            `best-ls --changed manifest.json DIR`. Entries are matched by name; an entry \
+            that vanished and one that appeared with the same device and inode are \
+            reported as `renamed: old -> new`. Sizes, modification times and inodes decide \
+            whether an entry was modified, those of symlinks themselves rather than their \
+            targets, so a retargeted link counts as modified."
+    )]
+    pub changed: Option<PathBuf>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["tree", "csv", "markdown", "json_lines", "changed", "summary_only", "duplicates", "find", "by_owner", "watch", "mounts"],
+        help = "Compare two directories given as PATHs, LEFT then RIGHT",
+        long_help = "Compare two directories given as PATHs, LEFT then RIGHT.\n\n\
+            Entries are matched by name and marked `-` when only LEFT has them, `+` when \
+            only RIGHT has them, `~` when they differ and `=` when they are alike. Files \
+            differ by size or modification time, or by size and contents under --hash. \
+            A file on one side and a directory on the other differ by type. \
+            -R also compares the directories both sides have, entry by entry; --all and \
+            the filters apply to both sides."
+    )]
+    pub diff: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["tree", "csv", "markdown", "json_lines", "changed", "summary_only", "duplicates", "find", "by_owner", "watch", "mounts", "diff", "recursive"],
+        help = "Describe each PATH in a block of labeled lines, like stat",
+        long_help = "Describe each PATH in a block of labeled lines, like stat.\n\n\
+            Shows the absolute path, type, symlink target, size and allocated size, \
+            permissions both symbolic and in octal, owner and group, hard links, inode, \
+            device, all four timestamps and the names of extended attributes. Timestamps \
+            follow --time-style, long-iso by default. With --json or --yaml each PATH is \
+            an object with all of these, several PATHs are keyed by path."
+    )]
+    pub stat: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["json", "yaml", "csv", "markdown", "json_lines", "changed", "summary_only", "duplicates", "find", "by_owner", "watch", "mounts", "diff", "stat"],
+        help = "Pick entries from an interactive list and print their paths",
+        long_help = "Pick entries from an interactive list and print their paths, e.g. \
+            `cd \"$(best-ls --pick --dirs-only)\"`.\n\n\
+            The list shows what the listing would, with --all, the filters and the sort \
+            order, or the whole tree under --tree. It is drawn on stderr, so stdout only \
+            gets the picked paths. The arrow keys, Ctrl-J and Ctrl-K move, and so do j and \
+            k until a filter is typed; any other key starts a fuzzy filter, as does `/`. \
+            Tab marks several entries. Enter prints the marked entries one per line, or the \
+            highlighted one, and exits 0; Esc exits 130 without printing anything."
+    )]
+    pub pick: bool,
+
+    #[arg(
+        long,
+        help = "List the members of archives given as PATH, whatever their names",
+        long_help = "List the members of archives given as PATH, whatever their names.\n\n\
+            PATHs ending in .zip, .tar, .tar.gz or .tgz are always listed by their members, \
+            with the names, types, sizes, permissions and times their headers record; \
+            --archive also recognizes other files as zip, tar or gzipped tar archives by \
+            their first bytes. Members are named by their paths inside the archive, and \
+            --tree nests them by those paths. Nothing is extracted."
+    )]
+    pub archive: bool,
+
+    #[arg(long, help = "Explain which config file settings were applied")]
+    pub verbose: bool,
+
+    #[arg(
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        conflicts_with = "verbose",
+        help = "Print only the data: no headers, totals, notes or warnings (-qq also hides errors)",
+        long_help = "Print only the data: no header row, path headers, total and summary \
+            lines, notes or warnings.\n\n\
+            Warnings still decide the exit status, e.g. a listing cut short by \
+            --max-entries exits with 1 and one cut short by --timeout with 3. \
+            Given twice (-qq) error messages are hidden as well."
+    )]
+    pub quiet: u8,
+
+    #[arg(skip)]
+    pub theme: config::Theme,
+}
+
+// Owner, permissions and links are only looked up when a column needs them
+pub fn needs_long_entries(cli: &Cli) -> bool {
+    cli.long
+        || cli.xattrs
+        || cli.context
+        || cli.fields.iter().any(|f| {
+            matches!(
+                f,
+                Field::Permissions | Field::Links | Field::Owner | Field::Context
+            )
+        })
+}
+
+/// Joins a number following --newest or --largest to it, `--largest 5` becoming
+/// `--largest=5`. Their count is optional, so clap alone would take the number for a path.
+pub fn attach_counts(args: Vec<OsString>) -> Vec<OsString> {
+    let mut joined: Vec<OsString> = Vec::with_capacity(args.len());
+    let mut options = true;
+    for arg in args {
+        let count = arg
+            .to_str()
+            .is_some_and(|arg| arg.bytes().all(|b| b.is_ascii_digit()) && !arg.is_empty());
+        match joined.last_mut() {
+            Some(last) if options && count && (last == "--newest" || last == "--largest") => {
+                last.push("=");
+                last.push(&arg);
+            }
+            _ => {
+                options &= arg != "--";
+                joined.push(arg);
+            }
+        }
+    }
+    joined
+}
+
+/// Turns the headers, summaries and totals off under -q, so only the data itself is left
+/// on stdout.
+pub fn apply_quiet(cli: &mut Cli) {
+    if cli.quiet > 0 {
+        cli.no_header = true;
+        cli.no_header_path = true;
+        cli.no_summary = true;
+        cli.no_total = true;
+    }
+}
+
+/// Formats read by other programs rather than people, which aren't paged by default.
+pub fn data_output(cli: &Cli) -> bool {
+    cli.json || cli.json_lines || cli.yaml || cli.csv || cli.print0 || cli.shell_quote
+}
+
+/// Whether each listing of several directories or -R gets its path above it, never for
+/// formats read by other programs or under -q.
+pub fn shows_path_headers(cli: &Cli) -> bool {
+    !(cli.no_header_path || cli.quiet > 0 || data_output(cli))
+}
+
+/// Whether entries starting with a dot are shown, under -a or -A.
+pub fn shows_hidden(cli: &Cli) -> bool {
+    cli.all || cli.almost_all
+}
+
+/// Whether names are wrapped in links, --hyperlink is settled to always or never by then.
+pub fn hyperlinks(cli: &Cli) -> bool {
+    cli.hyperlink == Some(HyperlinkMode::Always) && !cli.markdown
+}
+
+/// Whether entries carry their age, for --age, --stale-after or an age column in --fields.
+pub fn needs_ages(cli: &Cli) -> bool {
+    cli.age || cli.stale_after.is_some() || cli.fields.contains(&Field::Age)
+}
+
+/// Whether the contents of files are sniffed, for --kind or a kind column in --fields.
+pub fn needs_kinds(cli: &Cli) -> bool {
+    cli.kind || cli.fields.contains(&Field::Kind)
+}
+
+/// Whether security contexts are read, for -Z or a context column in --fields.
+pub fn needs_contexts(cli: &Cli) -> bool {
+    cli.context || cli.fields.contains(&Field::Context)
+}
+
+/// The checksum to compute, --fields hash without --hash means sha256.
+pub fn hash_algorithm(cli: &Cli) -> Option<HashAlgorithm> {
+    cli.hash.or_else(|| {
+        cli.fields
+            .contains(&Field::Hash)
+            .then_some(HashAlgorithm::Sha256)
+    })
+}
+
+/// The columns to show, either picked with --fields or the layout of the active format.
+pub fn active_fields(cli: &Cli) -> Vec<Field> {
+    if !cli.fields.is_empty() {
+        return cli.fields.clone();
+    }
+
+    let mut fields = if cli.long || cli.xattrs || cli.context {
+        columns::LONG_LAYOUT.to_vec()
+    } else {
+        columns::SHORT_LAYOUT.to_vec()
+    };
+    if cli.allocated {
+        fields.insert(0, Field::Allocated);
+    }
+    if cli.inode {
+        fields.insert(0, Field::Inode);
+    }
+    if cli.context {
+        let after_owner = fields
+            .iter()
+            .position(|&f| f == Field::Owner)
+            .map_or(0, |i| i + 1);
+        fields.insert(after_owner, Field::Context);
+    }
+    if cli.kind {
+        let after_type = fields
+            .iter()
+            .position(|&f| f == Field::Type)
+            .map_or(fields.len(), |i| i + 1);
+        fields.insert(after_type, Field::Kind);
+    }
+    if cli.unused.is_some() {
+        fields.push(Field::Accessed);
+    }
+    if cli.age || cli.stale_after.is_some() {
+        let after_modified = fields
+            .iter()
+            .position(|&f| f == Field::Modified)
+            .map_or(fields.len(), |i| i + 1);
+        fields.insert(after_modified, Field::Age);
+    }
+    if cli.hash.is_some() {
+        fields.push(Field::Hash);
+    }
+    fields
+}
+
+pub const TREE_MAX_DEPTH: usize = 3;
+
+// Dependencies, build output and VCS internals bury the structure of a project
+pub const TREE_SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "__pycache__", ".venv"];
+
+/// How sizes are written in tables, trees and summaries.
+pub fn size_style(cli: &Cli) -> SizeStyle {
+    if cli.bytes {
+        SizeStyle::Grouped
+    } else if cli.si {
+        SizeStyle::Si
+    } else if cli.human_readable {
+        SizeStyle::Binary
+    } else {
+        SizeStyle::Plain
+    }
+}
+
+/// Whether `raw`, a directory in `dir`, is on another file system than `dir` and is left
+/// alone under --one-file-system.
+pub fn mounted_elsewhere(dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+    cli.one_file_system && raw.is_real_dir() && mounts::other_device(dir, raw)
+}
+
+/// Whether a tree shows the directory `raw` collapsed instead of descending into it.
+pub fn skipped_in_tree(raw: &RawEntry, cli: &Cli) -> bool {
+    raw.is_real_dir()
+        && (cli.exclude.contains(&raw.name) || !cli.no_ignore && cli.tree_skip.contains(&raw.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, ValueEnum};
+
+    // Every long option users can see, hidden ones aren't completed
+    fn long_options() -> Vec<String> {
+        Cli::command()
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long().map(str::to_string))
+            .collect()
+    }
+
+    fn completions(shell: Shell) -> String {
+        let mut command = Cli::command();
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, "best-ls", &mut script);
+        String::from_utf8(script).unwrap()
+    }
+
+    #[test]
+    fn every_shell_completes_every_long_option() {
+        let options = long_options();
+        assert!(options.len() > 50);
+        for &shell in Shell::value_variants() {
+            let script = completions(shell);
+            assert!(script.contains("best-ls"), "{}", shell);
+            for option in &options {
+                assert!(
+                    script.contains(option.as_str()),
+                    "{} lacks --{}",
+                    shell,
+                    option
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn enum_values_are_completed() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            let script = completions(shell);
+            for value in ["insensitive", "rounded", "always", "powershell"] {
+                assert!(script.contains(value), "{} lacks {}", shell, value);
+            }
+        }
+    }
+
+    #[test]
+    fn the_man_page_has_every_long_option() {
+        let mut roff = Vec::new();
+        clap_mangen::Man::new(Cli::command())
+            .render(&mut roff)
+            .unwrap();
+        let roff = String::from_utf8(roff).unwrap();
+        assert!(roff.starts_with(".ie"), "{}", &roff[..40]);
+        assert!(roff.contains(".TH best-ls 1"));
+        for option in long_options() {
+            // roff escapes every dash
+            let spelled = format!("\\-\\-{}", option.replace('-', "\\-"));
+            assert!(roff.contains(&spelled), "the man page lacks --{}", option);
+        }
+    }
+
+    #[test]
+    fn every_option_is_explained() {
+        for arg in Cli::command()
+            .get_arguments()
+            .filter(|arg| !arg.is_hide_set())
+        {
+            let help = arg
+                .get_help()
+                .map(|help| help.to_string())
+                .unwrap_or_default();
+            assert!(!help.trim().is_empty(), "{} has no help", arg.get_id());
+        }
+    }
+
+    fn attached(args: &[&str]) -> Vec<String> {
+        attach_counts(args.iter().map(OsString::from).collect())
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn counts_after_a_space_belong_to_newest_and_largest() {
+        assert_eq!(attached(&["--largest", "2", "src"]), ["--largest=2", "src"]);
+        assert_eq!(attached(&["--newest", "10", "-1"]), ["--newest=10", "-1"]);
+        // No count, or not one, leaves the default of 1 and the path
+        assert_eq!(attached(&["--newest", "src"]), ["--newest", "src"]);
+        assert_eq!(attached(&["--newest"]), ["--newest"]);
+        assert_eq!(attached(&["--newest=3", "4"]), ["--newest=3", "4"]);
+        assert_eq!(attached(&["--depth", "2"]), ["--depth", "2"]);
+        // After `--` everything is a path
+        assert_eq!(
+            attached(&["--", "--largest", "2"]),
+            ["--", "--largest", "2"]
+        );
+
+        let cli = <Cli as Parser>::parse_from(attach_counts(
+            ["best-ls", "--largest", "2", "src"]
+                .map(OsString::from)
+                .to_vec(),
+        ));
+        assert_eq!(cli.largest, Some(2));
+        assert_eq!(cli.paths, [PathBuf::from("src")]);
+    }
+}
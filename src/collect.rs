@@ -0,0 +1,747 @@
+use crate::cache::CacheMode;
+use crate::cli::{
+    Cli, hash_algorithm, hyperlinks, needs_contexts, needs_kinds, needs_long_entries, shows_hidden,
+};
+use crate::entry::{EntryType, RawEntry};
+use crate::pattern::NamePattern;
+use crate::report::unreadable;
+use crate::summary::Summary;
+use crate::{
+    archive, cache, counts, deadline, du, failures, hash, hyperlink, ignore, kind, pool, sort,
+    xattrs,
+};
+use std::ops::ControlFlow;
+use std::{fs, path::Path};
+
+/// The prefix --full-path gives the names of entries listed from `dir`, canonicalized under
+/// --absolute.
+pub fn full_path_parent(dir: &Path, cli: &Cli) -> Option<String> {
+    if !cli.full_path {
+        return None;
+    }
+    let dir = if cli.absolute {
+        fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf())
+    } else {
+        dir.to_path_buf()
+    };
+    Some(format!("{}/", dir.to_string_lossy().trim_end_matches('/')))
+}
+
+pub fn name_patterns(cli: &Cli) -> impl Iterator<Item = &NamePattern> {
+    cli.regex.iter().chain(cli.name_match.iter())
+}
+
+pub fn name_matches(name: &str, cli: &Cli) -> bool {
+    name_patterns(cli).all(|pattern| pattern.is_match(name))
+}
+
+pub fn filters_metadata(cli: &Cli) -> bool {
+    cli.files_only
+        || cli.dirs_only
+        || cli.min_size.is_some()
+        || cli.max_size.is_some()
+        || cli.newer_than.is_some()
+        || cli.older_than.is_some()
+        || cli.owner.is_some()
+        || cli.group.is_some()
+}
+
+pub fn type_matches(is_dir: bool, cli: &Cli) -> bool {
+    !(cli.files_only && is_dir || cli.dirs_only && !is_dir)
+}
+
+pub fn ids_match(uid: u32, gid: u32, cli: &Cli) -> bool {
+    cli.owner.is_none_or(|owner| uid == owner) && cli.group.is_none_or(|group| gid == group)
+}
+
+// Compares the timestamp picked with --time, both bounds are inclusive and entries without
+// that timestamp never match one
+pub fn time_matches(raw: &RawEntry, cli: &Cli) -> bool {
+    if cli.newer_than.is_none() && cli.older_than.is_none() {
+        return true;
+    }
+    raw.time(cli.time).is_some_and(|at| {
+        cli.newer_than.is_none_or(|cutoff| at >= cutoff)
+            && cli.older_than.is_none_or(|cutoff| at <= cutoff)
+    })
+}
+
+// Directories only have a meaningful size under --du, otherwise they always pass
+pub fn size_matches(is_dir: bool, len_bytes: u64, cli: &Cli) -> bool {
+    if is_dir && !cli.du {
+        return true;
+    }
+    cli.min_size.is_none_or(|min| len_bytes >= min)
+        && cli.max_size.is_none_or(|max| len_bytes <= max)
+}
+
+/// Entries read from a directory, `truncated` is set when `--max-entries` stopped the read early.
+pub struct Listing<T> {
+    pub entries: Vec<T>,
+    pub summary: Summary,
+    pub truncated: bool,
+    /// Entries past --limit, they still count in the summary
+    pub omitted: usize,
+}
+
+/// Whether `raw` passes --all and the filters of the command line.
+pub fn is_listed(raw: &RawEntry, cli: &Cli) -> bool {
+    (shows_hidden(cli) || !raw.name.starts_with('.'))
+        && name_matches(&raw.name, cli)
+        && type_matches(raw.is_real_dir(), cli)
+        && time_matches(raw, cli)
+        && ids_match(raw.uid, raw.gid, cli)
+        // Under --du directories are compared once their totals are known
+        && (raw.is_real_dir() && cli.du || size_matches(raw.is_real_dir(), raw.len, cli))
+        // Nothing is known to match the filters that look at metadata
+        && !(raw.error.is_some() && filters_metadata(cli))
+}
+
+/// Reads the visible entries of `path` one at a time, handing each mapped entry to `f`
+/// until the directory is exhausted or `f` breaks.
+pub fn walk_entries<T>(
+    path: &Path,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+    mut f: impl FnMut(T) -> ControlFlow<()>,
+) {
+    let parent = full_path_parent(path, cli);
+    let mut visit = |mut raw: RawEntry| {
+        if !is_listed(&raw, cli) || ignore::ignores(path, &raw, cli) {
+            return ControlFlow::Continue(());
+        }
+        if raw.error.is_some() {
+            failures::record_entry();
+        }
+        raw.path = parent
+            .as_ref()
+            .map(|parent| format!("{}{}", parent, raw.name));
+        f(map(raw, cli))
+    };
+    if archive::opens(path, cli) {
+        return read_members(path, cli, visit);
+    }
+    for raw in dot_entries(path, cli) {
+        if visit(raw).is_break() {
+            return;
+        }
+    }
+
+    match cli.cache {
+        // Cached entries are the links themselves
+        Some(CacheMode::Use | CacheMode::Refresh) if !cli.dereference => {
+            walk_cached(path, cli, visit)
+        }
+        _ => read_raw_entries(path, cli, visit),
+    }
+}
+
+// `.` and `..` under -a, with the metadata of the directory itself and of its parent.
+// They never go into the cache.
+fn dot_entries(path: &Path, cli: &Cli) -> Vec<RawEntry> {
+    if !cli.all {
+        return Vec::new();
+    }
+    [".", ".."]
+        .into_iter()
+        .filter_map(|name| {
+            let path = path.join(name);
+            let meta = fs::symlink_metadata(&path).ok()?;
+            let mut raw = RawEntry::from_metadata(&path, name.to_string(), meta);
+            Probes::of(cli).probe(&path, &mut raw);
+            Some(raw)
+        })
+        .collect()
+}
+
+// The members of the archive at `path` take the place of a directory's entries
+fn read_members(path: &Path, cli: &Cli, mut f: impl FnMut(RawEntry) -> ControlFlow<()>) {
+    let members = match archive::members(path, cli) {
+        Ok(members) => members,
+        Err(e) => return unreadable(cli, path, &e),
+    };
+    for raw in members {
+        if deadline::expired() || f(raw).is_break() {
+            break;
+        }
+    }
+}
+
+// Directories that can't be read are reported and left out, entries whose metadata can't
+// be read are still listed by name
+fn read_raw_entries(path: &Path, cli: &Cli, mut f: impl FnMut(RawEntry) -> ControlFlow<()>) {
+    let mut read_dir = match fs::read_dir(path) {
+        Ok(read_dir) => read_dir,
+        Err(e) => return unreadable(cli, path, &e),
+    };
+    // Read in batches, so streamed output starts before a huge directory was read
+    // to the end and stops reading once nobody wants more
+    let mut files = Vec::with_capacity(PARALLEL_MIN_ENTRIES);
+    let mut exhausted = false;
+    while !exhausted {
+        files.clear();
+        while files.len() < PARALLEL_MIN_ENTRIES {
+            match read_dir.next() {
+                _ if deadline::expired() => {
+                    exhausted = true;
+                    break;
+                }
+                Some(Ok(file)) => files.push(file),
+                Some(Err(e)) => unreadable(cli, path, &e),
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            }
+        }
+        if stat_entries(&files, Probes::of(cli), &mut f).is_break() {
+            return;
+        }
+    }
+}
+
+/// What is read about every entry besides its metadata, each costs another system call.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Probes {
+    /// The targets of symlinks, for --dereference
+    pub dereference: bool,
+    /// The first bytes of regular files, for --kind
+    pub kinds: bool,
+    /// The names of extended attributes, for the permission markers and --xattrs
+    pub xattrs: bool,
+    /// The SELinux context, for -Z
+    pub contexts: bool,
+    /// The file:// URL names link to, for --hyperlink
+    pub urls: bool,
+    /// The sizes of symlink targets, for --link-sizes
+    pub link_sizes: bool,
+}
+
+impl Probes {
+    pub fn of(cli: &Cli) -> Probes {
+        Probes {
+            dereference: cli.dereference,
+            kinds: needs_kinds(cli),
+            xattrs: needs_long_entries(cli),
+            contexts: needs_contexts(cli),
+            urls: hyperlinks(cli),
+            link_sizes: cli.link_sizes,
+        }
+    }
+
+    /// Fills in what was asked for of `raw`, which lives at `path`.
+    pub fn probe(self, path: &Path, raw: &mut RawEntry) {
+        if self.dereference {
+            raw.dereference(path);
+        }
+        if self.urls {
+            raw.url = hyperlink::file_url(path);
+        }
+        if self.link_sizes {
+            raw.read_target_len(path);
+        }
+        if raw.error.is_some() {
+            return;
+        }
+        if self.kinds && raw.e_type == EntryType::File {
+            raw.kind = kind::detect(path);
+        }
+        if self.xattrs {
+            raw.xattrs = xattrs::names(path);
+        }
+        if self.contexts {
+            raw.context = xattrs::selinux_context(path);
+        }
+    }
+
+    /// Probes `entries` of `dir` in parallel, for entries that were read without, e.g.
+    /// from the cache.
+    pub fn apply(self, dir: &Path, entries: &mut [RawEntry]) {
+        if !(self.kinds || self.xattrs || self.contexts || self.urls || self.link_sizes) {
+            return;
+        }
+        let probed = pool::map(entries, |raw| {
+            let mut raw = raw.clone();
+            self.probe(&raw.path_in(dir), &mut raw);
+            (raw.kind, raw.xattrs, raw.context, raw.url, raw.target_len)
+        });
+        for (raw, probed) in entries.iter_mut().zip(probed) {
+            if let Some((kind, xattrs, context, url, target_len)) = probed {
+                raw.kind = kind;
+                raw.xattrs = xattrs;
+                raw.context = context;
+                raw.url = url;
+                raw.target_len = target_len;
+            }
+        }
+    }
+}
+
+// Threads only pay for their startup once there are plenty of stat calls to spread out
+const PARALLEL_MIN_ENTRIES: usize = 1000;
+
+/// Reads the metadata of `files`, in parallel for big directories, and hands the entries
+/// to `f` in directory order until it breaks. Whatever `probes` asks for is read in the
+/// same pass. Sorting happens later, so the output doesn't
+/// depend on which thread finished first.
+pub fn stat_entries(
+    files: &[fs::DirEntry],
+    probes: Probes,
+    mut f: impl FnMut(RawEntry) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    let stat = |file: &fs::DirEntry| {
+        let mut raw =
+            RawEntry::from_dir_entry(file).unwrap_or_else(|e| RawEntry::unreadable(file, &e));
+        probes.probe(&file.path(), &mut raw);
+        raw
+    };
+    if files.len() < PARALLEL_MIN_ENTRIES {
+        for file in files {
+            if deadline::expired() {
+                return ControlFlow::Break(());
+            }
+            f(stat(file))?;
+        }
+        return ControlFlow::Continue(());
+    }
+    for raw in pool::map(files, stat) {
+        // Entries nobody got to before the deadline end the listing
+        let Some(raw) = raw else {
+            return ControlFlow::Break(());
+        };
+        f(raw)?;
+    }
+    ControlFlow::Continue(())
+}
+
+// Serves the listing from the metadata cache when the directory hasn't changed,
+// otherwise reads it from disk and refreshes the cached copy
+fn walk_cached(path: &Path, cli: &Cli, f: impl FnMut(RawEntry) -> ControlFlow<()>) {
+    let Some(stamp) = cache::DirStamp::of(path) else {
+        return read_raw_entries(path, cli, f);
+    };
+    let mut dir_cache = cache::DirCache::open();
+    if walk_through(&mut dir_cache, path, stamp, cli, f) {
+        let _ = dir_cache.save();
+    }
+}
+
+// `walk_cached` with the cache given, true when the listing was read and stored in it
+fn walk_through(
+    dir_cache: &mut cache::DirCache,
+    path: &Path,
+    stamp: cache::DirStamp,
+    cli: &Cli,
+    mut f: impl FnMut(RawEntry) -> ControlFlow<()>,
+) -> bool {
+    if cli.cache == Some(CacheMode::Use)
+        && let Some(entries) = dir_cache.lookup(path, &stamp)
+    {
+        let mut entries = entries.to_vec();
+        Probes::of(cli).apply(path, &mut entries);
+        for raw in entries {
+            if f(raw).is_break() {
+                break;
+            }
+        }
+        return false;
+    }
+
+    let mut entries = Vec::new();
+    let mut complete = true;
+    read_raw_entries(path, cli, |raw| {
+        // Partial listings are never cached
+        if max_entries_reached(entries.len(), cli) {
+            complete = false;
+            return ControlFlow::Break(());
+        }
+        entries.push(raw);
+        ControlFlow::Continue(())
+    });
+
+    let stored = complete && !deadline::hit() && !failures::any();
+    if stored {
+        dir_cache.store(path, stamp, entries.clone());
+    }
+
+    for raw in entries {
+        if f(raw).is_break() {
+            break;
+        }
+    }
+    stored
+}
+
+pub fn collect_entries<T>(path: &Path, cli: &Cli, map: fn(RawEntry, &Cli) -> T) -> Listing<T> {
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    walk_entries(
+        path,
+        cli,
+        |raw, _| raw,
+        |raw| {
+            if max_entries_reached(entries.len(), cli) {
+                truncated = true;
+                return ControlFlow::Break(());
+            }
+            entries.push(raw);
+            ControlFlow::Continue(())
+        },
+    );
+
+    // Members of an archive aren't on disk to be measured, counted or hashed
+    if archive::opens(path, cli) {
+        return finish_listing(entries, truncated, cli, map);
+    }
+    if cli.du {
+        du::apply(path, &mut entries, cli.one_file_system);
+        entries.retain(|raw| size_matches(raw.is_real_dir(), raw.len, cli));
+    }
+    if cli.dir_counts {
+        counts::apply(path, &mut entries, shows_hidden(cli));
+    }
+    if let Some(algorithm) = hash_algorithm(cli) {
+        hash::apply(path, algorithm, &mut entries);
+    }
+    finish_listing(entries, truncated, cli, map)
+}
+
+/// Sorts `entries`, totals them and cuts them short at --limit, `truncated` is passed on.
+pub fn finish_listing<T>(
+    mut entries: Vec<RawEntry>,
+    truncated: bool,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+) -> Listing<T> {
+    sort::sort_entries(&mut entries, cli.sort, cli.time, cli.sort_case);
+    if cli.reverse {
+        sort::reverse_entries(&mut entries);
+    }
+    let mut summary = Summary::default();
+    for raw in entries.iter().filter(|raw| !raw.is_dot_entry()) {
+        summary.add(raw.is_real_dir(), raw.len);
+    }
+    let mut omitted = 0;
+    if cli.limit != 0 && entries.len() > cli.limit {
+        omitted = entries.len() - cli.limit;
+        entries.truncate(cli.limit);
+    }
+    Listing {
+        entries: entries.into_iter().map(|raw| map(raw, cli)).collect(),
+        summary,
+        truncated,
+        omitted,
+    }
+}
+
+fn max_entries_reached(count: usize, cli: &Cli) -> bool {
+    cli.max_entries != 0 && count >= cli.max_entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, cli, output};
+    use crate::render;
+    use std::time::SystemTime;
+
+    fn names(listing: &Listing<RawEntry>) -> Vec<&str> {
+        listing
+            .entries
+            .iter()
+            .map(|raw| raw.name.as_str())
+            .collect()
+    }
+
+    fn fixture() -> Fixture {
+        let fixture = Fixture::new();
+        fixture
+            .file("notes.txt", b"some notes")
+            .file("Cargo.toml", b"[package]\n")
+            .file(".env", b"KEY=1\n")
+            .dir("src");
+        fixture
+    }
+
+    #[test]
+    fn hidden_entries_only_with_all() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&[]), |raw, _| raw);
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt", "src"]);
+
+        let listing = collect_entries(fixture.path(), &cli(&["-A"]), |raw, _| raw);
+        assert_eq!(names(&listing), [".env", "Cargo.toml", "notes.txt", "src"]);
+    }
+
+    #[test]
+    fn summary_counts_what_is_listed() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&["--files-only"]), |raw, _| raw);
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt"]);
+        assert_eq!(listing.summary.files, 2);
+        assert_eq!(listing.summary.directories, 0);
+        assert_eq!(listing.summary.total_bytes, 20);
+        assert!(!listing.truncated);
+    }
+
+    #[test]
+    fn entries_are_mapped_after_filtering() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&["--dirs-only"]), |raw, cli| {
+            (raw.name, cli.dirs_only)
+        });
+        assert_eq!(listing.entries, [("src".to_string(), true)]);
+    }
+
+    #[test]
+    fn walking_stops_when_asked_to() {
+        let fixture = fixture();
+        let mut seen = 0;
+        walk_entries(
+            fixture.path(),
+            &cli(&[]),
+            |raw, _| raw,
+            |_| {
+                seen += 1;
+                ControlFlow::Break(())
+            },
+        );
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    fn a_missing_directory_lists_nothing() {
+        let fixture = fixture();
+        let listing = collect_entries(&fixture.join("gone"), &cli(&["-qq"]), |raw, _| raw);
+        assert!(listing.entries.is_empty());
+        assert!(failures::any());
+    }
+
+    #[test]
+    fn size_filters_spare_directories() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&["--min-size", "11"]), |raw, _| raw);
+        assert_eq!(names(&listing), ["src"]);
+        let listing = collect_entries(fixture.path(), &cli(&["--max-size", "10"]), |raw, _| raw);
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt", "src"]);
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--min-size", "10", "--max-size", "10"]),
+            |raw, _| raw,
+        );
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt", "src"]);
+    }
+
+    #[test]
+    fn under_du_directories_are_filtered_by_their_total() {
+        let fixture = fixture();
+        fixture.file("src/main.rs", b"fn main() {}\n").dir("src");
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--du", "--max-size", "10"]),
+            |raw, _| raw,
+        );
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt"]);
+    }
+
+    #[test]
+    fn time_windows_are_inclusive() {
+        let fixture = fixture();
+        fixture.touch("notes.txt", SystemTime::now());
+        let at = "2023-11-14T22:13:20Z";
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--newer-than", at, "--older-than", at]),
+            |raw, _| raw,
+        );
+        assert_eq!(names(&listing), ["Cargo.toml", "src"]);
+
+        let listing = collect_entries(fixture.path(), &cli(&["--newer-than", "1h"]), |raw, _| raw);
+        assert_eq!(names(&listing), ["notes.txt"]);
+    }
+
+    #[test]
+    fn time_windows_follow_the_time_picked() {
+        let fixture = fixture();
+        // A new file counts as read when it was created
+        let unread = fs::FileTimes::new().set_accessed(crate::fixture::mtime());
+        fs::File::open(fixture.join("Cargo.toml"))
+            .and_then(|file| file.set_times(unread))
+            .unwrap();
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--files-only", "--time", "accessed", "--older-than", "1d"]),
+            |raw, _| raw,
+        );
+        assert_eq!(names(&listing), ["Cargo.toml"]);
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--files-only", "--older-than", "1d"]),
+            |raw, _| raw,
+        );
+        assert_eq!(names(&listing), ["Cargo.toml", "notes.txt"]);
+    }
+
+    #[test]
+    fn an_empty_window_lists_nothing() {
+        let fixture = fixture();
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--newer-than", "2024-01-01", "--older-than", "2023-01-01"]),
+            |raw, _| raw,
+        );
+        assert!(listing.entries.is_empty());
+        assert_eq!(listing.summary.files, 0);
+    }
+
+    #[test]
+    fn max_entries_stops_the_read() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&["--max-entries", "2"]), |raw, _| raw);
+        assert_eq!(listing.entries.len(), 2);
+        assert_eq!(listing.summary.files + listing.summary.directories, 2);
+        assert!(listing.truncated);
+
+        let listing = collect_entries(fixture.path(), &cli(&["--max-entries", "3"]), |raw, _| raw);
+        assert_eq!(listing.entries.len(), 3);
+        assert!(!listing.truncated);
+    }
+
+    #[test]
+    fn max_entries_zero_reads_everything() {
+        let fixture = fixture();
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["-A", "--max-entries", "0"]),
+            |raw, _| raw,
+        );
+        assert_eq!(listing.entries.len(), 4);
+        assert!(!listing.truncated);
+    }
+
+    #[test]
+    fn limit_is_not_truncation() {
+        let fixture = fixture();
+        let listing = collect_entries(fixture.path(), &cli(&["--limit", "1"]), |raw, _| raw);
+        assert_eq!(names(&listing), ["Cargo.toml"]);
+        assert_eq!(listing.omitted, 2);
+        assert_eq!(listing.summary.files, 2);
+        assert!(!listing.truncated);
+    }
+
+    fn summary_only(path: &Path, args: &[&str]) -> serde_json::Value {
+        let args: Vec<_> = ["--summary-only", "--json", "--compact"]
+            .iter()
+            .chain(args)
+            .copied()
+            .collect();
+        let json = output(|out| render::print_summary_only(out, path, &cli(&args)).map(drop));
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn summary_only_counts_hidden_entries_with_all() {
+        let fixture = fixture();
+        fixture.file(".cache/blob", &[0; 100]).dir(".cache");
+
+        let summary = summary_only(fixture.path(), &[]);
+        assert_eq!(summary["directories"], 1);
+        assert_eq!(summary["files"], 2);
+        assert_eq!(summary["largest"]["path"], "Cargo.toml");
+
+        let summary = summary_only(fixture.path(), &["-A"]);
+        assert_eq!(summary["directories"], 2);
+        assert_eq!(summary["files"], 3);
+        assert_eq!(summary["largest"]["path"], "Cargo.toml");
+
+        // Below a hidden directory only --all looks, however deep -R goes
+        let summary = summary_only(fixture.path(), &["-R"]);
+        assert_eq!(summary["directories"], 1);
+        assert_eq!(summary["files"], 2);
+        let summary = summary_only(fixture.path(), &["-R", "-A"]);
+        assert_eq!(summary["directories"], 2);
+        assert_eq!(summary["files"], 4);
+        assert_eq!(summary["largest"]["path"], ".cache/blob");
+    }
+
+    #[test]
+    fn summary_only_applies_the_filters_to_hidden_entries() {
+        let fixture = fixture();
+        let summary = summary_only(fixture.path(), &["-A", "--files-only", "--match", ".*"]);
+        assert_eq!(summary["directories"], 0);
+        assert_eq!(summary["files"], 1);
+        assert_eq!(summary["total_bytes"], 6);
+        assert_eq!(summary["largest"]["path"], ".env");
+    }
+
+    #[test]
+    #[ignore = "creates 200 000 files"]
+    fn max_entries_stops_a_huge_directory() {
+        let fixture = Fixture::new();
+        for i in 0..200_000 {
+            fs::File::create(fixture.join(&format!("{:06}", i))).unwrap();
+        }
+        let listing = collect_entries(
+            fixture.path(),
+            &cli(&["--max-entries", "150000", "--sort", "none"]),
+            |raw, _| raw,
+        );
+        assert_eq!(listing.entries.len(), 150_000);
+        assert!(listing.truncated);
+    }
+
+    // Stats made by this thread while `walk` runs, and the names it was handed
+    fn counted(
+        walk: impl FnOnce(&mut dyn FnMut(RawEntry) -> ControlFlow<()>),
+    ) -> (usize, Vec<String>) {
+        let before = crate::entry::STATS.with(|stats| stats.get());
+        let mut names = Vec::new();
+        walk(&mut |raw| {
+            names.push(raw.name);
+            ControlFlow::Continue(())
+        });
+        names.sort();
+        (
+            crate::entry::STATS.with(|stats| stats.get()) - before,
+            names,
+        )
+    }
+
+    #[test]
+    fn cache_hits_stat_no_entries() {
+        let fixture = fixture();
+        let stamp = cache::DirStamp::of(fixture.path()).unwrap();
+        let mut dir_cache = cache::DirCache::empty();
+        let cli = cli(&["--cache"]);
+
+        // Whether a walk stores its listing depends on the failures of every test, so the
+        // cache is filled here
+        let (read, listed) = counted(|f| read_raw_entries(fixture.path(), &cli, f));
+        assert_eq!(read, listed.len());
+        let mut entries = Vec::new();
+        read_raw_entries(fixture.path(), &cli, |raw| {
+            entries.push(raw);
+            ControlFlow::Continue(())
+        });
+        dir_cache.store(fixture.path(), stamp, entries);
+
+        let (hit, cached) = counted(|f| {
+            assert!(!walk_through(
+                &mut dir_cache,
+                fixture.path(),
+                stamp,
+                &cli,
+                f
+            ));
+        });
+        assert_eq!(hit, 0);
+        assert_eq!(cached, listed);
+
+        // Refreshing reads everything again
+        let refresh = crate::fixture::cli(&["--cache=refresh"]);
+        let (again, _) = counted(|f| {
+            walk_through(&mut dir_cache, fixture.path(), stamp, &refresh, f);
+        });
+        assert_eq!(again, listed.len());
+    }
+}
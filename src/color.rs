@@ -0,0 +1,114 @@
+use clap::ValueEnum;
+use std::env;
+use std::fmt::Display;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const ESC: u8 = 0x1b;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Never,
+    /// Only when the output is a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+}
+
+impl ColorChoice {
+    /// Whether colors are written to a stream, `terminal` telling whether it is one.
+    pub fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Never => false,
+            ColorChoice::Auto => terminal && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+            ColorChoice::Always => true,
+        }
+    }
+}
+
+// Messages keep their colors unless `settle` decided otherwise
+static STDERR_COLORS: AtomicBool = AtomicBool::new(true);
+
+/// Decides for the whole run whether stdout and stderr get colors, returns it for stdout.
+/// owo-colors' own detection is overridden to agree.
+pub fn settle(choice: ColorChoice) -> bool {
+    let stdout = choice.enabled(io::stdout().is_terminal());
+    owo_colors::set_override(stdout);
+    STDERR_COLORS.store(
+        choice.enabled(io::stderr().is_terminal()),
+        Ordering::Relaxed,
+    );
+    stdout
+}
+
+/// `message` the way stderr gets it, plain unless it is colored.
+pub fn for_stderr(message: impl Display) -> String {
+    let message = message.to_string();
+    if STDERR_COLORS.load(Ordering::Relaxed) {
+        return message;
+    }
+    let mut plain = Plain::new(Vec::with_capacity(message.len()), true);
+    let _ = plain.write_all(message.as_bytes());
+    String::from_utf8_lossy(&plain.into_inner()).to_string()
+}
+
+/// Passes what is written on to `inner`, without the color and style escapes
+/// (`ESC [ … m`) when `strip` is set. Other escapes, like the links of --hyperlink, go
+/// through, and a sequence split between two writes is still recognized.
+pub struct Plain<W: Write> {
+    inner: W,
+    strip: bool,
+    // The start of an escape sequence the last write didn't finish
+    pending: Vec<u8>,
+}
+
+impl<W: Write> Plain<W> {
+    pub fn new(inner: W, strip: bool) -> Plain<W> {
+        Plain {
+            inner,
+            strip,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for Plain<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.strip {
+            return self.inner.write(buf);
+        }
+        let mut kept = Vec::with_capacity(buf.len());
+        for &byte in buf {
+            if self.pending.is_empty() {
+                if byte == ESC {
+                    self.pending.push(byte);
+                } else {
+                    kept.push(byte);
+                }
+                continue;
+            }
+            self.pending.push(byte);
+            match self.pending.as_slice() {
+                [ESC, b'['] => {}
+                // Parameters and intermediates run until a final byte
+                [ESC, b'[', .., last] if (0x40..=0x7e).contains(last) => {
+                    if *last != b'm' {
+                        kept.extend_from_slice(&self.pending);
+                    }
+                    self.pending.clear();
+                }
+                [ESC, b'[', ..] => {}
+                _ => kept.append(&mut self.pending),
+            }
+        }
+        self.inner.write_all(&kept)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
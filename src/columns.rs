@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use strum::{Display, EnumString};
+use tabled::settings::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, EnumString, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    Inode,
+    Permissions,
+    Links,
+    Owner,
+    Context,
+    Name,
+    Type,
+    Kind,
+    Size,
+    Allocated,
+    Modified,
+    Accessed,
+    Age,
+    Hash,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnType {
+    String,
+    Integer,
+    Timestamp,
+    Enum,
+}
+
+/// A column that can appear in table, CSV and JSON output.
+#[derive(Debug, Serialize)]
+pub struct ColumnSpec {
+    #[serde(rename = "id")]
+    pub field: Field,
+    pub label: &'static str,
+    #[serde(rename = "type")]
+    pub kind: ColumnType,
+    pub json_key: &'static str,
+    #[serde(skip)]
+    pub min_width: usize,
+    #[serde(skip)]
+    pub right_aligned: bool,
+    #[serde(skip)]
+    pub color: Color,
+}
+
+pub const COLUMNS: &[ColumnSpec] = &[
+    ColumnSpec {
+        field: Field::Inode,
+        label: "Inode",
+        kind: ColumnType::Integer,
+        json_key: "ino",
+        min_width: 8,
+        right_aligned: true,
+        color: Color::FG_BRIGHT_BLACK,
+    },
+    ColumnSpec {
+        field: Field::Permissions,
+        label: "Permission",
+        kind: ColumnType::String,
+        json_key: "permissions",
+        min_width: 12,
+        right_aligned: false,
+        color: Color::FG_BRIGHT_YELLOW,
+    },
+    ColumnSpec {
+        field: Field::Links,
+        label: "Links",
+        kind: ColumnType::Integer,
+        json_key: "nlink",
+        min_width: 5,
+        right_aligned: true,
+        color: Color::FG_WHITE,
+    },
+    ColumnSpec {
+        field: Field::Owner,
+        label: "Owner",
+        kind: ColumnType::String,
+        json_key: "owner",
+        min_width: 12,
+        right_aligned: false,
+        color: Color::FG_BRIGHT_WHITE,
+    },
+    ColumnSpec {
+        field: Field::Context,
+        label: "Context",
+        kind: ColumnType::String,
+        json_key: "context",
+        min_width: 20,
+        right_aligned: false,
+        color: Color::FG_YELLOW,
+    },
+    ColumnSpec {
+        field: Field::Name,
+        label: "Name",
+        kind: ColumnType::String,
+        json_key: "name",
+        min_width: 15,
+        right_aligned: false,
+        color: Color::FG_BRIGHT_CYAN,
+    },
+    ColumnSpec {
+        field: Field::Type,
+        label: "Type",
+        kind: ColumnType::Enum,
+        json_key: "e_type",
+        min_width: 6,
+        right_aligned: false,
+        color: Color::FG_WHITE,
+    },
+    ColumnSpec {
+        field: Field::Kind,
+        label: "Kind",
+        kind: ColumnType::String,
+        json_key: "kind",
+        min_width: 12,
+        right_aligned: false,
+        color: Color::FG_CYAN,
+    },
+    ColumnSpec {
+        field: Field::Size,
+        label: "Size B",
+        kind: ColumnType::Integer,
+        json_key: "len_bytes",
+        min_width: 10,
+        right_aligned: true,
+        color: Color::FG_BRIGHT_MAGENTA,
+    },
+    ColumnSpec {
+        field: Field::Allocated,
+        label: "Alloc B",
+        kind: ColumnType::Integer,
+        json_key: "allocated_bytes",
+        min_width: 10,
+        right_aligned: true,
+        color: Color::FG_MAGENTA,
+    },
+    ColumnSpec {
+        field: Field::Modified,
+        label: "Modified",
+        kind: ColumnType::Timestamp,
+        json_key: "modified",
+        min_width: 15,
+        right_aligned: false,
+        color: Color::FG_BRIGHT_BLUE,
+    },
+    ColumnSpec {
+        field: Field::Accessed,
+        label: "Last Accessed",
+        kind: ColumnType::Timestamp,
+        json_key: "accessed",
+        min_width: 15,
+        right_aligned: false,
+        color: Color::FG_BLUE,
+    },
+    ColumnSpec {
+        field: Field::Age,
+        label: "Age",
+        kind: ColumnType::Integer,
+        json_key: "age_seconds",
+        min_width: 4,
+        right_aligned: true,
+        color: Color::FG_CYAN,
+    },
+    ColumnSpec {
+        field: Field::Hash,
+        label: "Hash",
+        kind: ColumnType::String,
+        json_key: "hash",
+        min_width: 13,
+        right_aligned: false,
+        color: Color::FG_BRIGHT_BLACK,
+    },
+];
+
+pub const SHORT_LAYOUT: &[Field] = &[Field::Name, Field::Type, Field::Size, Field::Modified];
+// Links sit between permissions and owner, as in `ls -l`
+pub const LONG_LAYOUT: &[Field] = &[
+    Field::Permissions,
+    Field::Links,
+    Field::Owner,
+    Field::Name,
+    Field::Type,
+    Field::Size,
+    Field::Modified,
+];
+
+pub fn spec(field: Field) -> &'static ColumnSpec {
+    COLUMNS
+        .iter()
+        .find(|c| c.field == field)
+        .expect("every field has a column")
+}
+
+/// Parses one entry of `--fields`, listing the valid names when it isn't one.
+pub fn parse_field(input: &str) -> Result<Field, String> {
+    Field::from_str(input.trim()).map_err(|_| {
+        let valid: Vec<_> = COLUMNS.iter().map(|c| c.field.to_string()).collect();
+        format!(
+            "unknown field '{}', valid fields are: {}",
+            input,
+            valid.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const EVERY_FIELD: &[Field] = &[
+        Field::Inode,
+        Field::Permissions,
+        Field::Links,
+        Field::Owner,
+        Field::Context,
+        Field::Name,
+        Field::Type,
+        Field::Kind,
+        Field::Size,
+        Field::Allocated,
+        Field::Modified,
+        Field::Accessed,
+        Field::Age,
+        Field::Hash,
+    ];
+
+    #[test]
+    fn every_field_has_one_column() {
+        assert_eq!(COLUMNS.len(), EVERY_FIELD.len());
+        for &field in EVERY_FIELD {
+            assert_eq!(spec(field).field, field);
+        }
+        let keys: HashSet<_> = COLUMNS.iter().map(|c| c.json_key).collect();
+        assert_eq!(keys.len(), COLUMNS.len());
+    }
+
+    #[test]
+    fn fields_parse_by_their_id() {
+        for column in COLUMNS {
+            assert_eq!(parse_field(&column.field.to_string()), Ok(column.field));
+        }
+        assert_eq!(parse_field(" size "), Ok(Field::Size));
+    }
+
+    #[test]
+    fn unknown_fields_list_the_registry() {
+        let error = parse_field("colour").unwrap_err();
+        assert!(error.starts_with("unknown field 'colour', valid fields are: inode, "));
+        for column in COLUMNS {
+            assert!(error.contains(&column.field.to_string()));
+        }
+    }
+}
@@ -0,0 +1,469 @@
+use crate::{
+    Cli, color,
+    columns::Field,
+    pattern::{self, NamePattern},
+    size,
+    sort::SortKey,
+    style::TableStyle,
+};
+use clap::{ArgMatches, parser::ValueSource};
+use owo_colors::{AnsiColors, OwoColorize};
+use serde::{Deserialize, Deserializer, de::Error};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+use tabled::settings::Color;
+
+/// `config.toml`, every key is optional and missing ones keep the built in defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    defaults: Defaults,
+    table: TableConfig,
+    theme: Theme,
+    /// Overrides for listings of paths matching a glob, e.g. `[paths."~/Downloads"]`
+    paths: BTreeMap<String, PathOverrides>,
+}
+
+/// Values for command line flags that weren't given explicitly.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Defaults {
+    all: Option<bool>,
+    long: Option<bool>,
+    human_readable: Option<bool>,
+    sort: Option<SortKey>,
+    depth: Option<usize>,
+    /// Replaces the directories trees collapse, e.g. `tree_skip = ["node_modules", "dist"]`
+    tree_skip: Option<Vec<String>>,
+}
+
+/// Settings for one `[paths]` pattern, they win over the global ones but not over flags.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PathOverrides {
+    all: Option<bool>,
+    sort: Option<SortKey>,
+    fields: Option<Vec<Field>>,
+    theme: Option<Theme>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TableConfig {
+    style: Option<TableStyle>,
+}
+
+/// Colors picked in `[theme]`, anything left out keeps its built in color.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    header: Option<ThemeColor>,
+    /// Keyed by column id, e.g. `name` or `size`. --mounts has filesystem, type, size,
+    /// use_percent, mounted_on and options, --by-owner and --by-extension have owner or
+    /// extension, files, dirs, size and share
+    columns: HashMap<String, ThemeColor>,
+    /// Keyed by file kind as used by the tree: directory, source, docs, data, image, other,
+    /// and in tables too: symlink, fifo, socket, device, executable
+    kinds: HashMap<String, ThemeColor>,
+    sizes: SizeThresholds,
+}
+
+/// Where sizes turn yellow, red and bright red, written like `large = "100Mi"`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SizeThresholds {
+    #[serde(deserialize_with = "size_threshold")]
+    medium: Option<u64>,
+    #[serde(deserialize_with = "size_threshold")]
+    large: Option<u64>,
+    #[serde(deserialize_with = "size_threshold")]
+    huge: Option<u64>,
+}
+
+fn size_threshold<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    let input = String::deserialize(deserializer)?;
+    size::parse_size(&input).map(Some).map_err(D::Error::custom)
+}
+
+impl Theme {
+    pub fn header(&self) -> Color {
+        self.header
+            .map(ThemeColor::cell)
+            .unwrap_or(Color::FG_BRIGHT_GREEN)
+    }
+
+    pub fn column(&self, id: &str, default: &Color) -> Color {
+        self.columns
+            .get(id)
+            .map(|c| c.cell())
+            .unwrap_or_else(|| default.clone())
+    }
+
+    pub fn kind(&self, kind: &str, default: ThemeColor) -> ThemeColor {
+        self.kinds.get(kind).copied().unwrap_or(default)
+    }
+
+    /// Color of a size cell, sizes below a MiB keep the column color.
+    pub fn size(&self, len_bytes: u64) -> Option<Color> {
+        const MIB: u64 = 1024 * 1024;
+        let sizes = &self.sizes;
+        if len_bytes >= sizes.huge.unwrap_or(1024 * MIB) {
+            Some(Color::FG_BRIGHT_RED | Color::BOLD)
+        } else if len_bytes >= sizes.large.unwrap_or(100 * MIB) {
+            Some(Color::FG_RED)
+        } else if len_bytes >= sizes.medium.unwrap_or(MIB) {
+            Some(Color::FG_YELLOW)
+        } else {
+            None
+        }
+    }
+
+    /// Lays `other` over this theme, keeping the colors it doesn't mention.
+    fn merge(&mut self, other: Theme) {
+        self.header = other.header.or(self.header);
+        self.columns.extend(other.columns);
+        self.kinds.extend(other.kinds);
+        self.sizes.medium = other.sizes.medium.or(self.sizes.medium);
+        self.sizes.large = other.sizes.large.or(self.sizes.large);
+        self.sizes.huge = other.sizes.huge.or(self.sizes.huge);
+    }
+}
+
+/// The 16 standard terminal colors, written like `bright-blue` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+impl ThemeColor {
+    /// The color as a table cell color.
+    pub fn cell(self) -> Color {
+        match self {
+            ThemeColor::Black => Color::FG_BLACK,
+            ThemeColor::Red => Color::FG_RED,
+            ThemeColor::Green => Color::FG_GREEN,
+            ThemeColor::Yellow => Color::FG_YELLOW,
+            ThemeColor::Blue => Color::FG_BLUE,
+            ThemeColor::Magenta => Color::FG_MAGENTA,
+            ThemeColor::Cyan => Color::FG_CYAN,
+            ThemeColor::White => Color::FG_WHITE,
+            ThemeColor::BrightBlack => Color::FG_BRIGHT_BLACK,
+            ThemeColor::BrightRed => Color::FG_BRIGHT_RED,
+            ThemeColor::BrightGreen => Color::FG_BRIGHT_GREEN,
+            ThemeColor::BrightYellow => Color::FG_BRIGHT_YELLOW,
+            ThemeColor::BrightBlue => Color::FG_BRIGHT_BLUE,
+            ThemeColor::BrightMagenta => Color::FG_BRIGHT_MAGENTA,
+            ThemeColor::BrightCyan => Color::FG_BRIGHT_CYAN,
+            ThemeColor::BrightWhite => Color::FG_BRIGHT_WHITE,
+        }
+    }
+
+    /// The color for text printed outside of tables, like tree lines.
+    pub fn ansi(self) -> AnsiColors {
+        match self {
+            ThemeColor::Black => AnsiColors::Black,
+            ThemeColor::Red => AnsiColors::Red,
+            ThemeColor::Green => AnsiColors::Green,
+            ThemeColor::Yellow => AnsiColors::Yellow,
+            ThemeColor::Blue => AnsiColors::Blue,
+            ThemeColor::Magenta => AnsiColors::Magenta,
+            ThemeColor::Cyan => AnsiColors::Cyan,
+            ThemeColor::White => AnsiColors::White,
+            ThemeColor::BrightBlack => AnsiColors::BrightBlack,
+            ThemeColor::BrightRed => AnsiColors::BrightRed,
+            ThemeColor::BrightGreen => AnsiColors::BrightGreen,
+            ThemeColor::BrightYellow => AnsiColors::BrightYellow,
+            ThemeColor::BrightBlue => AnsiColors::BrightBlue,
+            ThemeColor::BrightMagenta => AnsiColors::BrightMagenta,
+            ThemeColor::BrightCyan => AnsiColors::BrightCyan,
+            ThemeColor::BrightWhite => AnsiColors::BrightWhite,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, a missing file is the same as an empty one. A file that
+    /// can't be read or parsed is ignored, with a warning on stderr unless `quiet`.
+    pub fn load(quiet: bool) -> Config {
+        let report = |message: &str| {
+            if !quiet {
+                warn(message);
+            }
+        };
+        let Some(path) = config_file() else {
+            return Config::default();
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Config::default(),
+            Err(e) => {
+                report(&format!("cannot read {}: {}", path.display(), e));
+                return Config::default();
+            }
+        };
+
+        toml::from_str(&content).unwrap_or_else(|e| {
+            report(&format!("ignoring {}: {}", path.display(), e));
+            Config::default()
+        })
+    }
+
+    /// Fills in every option of `cli` that wasn't given on the command line, from the
+    /// most specific `[paths]` entry matching `path` first and the global settings second.
+    pub fn apply(self, cli: &mut Cli, matches: &ArgMatches, path: &Path) {
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+        let mut defaults = self.defaults;
+        let mut theme = self.theme;
+
+        if let Some(overrides) = matching_paths(self.paths, path, cli.verbose) {
+            defaults.all = overrides.all.or(defaults.all);
+            defaults.sort = overrides.sort.or(defaults.sort);
+            if let Some(fields) = overrides.fields.filter(|_| unset("fields")) {
+                cli.fields = fields;
+            }
+            if let Some(path_theme) = overrides.theme {
+                theme.merge(path_theme);
+            }
+        }
+
+        if let Some(all) = defaults.all.filter(|_| unset("all") && unset("no_all")) {
+            cli.all = all;
+        }
+        if let Some(long) = defaults.long.filter(|_| unset("long") && unset("no_long")) {
+            cli.long = long;
+        }
+        if let Some(human) = defaults.human_readable.filter(|_| unset("human_readable")) {
+            cli.human_readable = human;
+        }
+        if let Some(sort) = defaults
+            .sort
+            .filter(|_| unset("sort") && unset("sort_by_time") && unset("no_sort"))
+        {
+            cli.sort = sort;
+        }
+        if let Some(depth) = defaults.depth.filter(|_| unset("depth")) {
+            cli.depth = depth;
+        }
+        if let Some(tree_skip) = defaults.tree_skip {
+            cli.tree_skip = tree_skip;
+        }
+        if let Some(style) = self.table.style.filter(|_| unset("style")) {
+            cli.style = style;
+        }
+        cli.theme = theme;
+    }
+}
+
+/// Picks the `[paths]` entry for `path`. The pattern with the most literal text wins,
+/// equally specific patterns are decided by which sorts first.
+fn matching_paths(
+    paths: BTreeMap<String, PathOverrides>,
+    path: &Path,
+    verbose: bool,
+) -> Option<PathOverrides> {
+    let path = fs::canonicalize(path).ok()?;
+    let path = path.to_string_lossy();
+
+    let mut candidates: Vec<(String, NamePattern, PathOverrides)> = Vec::new();
+    for (pattern, overrides) in paths {
+        match pattern::parse_path_glob(&expand_home(&pattern)) {
+            Ok(glob) if glob.is_match(&path) => candidates.push((pattern, glob, overrides)),
+            Ok(_) => {}
+            Err(e) => warn(&format!("ignoring [paths] entry: {}", e)),
+        }
+    }
+    // Stable, so ties keep the alphabetical order of the map
+    candidates.sort_by_key(|(_, glob, _)| std::cmp::Reverse(glob.specificity()));
+
+    let mut candidates = candidates.into_iter();
+    let (chosen, _, overrides) = candidates.next()?;
+    if verbose {
+        eprintln!("config: [paths.\"{}\"] applies to {}", chosen, path);
+        for (pattern, _, _) in candidates {
+            eprintln!(
+                "config: [paths.\"{}\"] also matches but is less specific than \"{}\", ignored",
+                pattern, chosen
+            );
+        }
+    }
+    Some(overrides)
+}
+
+fn expand_home(pattern: &str) -> String {
+    match (pattern.strip_prefix('~'), env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home, rest)
+        }
+        _ => pattern.to_string(),
+    }
+}
+
+fn warn(message: &str) {
+    eprintln!(
+        "{}",
+        color::for_stderr(format!("warning: {}", message).yellow())
+    );
+}
+
+fn config_file() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("better-ls").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use clap::{CommandFactory, FromArgMatches};
+
+    struct Setup {
+        fixture: Fixture,
+        root: String,
+    }
+
+    fn setup() -> Setup {
+        let fixture = Fixture::new();
+        fixture.dir("downloads/old").dir("projects/app");
+        let root = fs::canonicalize(fixture.path())
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        Setup { fixture, root }
+    }
+
+    // `config` with every `{root}` replaced, applied to `relative` listed with `args`
+    fn applied(setup: &Setup, config: &str, relative: &str, args: &[&str]) -> Cli {
+        let config: Config = toml::from_str(&config.replace("{root}", &setup.root)).unwrap();
+        let matches = Cli::command()
+            .try_get_matches_from(std::iter::once("best-ls").chain(args.iter().copied()))
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        config.apply(&mut cli, &matches, &setup.fixture.join(relative));
+        cli
+    }
+
+    const OVERLAPPING: &str = r#"
+        [defaults]
+        sort = "size"
+
+        [paths."{root}/**"]
+        sort = "name"
+        all = true
+
+        [paths."{root}/downloads"]
+        sort = "time"
+    "#;
+
+    #[test]
+    fn the_most_specific_pattern_wins() {
+        let setup = setup();
+        assert_eq!(
+            applied(&setup, OVERLAPPING, "downloads", &[]).sort,
+            SortKey::Time
+        );
+        assert_eq!(
+            applied(&setup, OVERLAPPING, "projects", &[]).sort,
+            SortKey::Name
+        );
+        // `downloads` matches itself only, what is below it falls back to `**`
+        assert_eq!(
+            applied(&setup, OVERLAPPING, "downloads/old", &[]).sort,
+            SortKey::Name
+        );
+    }
+
+    #[test]
+    fn unmatched_paths_keep_the_defaults() {
+        let setup = setup();
+        let cli = applied(&setup, OVERLAPPING, "/", &[]);
+        assert_eq!(cli.sort, SortKey::Size);
+        assert!(!cli.all);
+    }
+
+    #[test]
+    fn only_the_chosen_pattern_applies() {
+        let setup = setup();
+        // `all` of the less specific pattern doesn't leak into downloads
+        assert!(!applied(&setup, OVERLAPPING, "downloads", &[]).all);
+        assert!(applied(&setup, OVERLAPPING, "projects", &[]).all);
+    }
+
+    #[test]
+    fn flags_win_over_everything() {
+        let setup = setup();
+        let cli = applied(&setup, OVERLAPPING, "downloads", &["--sort", "none"]);
+        assert_eq!(cli.sort, SortKey::None);
+        assert!(!applied(&setup, OVERLAPPING, "projects", &["--no-all"]).all);
+        // The flags that stand for a sort key count as giving it
+        assert_eq!(
+            applied(&setup, OVERLAPPING, "downloads", &["-t"]).sort,
+            SortKey::Time
+        );
+        assert_eq!(
+            applied(&setup, OVERLAPPING, "downloads", &["-U"]).sort,
+            SortKey::None
+        );
+    }
+
+    #[test]
+    fn paths_pick_fields_unless_given() {
+        let setup = setup();
+        let config = r#"
+            [paths."{root}/projects/*"]
+            fields = ["name", "size"]
+        "#;
+        let cli = applied(&setup, config, "projects/app", &[]);
+        assert_eq!(cli.fields, [Field::Name, Field::Size]);
+        let cli = applied(&setup, config, "projects/app", &["--fields", "modified"]);
+        assert_eq!(cli.fields, [Field::Modified]);
+    }
+
+    #[test]
+    fn equally_specific_patterns_go_by_name() {
+        let setup = setup();
+        let config = r#"
+            [paths."{root}/projects/a*"]
+            sort = "time"
+
+            [paths."{root}/projects/*p"]
+            sort = "size"
+        "#;
+        assert_eq!(
+            applied(&setup, config, "projects/app", &[]).sort,
+            SortKey::Size
+        );
+    }
+
+    #[test]
+    fn home_is_expanded_only_at_the_start() {
+        let home = env::var("HOME").unwrap_or_default();
+        assert_eq!(expand_home("~/Downloads"), format!("{}/Downloads", home));
+        assert_eq!(expand_home("~"), home);
+        assert_eq!(expand_home("~user/x"), "~user/x");
+        assert_eq!(expand_home("/a/~/b"), "/a/~/b");
+    }
+}
@@ -0,0 +1,31 @@
+use crate::entry::RawEntry;
+use crate::pool;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How many entries `dir` holds, without descending. Hidden ones only count with `all`,
+/// like they are only listed with it.
+pub fn entry_count(dir: &Path, all: bool) -> io::Result<u64> {
+    let mut count = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if all || !entry.file_name().as_encoded_bytes().starts_with(b".") {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Fills in the entry counts of the directories in `entries`, reading them in parallel.
+pub fn apply(dir: &Path, entries: &mut [RawEntry], all: bool) {
+    let mut dirs: Vec<_> = entries.iter_mut().filter(|e| e.is_real_dir()).collect();
+    let paths: Vec<_> = dirs.iter().map(|e| e.path_in(dir)).collect();
+    let counts = pool::map(&paths, |path| entry_count(path, all));
+
+    for (raw, count) in dirs.iter_mut().zip(counts) {
+        if let Some(count) = count {
+            raw.entry_count = Some(count.map_err(|e| e.to_string()));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::duration;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::time::SystemTime;
+
+// Absolute forms besides RFC 3339, read as local time
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Parses the point in time of `--newer-than`/`--older-than`: a duration back from now
+/// (`90m`, `30d`, `1y`), a date (`2024-01-01`, local midnight) or a timestamp,
+/// either RFC 3339 or `2024-01-01 13:37[:42]` in local time.
+pub fn parse_cutoff(input: &str) -> Result<SystemTime, String> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Ok(time.into());
+    }
+    // Durations always end in a unit and dates never do, so `2024` isn't taken for either
+    if input.ends_with(|c: char| c.is_ascii_alphabetic()) {
+        let age = duration::parse_duration(input)?;
+        return SystemTime::now()
+            .checked_sub(age)
+            .ok_or_else(|| format!("duration '{}' reaches too far back", input));
+    }
+
+    let local = if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        date.and_hms_opt(0, 0, 0)
+    } else {
+        DATETIME_FORMATS
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+    };
+    let Some(local) = local else {
+        return Err(format!(
+            "invalid time '{}', expected a duration like 30d, a date like 2024-01-01 \
+             or a timestamp like 2024-01-01T13:37:00Z",
+            input
+        ));
+    };
+    // A time skipped by a DST change doesn't exist, one repeated by it takes the earlier
+    Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(SystemTime::from)
+        .ok_or_else(|| format!("'{}' doesn't exist in the local time zone", input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn local(date: &str) -> SystemTime {
+        let local = NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S").unwrap();
+        Local.from_local_datetime(&local).earliest().unwrap().into()
+    }
+
+    #[test]
+    fn rfc3339_is_exact() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(parse_cutoff("2023-11-14T22:13:20Z"), Ok(expected));
+        assert_eq!(parse_cutoff("2023-11-15T03:43:20+05:30"), Ok(expected));
+    }
+
+    #[test]
+    fn dates_and_times_are_local() {
+        assert_eq!(parse_cutoff("2024-01-01"), Ok(local("2024-01-01 00:00:00")));
+        assert_eq!(
+            parse_cutoff(" 2024-01-01 13:37 "),
+            Ok(local("2024-01-01 13:37:00"))
+        );
+        assert_eq!(
+            parse_cutoff("2024-01-01 13:37:42"),
+            Ok(local("2024-01-01 13:37:42"))
+        );
+        assert_eq!(
+            parse_cutoff("2024-01-01T13:37:42"),
+            Ok(local("2024-01-01 13:37:42"))
+        );
+    }
+
+    #[test]
+    fn durations_count_back_from_now() {
+        let before = SystemTime::now();
+        let cutoff = parse_cutoff("30d").unwrap();
+        let after = SystemTime::now();
+        let month = Duration::from_secs(30 * 24 * 3600);
+        assert!(cutoff >= before - month && cutoff <= after - month);
+
+        // `m` is minutes, never months
+        let cutoff = parse_cutoff("1m").unwrap();
+        assert!(SystemTime::now().duration_since(cutoff).unwrap() < Duration::from_secs(120));
+    }
+
+    #[test]
+    fn ambiguous_inputs_are_rejected() {
+        // A year alone is neither a date nor a duration
+        assert!(
+            parse_cutoff("2024")
+                .unwrap_err()
+                .starts_with("invalid time '2024'")
+        );
+        assert!(parse_cutoff("30").is_err());
+        assert!(parse_cutoff("2024-01").is_err());
+        assert!(parse_cutoff("2024-13-01").is_err());
+        assert!(parse_cutoff("01/02/2024").is_err());
+        assert!(parse_cutoff("").is_err());
+        // Ends in a letter, so it is read as a duration and reported as one
+        assert!(parse_cutoff("yesterday").unwrap_err().contains("duration"));
+        assert!(
+            parse_cutoff("5x")
+                .unwrap_err()
+                .contains("unknown duration unit")
+        );
+    }
+
+    #[test]
+    fn too_far_back() {
+        assert!(parse_cutoff("9999999999999y").is_err());
+    }
+}
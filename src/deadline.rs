@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// A time budget every thread can check, unlimited until it is started.
+#[derive(Debug, Default)]
+pub struct Deadline {
+    at: OnceLock<Instant>,
+    // Latched so every walker stops once any of them noticed, without asking the clock again
+    expired: AtomicBool,
+}
+
+impl Deadline {
+    pub const fn new() -> Deadline {
+        Deadline {
+            at: OnceLock::new(),
+            expired: AtomicBool::new(false),
+        }
+    }
+
+    /// Starts the budget, counted from now. Only the first start counts.
+    pub fn start(&self, timeout: Duration) {
+        let _ = self.at.set(Instant::now() + timeout);
+    }
+
+    /// Whether the budget is used up.
+    pub fn expired(&self) -> bool {
+        if self.expired.load(Ordering::Relaxed) {
+            return true;
+        }
+        let Some(at) = self.at.get() else {
+            return false;
+        };
+        if Instant::now() >= *at {
+            self.expired.store(true, Ordering::Relaxed);
+            return true;
+        }
+        false
+    }
+
+    /// Whether anyone found the budget used up.
+    pub fn hit(&self) -> bool {
+        self.expired.load(Ordering::Relaxed)
+    }
+}
+
+/// The `--timeout` budget of this run, which the functions below check.
+pub static RUN: Deadline = Deadline::new();
+
+/// Starts the `--timeout` budget, counted from now.
+pub fn start(timeout: Duration) {
+    RUN.start(timeout);
+}
+
+/// Whether the budget is used up, walkers call this before each unit of work and stop when it is.
+pub fn expired() -> bool {
+    RUN.expired()
+}
+
+/// Whether any walker was cut short by the deadline during this run.
+pub fn hit() -> bool {
+    RUN.hit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_until_started() {
+        let deadline = Deadline::new();
+        assert!(!deadline.expired());
+        assert!(!deadline.hit());
+    }
+
+    #[test]
+    fn expires_once_the_budget_is_used() {
+        let deadline = Deadline::new();
+        deadline.start(Duration::from_millis(200));
+        assert!(!deadline.expired());
+        std::thread::sleep(Duration::from_millis(250));
+        // Nobody has looked yet
+        assert!(!deadline.hit());
+        assert!(deadline.expired());
+        assert!(deadline.hit());
+    }
+
+    #[test]
+    fn the_first_start_counts() {
+        let deadline = Deadline::new();
+        deadline.start(Duration::ZERO);
+        deadline.start(Duration::from_secs(3600));
+        assert!(deadline.expired());
+    }
+
+    #[test]
+    fn every_thread_sees_it_expired() {
+        let deadline = Deadline::new();
+        deadline.start(Duration::ZERO);
+        assert!(deadline.expired());
+        std::thread::scope(|scope| {
+            let seen = scope.spawn(|| deadline.hit() && deadline.expired());
+            assert!(seen.join().unwrap());
+        });
+    }
+}
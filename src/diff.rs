@@ -0,0 +1,180 @@
+use crate::cli::{Cli, skipped_in_tree};
+use crate::collect::collect_entries;
+use crate::deadline;
+use crate::entry::RawEntry;
+use crate::tree::descends;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// Only in the left directory, `-`
+    OnlyLeft,
+    /// Only in the right directory, `+`
+    OnlyRight,
+    /// In both but not alike, `~`
+    Differs,
+    /// In both and alike, `=`
+    Same,
+}
+
+impl Status {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Status::OnlyLeft => "-",
+            Status::OnlyRight => "+",
+            Status::Differs => "~",
+            Status::Same => "=",
+        }
+    }
+}
+
+/// What one side of --diff knows about an entry.
+#[derive(Debug, Serialize)]
+pub struct Side {
+    #[serde(rename = "e_type")]
+    pub type_name: String,
+    pub len_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// An entry of either directory of --diff.
+#[derive(Debug, Serialize)]
+pub struct DiffEntry {
+    pub status: Status,
+    /// Relative to the compared directories
+    pub name: String,
+    /// What differs: `type`, `size`, `modified` or `content`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub left: Option<Side>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub right: Option<Side>,
+}
+
+/// Joins the listings of `left` and `right` by name, in name order. Under -R directories
+/// in both are compared entry by entry too, one only on one side is reported on its own.
+/// Returns whether either listing was cut short by --max-entries.
+pub fn compare(left: &Path, right: &Path, cli: &Cli) -> (Vec<DiffEntry>, bool) {
+    let mut entries = Vec::new();
+    let truncated = compare_dirs(left, right, "", cli, &mut entries);
+    (entries, truncated)
+}
+
+fn compare_dirs(
+    left: &Path,
+    right: &Path,
+    prefix: &str,
+    cli: &Cli,
+    diffs: &mut Vec<DiffEntry>,
+) -> bool {
+    let left_listing = collect_entries(left, cli, |raw, _| raw);
+    let right_listing = collect_entries(right, cli, |raw, _| raw);
+    let mut truncated = left_listing.truncated || right_listing.truncated;
+
+    let mut by_name: BTreeMap<String, (Option<RawEntry>, Option<RawEntry>)> = BTreeMap::new();
+    for raw in left_listing
+        .entries
+        .into_iter()
+        .filter(|r| !r.is_dot_entry())
+    {
+        let name = raw.name.clone();
+        by_name.entry(name).or_default().0 = Some(raw);
+    }
+    for raw in right_listing
+        .entries
+        .into_iter()
+        .filter(|r| !r.is_dot_entry())
+    {
+        let name = raw.name.clone();
+        by_name.entry(name).or_default().1 = Some(raw);
+    }
+
+    for (name, sides) in by_name {
+        if deadline::expired() {
+            break;
+        }
+        let path = format!("{}{}", prefix, name);
+        let (status, reasons) = match &sides {
+            (Some(_), None) => (Status::OnlyLeft, Vec::new()),
+            (None, Some(_)) => (Status::OnlyRight, Vec::new()),
+            (Some(a), Some(b)) => {
+                let reasons = differences(a, b);
+                let status = if reasons.is_empty() {
+                    Status::Same
+                } else {
+                    Status::Differs
+                };
+                (status, reasons)
+            }
+            (None, None) => continue,
+        };
+        let (left_raw, right_raw) = sides;
+        // Directories of the same name on both sides, a file on one side never is
+        let subdirs = match (&left_raw, &right_raw) {
+            (Some(a), Some(b))
+                if cli.recursive
+                    && descends(left, a, cli)
+                    && descends(right, b, cli)
+                    && !skipped_in_tree(a, cli) =>
+            {
+                Some((a.path_in(left), b.path_in(right)))
+            }
+            _ => None,
+        };
+        diffs.push(DiffEntry {
+            status,
+            name: path.clone(),
+            reasons,
+            left: left_raw.map(|raw| side(raw, cli)),
+            right: right_raw.map(|raw| side(raw, cli)),
+        });
+        if let Some((left, right)) = subdirs {
+            truncated |= compare_dirs(&left, &right, &format!("{}/", path), cli, diffs);
+        }
+    }
+    truncated
+}
+
+// Directories are alike whenever they are both directories, their contents are compared
+// under -R. With --hash, files with the same contents are alike whatever their times.
+fn differences(a: &RawEntry, b: &RawEntry) -> Vec<&'static str> {
+    if a.e_type != b.e_type {
+        return vec!["type"];
+    }
+    if a.is_real_dir() {
+        return Vec::new();
+    }
+    let mut reasons = Vec::new();
+    if a.len != b.len {
+        reasons.push("size");
+    }
+    match (&a.hash, &b.hash) {
+        (Some(Ok(x)), Some(Ok(y))) if x != y => reasons.push("content"),
+        (Some(Ok(_)), Some(Ok(_))) => {}
+        _ if seconds(a) != seconds(b) => reasons.push("modified"),
+        _ => {}
+    }
+    reasons
+}
+
+// Copies often lose the fraction of a second
+fn seconds(raw: &RawEntry) -> Option<u64> {
+    raw.modified?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs())
+}
+
+fn side(raw: RawEntry, cli: &Cli) -> Side {
+    Side {
+        type_name: raw.e_type.json_name(cli.json_schema),
+        len_bytes: raw.len,
+        hash: raw.hash.and_then(Result::ok),
+    }
+}
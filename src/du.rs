@@ -0,0 +1,159 @@
+use crate::{RawEntry, deadline, mounts, pool};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+// Totals of every directory walked so far, so a directory that is a row of the listing
+// and part of a later walk (e.g. in a tree) is only read once
+static TOTALS: OnceLock<Mutex<HashMap<PathBuf, u64>>> = OnceLock::new();
+
+fn totals() -> &'static Mutex<HashMap<PathBuf, u64>> {
+    TOTALS.get_or_init(Default::default)
+}
+
+/// Replaces the size of every directory in `entries` with the size of its contents,
+/// walking the directories in parallel. With `one_file_system` mount points of other
+/// file systems keep their own size.
+pub fn apply(dir: &Path, entries: &mut [RawEntry], one_file_system: bool) {
+    // `..` would be the whole parent, `.` is the total of the listing
+    let mut dirs: Vec<_> = entries
+        .iter_mut()
+        .filter(|e| e.is_real_dir() && e.name != "..")
+        .filter(|e| !(one_file_system && mounts::other_device(dir, e)))
+        .collect();
+    let paths: Vec<PathBuf> = dirs.iter().map(|e| e.path_in(dir)).collect();
+    let sizes = pool::map(&paths, |path| total(path, one_file_system));
+
+    for (raw, size) in dirs.iter_mut().zip(sizes) {
+        if let Some(size) = size {
+            raw.len = size;
+        }
+    }
+}
+
+/// The summed size of everything below `dir`. Symlinks count with their own size and are
+/// not followed, entries that can't be read are skipped. With `one_file_system`
+/// directories on another device than `dir` aren't entered.
+pub fn total(dir: &Path, one_file_system: bool) -> u64 {
+    if let Some(size) = lookup(dir) {
+        return size;
+    }
+
+    let device = if one_file_system {
+        fs::symlink_metadata(dir)
+            .ok()
+            .and_then(|meta| mounts::device(&meta))
+    } else {
+        None
+    };
+    let mut size = 0;
+    if let Ok(read_dir) = fs::read_dir(dir) {
+        for entry in read_dir.flatten() {
+            if deadline::expired() {
+                // A partial sum must not be mistaken for the real one later
+                return size;
+            }
+            let Ok(meta) = fs::symlink_metadata(entry.path()) else {
+                continue;
+            };
+            size += if meta.is_dir() {
+                if mounts::crosses_device(device, mounts::device(&meta)) {
+                    continue;
+                }
+                total(&entry.path(), one_file_system)
+            } else {
+                meta.len()
+            };
+        }
+    }
+
+    if !deadline::hit() {
+        totals()
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(dir.to_path_buf(), size);
+    }
+    size
+}
+
+/// Forgets every total, for redraws that must see the current sizes.
+pub fn clear() {
+    totals().lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+fn lookup(dir: &Path) -> Option<u64> {
+    totals()
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(dir)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn contents_are_summed_at_every_depth() {
+        let fixture = Fixture::new();
+        fixture
+            .file("a.txt", b"hello")
+            .file("sub/b.txt", b"0123456789")
+            .file("sub/deeper/c.txt", b"abc");
+        assert_eq!(total(fixture.path(), false), 18);
+        assert_eq!(total(&fixture.join("sub"), false), 13);
+    }
+
+    // A link counts with the length of its target path, whatever it points to
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_are_not_followed() {
+        let fixture = Fixture::new();
+        fixture
+            .file("elsewhere/big.bin", &[0; 4096])
+            .file("dir/a.txt", b"hello")
+            .symlink("../elsewhere", "dir/to-dir")
+            .symlink("../elsewhere/big.bin", "dir/to-file");
+        let links = "../elsewhere".len() + "../elsewhere/big.bin".len();
+        assert_eq!(total(&fixture.join("dir"), false), 5 + links as u64);
+    }
+
+    // Root reads any directory, so there is nothing to skip then
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directories_are_skipped() {
+        use std::os::unix::fs::PermissionsExt;
+        if users::get_effective_uid() == 0 {
+            return;
+        }
+        let fixture = Fixture::new();
+        fixture
+            .file("a.txt", b"hello")
+            .file("locked/secret.txt", b"secret");
+        let locked = fixture.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let size = total(fixture.path(), false);
+        // Or the fixture can't be removed
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(size, 5);
+    }
+
+    #[test]
+    fn a_directory_is_only_walked_once() {
+        let fixture = Fixture::new();
+        fixture.file("sub/a.txt", b"hello");
+        let sub = fixture.join("sub");
+        assert_eq!(total(fixture.path(), false), 5);
+
+        // Walking the parent recorded the child too
+        fixture.file("sub/b.txt", b"more");
+        assert_eq!(lookup(&sub), Some(5));
+        assert_eq!(total(&sub, false), 5);
+        assert_eq!(total(fixture.path(), false), 5);
+
+        clear();
+        assert_eq!(total(fixture.path(), false), 9);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::cli::Cli;
+use crate::entry::{EntryType, RawEntry, UNIX_METADATA};
+use crate::hash::HashAlgorithm;
+use crate::pool;
+use crate::report::unreadable;
+use crate::tree::tree_walk;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupKind {
+    /// Separate files with the same contents
+    Duplicates,
+    /// Several names of the same file, removing one frees nothing
+    Hardlinks,
+}
+
+/// Files of `--duplicates` found to be the same, either by content or by inode.
+#[derive(Debug, Serialize)]
+pub struct DuplicateGroup {
+    pub kind: GroupKind,
+    pub len_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Relative to the listed directory
+    pub paths: Vec<String>,
+}
+
+impl DuplicateGroup {
+    /// Bytes freed by keeping only one of the files.
+    pub fn reclaimable(&self) -> u64 {
+        match self.kind {
+            GroupKind::Duplicates => self.len_bytes * (self.paths.len() as u64 - 1),
+            GroupKind::Hardlinks => 0,
+        }
+    }
+}
+
+struct Candidate {
+    // Relative to the listed directory
+    path: PathBuf,
+    // Device and inode number
+    inode: (u64, u64),
+}
+
+/// Groups the files below `dir` that have the same contents, largest files first. Only
+/// files sharing their size with another file are hashed. Names of the same inode are
+/// grouped as hardlinks, and only one of them takes part in the content comparison.
+pub fn find(
+    dir: &Path,
+    algorithm: HashAlgorithm,
+    recursive: bool,
+    cli: &Cli,
+) -> Vec<DuplicateGroup> {
+    let mut by_len: BTreeMap<u64, Vec<Candidate>> = BTreeMap::new();
+    for (index, (path, raw)) in tree_walk(dir, cli, recursive).into_iter().enumerate() {
+        if is_candidate(&raw) {
+            // Without inode numbers every name counts as a file of its own
+            let inode = if UNIX_METADATA {
+                (raw.dev, raw.ino)
+            } else {
+                (0, index as u64)
+            };
+            by_len
+                .entry(raw.len)
+                .or_default()
+                .push(Candidate { path, inode });
+        }
+    }
+
+    let mut groups = Vec::new();
+    let mut to_hash: Vec<(u64, Vec<PathBuf>)> = Vec::new();
+    for (len, files) in by_len.into_iter().rev() {
+        if files.len() < 2 {
+            continue;
+        }
+        let mut by_inode: BTreeMap<(u64, u64), Vec<PathBuf>> = BTreeMap::new();
+        for file in files {
+            by_inode.entry(file.inode).or_default().push(file.path);
+        }
+        let mut inodes = Vec::new();
+        for (_, mut names) in by_inode {
+            names.sort();
+            if names.len() > 1 {
+                groups.push(DuplicateGroup {
+                    kind: GroupKind::Hardlinks,
+                    len_bytes: len,
+                    hash: None,
+                    paths: names
+                        .iter()
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect(),
+                });
+            }
+            inodes.push(names.swap_remove(0));
+        }
+        if inodes.len() > 1 {
+            to_hash.push((len, inodes));
+        }
+    }
+
+    let paths: Vec<&PathBuf> = to_hash.iter().flat_map(|(_, paths)| paths).collect();
+    let digests = pool::map(&paths, |path| algorithm.digest_file(&dir.join(path)));
+    let mut digests = digests.into_iter();
+    for (len, paths) in &to_hash {
+        let mut by_hash: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            match digests.next().flatten() {
+                Some(Ok(hash)) => by_hash
+                    .entry(hash)
+                    .or_default()
+                    .push(path.to_string_lossy().to_string()),
+                Some(Err(e)) => unreadable(cli, &dir.join(path), &e),
+                // Not hashed before --timeout, so not known to be a duplicate
+                None => {}
+            }
+        }
+        for (hash, mut paths) in by_hash {
+            if paths.len() < 2 {
+                continue;
+            }
+            paths.sort();
+            groups.push(DuplicateGroup {
+                kind: GroupKind::Duplicates,
+                len_bytes: *len,
+                hash: Some(hash),
+                paths,
+            });
+        }
+    }
+
+    // Biggest savings first, hardlinks after the duplicates of the same size
+    groups.sort_by(|a, b| {
+        b.len_bytes
+            .cmp(&a.len_bytes)
+            .then_with(|| (a.kind as u8).cmp(&(b.kind as u8)))
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    groups
+}
+
+// Empty files are all alike and never reported
+fn is_candidate(raw: &RawEntry) -> bool {
+    raw.e_type == EntryType::File && raw.error.is_none() && raw.len > 0
+}
@@ -0,0 +1,184 @@
+use std::time::{Duration, SystemTime};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const YEAR: u64 = 365 * DAY;
+
+/// Parses durations like `90m`, `12h`, `30d`, `2w`, `1y` or combinations such as `1d12h`.
+/// Years count as 365 days.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("empty duration".to_string());
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = input;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if digits == 0 {
+            return Err(format!(
+                "invalid duration '{}', expected e.g. 30d or 12h",
+                input
+            ));
+        }
+        let value: u64 = rest[..digits]
+            .parse()
+            .map_err(|_| format!("duration '{}' is too large", input))?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let seconds = match &rest[..unit_len] {
+            "s" => 1,
+            "m" => MINUTE,
+            "h" => HOUR,
+            "d" => DAY,
+            "w" => 7 * DAY,
+            "y" => YEAR,
+            "" => {
+                return Err(format!(
+                    "duration '{}' is missing a unit (s, m, h, d, w, y)",
+                    input
+                ));
+            }
+            unit => {
+                return Err(format!(
+                    "unknown duration unit '{}' (use s, m, h, d, w, y)",
+                    unit
+                ));
+            }
+        };
+        rest = &rest[unit_len..];
+
+        total = value
+            .checked_mul(seconds)
+            .and_then(|s| total.checked_add(s))
+            .ok_or_else(|| format!("duration '{}' is too large", input))?;
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Writes an age in its largest whole unit, like `45s`, `3h`, `12d` or `2y`.
+pub fn short_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    let (value, unit) = match seconds {
+        s if s < MINUTE => (s, "s"),
+        s if s < HOUR => (s / MINUTE, "m"),
+        s if s < DAY => (s / HOUR, "h"),
+        s if s < YEAR => (s / DAY, "d"),
+        s => (s / YEAR, "y"),
+    };
+    format!("{}{}", value, unit)
+}
+
+/// Renders how long ago `time` was as seen from `now`, like `35m ago` or `in 2h` for
+/// timestamps in the future. Returns `None` once the distance passes ten years, where
+/// an absolute date reads better.
+pub fn relative_time(time: SystemTime, now: SystemTime) -> Option<String> {
+    let (distance, future) = match now.duration_since(time) {
+        Ok(age) => (age, false),
+        Err(e) => (e.duration(), true),
+    };
+
+    let seconds = distance.as_secs();
+    let (value, unit) = match seconds {
+        s if s < MINUTE => return Some("just now".to_string()),
+        s if s < HOUR => (s / MINUTE, "m"),
+        s if s < DAY => (s / HOUR, "h"),
+        s if s < YEAR => (s / DAY, "d"),
+        s if s < 10 * YEAR => (s / YEAR, "y"),
+        _ => return None,
+    };
+
+    Some(if future {
+        format!("in {}{}", value, unit)
+    } else {
+        format!("{}{} ago", value, unit)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    fn ago(seconds: u64) -> Option<String> {
+        relative_time(now() - Duration::from_secs(seconds), now())
+    }
+
+    fn ahead(seconds: u64) -> Option<String> {
+        relative_time(now() + Duration::from_secs(seconds), now())
+    }
+
+    #[test]
+    fn past_times_in_their_largest_unit() {
+        assert_eq!(ago(0).as_deref(), Some("just now"));
+        assert_eq!(ago(59).as_deref(), Some("just now"));
+        assert_eq!(ago(60).as_deref(), Some("1m ago"));
+        assert_eq!(ago(35 * MINUTE + 59).as_deref(), Some("35m ago"));
+        assert_eq!(ago(3 * HOUR).as_deref(), Some("3h ago"));
+        assert_eq!(ago(DAY - 1).as_deref(), Some("23h ago"));
+        assert_eq!(ago(4 * DAY).as_deref(), Some("4d ago"));
+        assert_eq!(ago(2 * YEAR + DAY).as_deref(), Some("2y ago"));
+    }
+
+    #[test]
+    fn future_times_dont_underflow() {
+        assert_eq!(ahead(30).as_deref(), Some("just now"));
+        assert_eq!(ahead(2 * HOUR + 5).as_deref(), Some("in 2h"));
+        assert_eq!(ahead(400 * DAY).as_deref(), Some("in 1y"));
+        assert_eq!(
+            relative_time(UNIX_EPOCH + Duration::from_nanos(1), UNIX_EPOCH).as_deref(),
+            Some("just now")
+        );
+    }
+
+    #[test]
+    fn old_times_fall_back_to_dates() {
+        assert_eq!(ago(10 * YEAR - 1).as_deref(), Some("9y ago"));
+        assert_eq!(ago(10 * YEAR), None);
+        assert_eq!(ahead(50 * YEAR), None);
+        assert_eq!(relative_time(UNIX_EPOCH, now()), None);
+    }
+
+    #[test]
+    fn short_ages() {
+        assert_eq!(short_age(Duration::from_secs(45)), "45s");
+        assert_eq!(short_age(Duration::from_secs(3 * HOUR + 1)), "3h");
+        assert_eq!(short_age(Duration::from_secs(12 * DAY)), "12d");
+        assert_eq!(short_age(Duration::from_secs(2 * YEAR)), "2y");
+    }
+
+    #[test]
+    fn durations_are_parsed() {
+        assert_eq!(parse_duration("90m"), Ok(Duration::from_secs(90 * MINUTE)));
+        assert_eq!(
+            parse_duration(" 1d12h "),
+            Ok(Duration::from_secs(DAY + 12 * HOUR))
+        );
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * DAY)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("12").unwrap_err().contains("missing a unit"));
+        assert!(
+            parse_duration("3x")
+                .unwrap_err()
+                .contains("unknown duration unit 'x'")
+        );
+        assert!(parse_duration("d").is_err());
+        assert!(
+            parse_duration("99999999999999999999y")
+                .unwrap_err()
+                .contains("too large")
+        );
+    }
+}
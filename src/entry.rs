@@ -0,0 +1,1478 @@
+use crate::cli::{Cli, needs_ages, size_style};
+use crate::collect::name_patterns;
+use crate::columns::Field;
+use crate::config::ThemeColor;
+use crate::icons::IconsMode;
+use crate::permissions::PermissionStyle;
+use crate::size::format_size;
+use crate::time_style::TimeKind;
+use crate::{
+    duration, failures, hyperlink, icons, names, owners, pattern, permissions, time_style, xattrs,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use strum::Display;
+use tabled::settings::Color;
+
+/// Owners, link counts, device and inode numbers only exist in Unix metadata. Elsewhere
+/// their columns stay blank and their JSON fields are null.
+pub const UNIX_METADATA: bool = cfg!(unix);
+
+/// The kind of an entry. Symlinks are reported as such, with the link's own metadata,
+/// unless --dereference puts what they point to in their place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum EntryType {
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+    // The metadata couldn't be read, shown as `?`
+    #[strum(to_string = "?")]
+    Unknown,
+}
+
+impl EntryType {
+    pub fn of(file_type: fs::FileType) -> EntryType {
+        if file_type.is_symlink() {
+            EntryType::Symlink
+        } else if file_type.is_dir() {
+            EntryType::Dir
+        } else {
+            EntryType::special(file_type).unwrap_or(EntryType::File)
+        }
+    }
+
+    #[cfg(unix)]
+    fn special(file_type: fs::FileType) -> Option<EntryType> {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            Some(EntryType::Fifo)
+        } else if file_type.is_socket() {
+            Some(EntryType::Socket)
+        } else if file_type.is_block_device() {
+            Some(EntryType::BlockDevice)
+        } else if file_type.is_char_device() {
+            Some(EntryType::CharDevice)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn special(_: fs::FileType) -> Option<EntryType> {
+        None
+    }
+
+    /// How `e_type` is written in JSON and YAML.
+    pub fn json_name(self, schema: JsonSchema) -> String {
+        let name = match self {
+            EntryType::File => "File",
+            EntryType::Dir => "Dir",
+            EntryType::Symlink => "Symlink",
+            EntryType::Fifo => "Fifo",
+            EntryType::Socket => "Socket",
+            EntryType::BlockDevice => "BlockDevice",
+            EntryType::CharDevice => "CharDevice",
+            EntryType::Unknown => "Unknown",
+        };
+        match schema {
+            JsonSchema::V1 => name.to_string(),
+            JsonSchema::V2 => name.to_lowercase(),
+        }
+    }
+
+    pub fn is_device(self) -> bool {
+        matches!(self, EntryType::BlockDevice | EntryType::CharDevice)
+    }
+
+    // Files and directories keep the colors of their columns and the tree's extension colors
+    pub fn theme_kind(self) -> Option<(&'static str, ThemeColor)> {
+        match self {
+            EntryType::File | EntryType::Dir | EntryType::Unknown => None,
+            EntryType::Symlink => Some(("symlink", ThemeColor::Cyan)),
+            EntryType::Fifo => Some(("fifo", ThemeColor::Yellow)),
+            EntryType::Socket => Some(("socket", ThemeColor::Magenta)),
+            EntryType::BlockDevice | EntryType::CharDevice => {
+                Some(("device", ThemeColor::BrightYellow))
+            }
+        }
+    }
+}
+
+/// The shape of entries in JSON and YAML output, picked with --json-schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum JsonSchema {
+    /// The original schema, e_type in PascalCase
+    V1,
+    /// Lowercase e_type, numeric timestamps, mode and ids next to the display strings
+    V2,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileEntryShort {
+    pub name: String,
+    // Only for names that aren't valid UTF-8, `name` has U+FFFD in their place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_bytes: Option<String>,
+    // The bytes behind `name_bytes`, for --print0 and --shell-quote
+    #[serde(skip)]
+    pub raw_name: Option<Vec<u8>>,
+    // Under --full-path, the name joined to the listed directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    // Under --hyperlink, where the name links to
+    #[serde(skip)]
+    pub url: Option<String>,
+    #[serde(skip)]
+    pub e_type: EntryType,
+    // e_type as --json-schema names it
+    #[serde(rename = "e_type")]
+    pub type_name: String,
+    // Under --kind, what a regular file's first bytes say it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub len_bytes: u64,
+    // Under --link-sizes, the size of a symlink's target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_len_bytes: Option<u64>,
+    #[serde(skip)]
+    pub dangling: bool,
+    pub allocated_bytes: u64,
+    // Device number of block and character devices, shown instead of their size
+    #[serde(skip)]
+    pub rdev: u64,
+    #[serde(skip)]
+    pub executable: bool,
+    pub modified: String,
+    // Under --json-schema v2, the modification time for programs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_iso: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_epoch: Option<i64>,
+    // The --time timestamp, shown in the time column
+    #[serde(skip)]
+    pub shown_at: Option<SystemTime>,
+    pub dev: Option<u64>,
+    pub ino: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<String>,
+    #[serde(skip)]
+    pub accessed_at: Option<SystemTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip)]
+    pub unused: bool,
+    // Under --age or --stale-after, seconds since the last modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_seconds: Option<u64>,
+    #[serde(skip)]
+    pub stale: bool,
+    // Under --hash, the hex digest of a regular file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip)]
+    pub hash_error: Option<String>,
+    // Under --dir-counts, how many entries a directory holds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_count: Option<u64>,
+    #[serde(skip)]
+    pub count_error: Option<String>,
+    // Why the metadata is missing, the other fields are zero or empty then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileEntryLong {
+    pub permissions: String,
+    // Under --json-schema v2, the permission bits and ids as numbers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    // Under --xattrs, the names of the extended attributes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xattrs: Option<Vec<String>>,
+    pub nlink: Option<u64>,
+    pub owner: Option<String>,
+    // Whether the effective uid owns the entry, left out without Unix metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owned_by_caller: Option<bool>,
+    #[serde(skip)]
+    pub root_owned: bool,
+    // Under -Z, the SELinux security context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name_bytes: Option<String>,
+    #[serde(skip)]
+    pub raw_name: Option<Vec<u8>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip)]
+    pub url: Option<String>,
+    #[serde(skip)]
+    pub e_type: EntryType,
+    // e_type as --json-schema names it
+    #[serde(rename = "e_type")]
+    pub type_name: String,
+    // Under --kind, what a regular file's first bytes say it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub len_bytes: u64,
+    // Under --link-sizes, the size of a symlink's target
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_len_bytes: Option<u64>,
+    #[serde(skip)]
+    pub dangling: bool,
+    pub allocated_bytes: u64,
+    // Device number of block and character devices, shown instead of their size
+    #[serde(skip)]
+    pub rdev: u64,
+    #[serde(skip)]
+    pub executable: bool,
+    pub modified: String,
+    // Under --json-schema v2, the modification time for programs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_iso: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_epoch: Option<i64>,
+    // The --time timestamp, shown in the time column
+    #[serde(skip)]
+    pub shown_at: Option<SystemTime>,
+    pub dev: Option<u64>,
+    pub ino: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accessed: Option<String>,
+    #[serde(skip)]
+    pub accessed_at: Option<SystemTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(skip)]
+    pub unused: bool,
+    // Under --age or --stale-after, seconds since the last modification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_seconds: Option<u64>,
+    #[serde(skip)]
+    pub stale: bool,
+    // Under --hash, the hex digest of a regular file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(skip)]
+    pub hash_error: Option<String>,
+    // Under --dir-counts, how many entries a directory holds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_count: Option<u64>,
+    #[serde(skip)]
+    pub count_error: Option<String>,
+    // Why the metadata is missing, the other fields are zero or empty then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Uniform access to the value behind each column for the table, CSV and JSON writers.
+pub trait FieldSource {
+    /// Text of a table cell
+    fn cell(&self, field: Field, cli: &Cli) -> String;
+
+    /// Plain value for CSV, timestamps are ISO 8601 unless --time-style says otherwise
+    fn csv(&self, field: Field, cli: &Cli) -> String;
+
+    fn json(&self, field: Field) -> serde_json::Value;
+
+    /// Color overriding the column color for this entry's cell
+    fn highlight(&self, field: Field, cli: &Cli) -> Option<Color>;
+
+    /// Why the entry's metadata couldn't be read, if it couldn't
+    fn error(&self) -> Option<&str>;
+
+    /// The name in its directory
+    fn name(&self) -> &str;
+
+    /// The name, or the path under --full-path, with the bytes the filesystem has
+    fn exact_name(&self) -> Vec<u8>;
+
+    /// The timestamp --time picked
+    fn shown_at(&self) -> Option<SystemTime>;
+}
+
+impl FieldSource for FileEntryShort {
+    fn cell(&self, field: Field, cli: &Cli) -> String {
+        if needs_metadata(field, self.error()) {
+            return "-".to_string();
+        }
+        match field {
+            Field::Permissions | Field::Links | Field::Owner | Field::Context => String::default(),
+            Field::Inode => number_cell(self.ino),
+            Field::Name => format!(
+                "{}{}",
+                linked(
+                    name_cell(
+                        &self.name,
+                        parent_of(&self.name, self.path.as_deref()),
+                        matches!(self.e_type, EntryType::Dir),
+                        cli
+                    ),
+                    self.url.as_deref()
+                ),
+                classify_suffix(self.e_type, self.executable, cli)
+            ),
+            Field::Type => self.e_type.to_string(),
+            Field::Kind => self.kind.clone().unwrap_or_else(|| "-".to_string()),
+            Field::Size if self.entry_count.is_some() || self.count_error.is_some() => {
+                count_cell(self.entry_count)
+            }
+            Field::Size => {
+                let size = size_or_device(self.e_type, self.len_bytes, self.rdev, cli);
+                link_size_cell(size, self.target_len_bytes, self.dangling, cli)
+            }
+            Field::Allocated => allocated_cell(self.len_bytes, self.allocated_bytes, cli),
+            Field::Modified => time_cell(self.shown_at, cli),
+            Field::Accessed => accessed_cell(self.accessed_at, self.unused, cli),
+            Field::Age => age_cell(self.age_seconds),
+            Field::Hash => hash_cell(self.hash.as_deref(), self.hash_error.is_some(), cli),
+        }
+    }
+
+    fn csv(&self, field: Field, cli: &Cli) -> String {
+        if needs_metadata(field, self.error()) {
+            return String::default();
+        }
+        match field {
+            Field::Name => match (&self.path, &self.name_bytes) {
+                (Some(path), _) => path.clone(),
+                (None, Some(bytes)) => bytes.clone(),
+                (None, None) => names::escape_bytes(self.name.as_bytes()),
+            },
+            Field::Size => self.len_bytes.to_string(),
+            Field::Modified => csv_time(self.shown_at, cli),
+            Field::Accessed => csv_time(self.accessed_at, cli),
+            _ => json_text(self.json(field)),
+        }
+    }
+
+    fn json(&self, field: Field) -> serde_json::Value {
+        if needs_metadata(field, self.error()) {
+            return serde_json::Value::Null;
+        }
+        match field {
+            Field::Permissions | Field::Links | Field::Owner | Field::Context => {
+                serde_json::Value::Null
+            }
+            Field::Inode => self.ino.into(),
+            Field::Name => self.name.clone().into(),
+            Field::Type => self.type_name.clone().into(),
+            Field::Kind => self.kind.clone().into(),
+            Field::Size => self.len_bytes.into(),
+            Field::Allocated => self.allocated_bytes.into(),
+            Field::Modified => self.modified.clone().into(),
+            Field::Accessed => self.accessed.clone().into(),
+            Field::Age => self.age_seconds.into(),
+            Field::Hash => self.hash.clone().into(),
+        }
+    }
+
+    fn highlight(&self, field: Field, cli: &Cli) -> Option<Color> {
+        kind_highlight(field, self.e_type, self.executable, cli)
+            .or_else(|| unused_highlight(field, self.unused))
+            .or_else(|| stale_highlight(field, self.stale))
+            .or_else(|| recency_highlight(field, self.shown_at))
+            .or_else(|| count_highlight(field, self.entry_count, self.count_error.is_some()))
+            .or_else(|| size_highlight(field, self.e_type, self.len_bytes, cli))
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn exact_name(&self) -> Vec<u8> {
+        exact_name(&self.name, self.raw_name.as_deref(), self.path.as_deref())
+    }
+
+    fn shown_at(&self) -> Option<SystemTime> {
+        self.shown_at
+    }
+}
+
+impl FieldSource for FileEntryLong {
+    fn cell(&self, field: Field, cli: &Cli) -> String {
+        if needs_metadata(field, self.error()) {
+            return "-".to_string();
+        }
+        match field {
+            // Markdown is meant to be plain text
+            Field::Permissions if cli.permissions == PermissionStyle::Symbolic && !cli.markdown => {
+                permissions::paint(&self.permissions)
+            }
+            Field::Permissions => self.permissions.clone(),
+            Field::Links => number_cell(self.nlink),
+            Field::Owner => self.owner.clone().unwrap_or_default(),
+            Field::Context => self.context.clone().unwrap_or_else(|| "-".to_string()),
+            Field::Inode => number_cell(self.ino),
+            Field::Name => format!(
+                "{}{}{}",
+                linked(
+                    name_cell(
+                        &self.name,
+                        parent_of(&self.name, self.path.as_deref()),
+                        matches!(self.e_type, EntryType::Dir),
+                        cli
+                    ),
+                    self.url.as_deref()
+                ),
+                classify_suffix(self.e_type, self.executable, cli),
+                xattr_lines(self.xattrs.as_deref(), cli)
+            ),
+            Field::Type => self.e_type.to_string(),
+            Field::Kind => self.kind.clone().unwrap_or_else(|| "-".to_string()),
+            Field::Size if self.entry_count.is_some() || self.count_error.is_some() => {
+                count_cell(self.entry_count)
+            }
+            Field::Size => {
+                let size = size_or_device(self.e_type, self.len_bytes, self.rdev, cli);
+                link_size_cell(size, self.target_len_bytes, self.dangling, cli)
+            }
+            Field::Allocated => allocated_cell(self.len_bytes, self.allocated_bytes, cli),
+            Field::Modified => time_cell(self.shown_at, cli),
+            Field::Accessed => accessed_cell(self.accessed_at, self.unused, cli),
+            Field::Age => age_cell(self.age_seconds),
+            Field::Hash => hash_cell(self.hash.as_deref(), self.hash_error.is_some(), cli),
+        }
+    }
+
+    fn csv(&self, field: Field, cli: &Cli) -> String {
+        if needs_metadata(field, self.error()) {
+            return String::default();
+        }
+        match field {
+            Field::Name => match (&self.path, &self.name_bytes) {
+                (Some(path), _) => path.clone(),
+                (None, Some(bytes)) => bytes.clone(),
+                (None, None) => names::escape_bytes(self.name.as_bytes()),
+            },
+            Field::Size => self.len_bytes.to_string(),
+            Field::Modified => csv_time(self.shown_at, cli),
+            Field::Accessed => csv_time(self.accessed_at, cli),
+            _ => json_text(self.json(field)),
+        }
+    }
+
+    fn json(&self, field: Field) -> serde_json::Value {
+        if needs_metadata(field, self.error()) {
+            return serde_json::Value::Null;
+        }
+        match field {
+            Field::Permissions => self.permissions.clone().into(),
+            Field::Links => self.nlink.into(),
+            Field::Owner => self.owner.clone().into(),
+            Field::Context => self.context.clone().into(),
+            Field::Inode => self.ino.into(),
+            Field::Name => self.name.clone().into(),
+            Field::Type => self.type_name.clone().into(),
+            Field::Kind => self.kind.clone().into(),
+            Field::Size => self.len_bytes.into(),
+            Field::Allocated => self.allocated_bytes.into(),
+            Field::Modified => self.modified.clone().into(),
+            Field::Accessed => self.accessed.clone().into(),
+            Field::Age => self.age_seconds.into(),
+            Field::Hash => self.hash.clone().into(),
+        }
+    }
+
+    fn highlight(&self, field: Field, cli: &Cli) -> Option<Color> {
+        // A file with more than one name is a hard link
+        let hard_link = matches!(self.e_type, EntryType::File) && self.nlink.is_some_and(|n| n > 1);
+        if field == Field::Links && hard_link {
+            return Some(Color::FG_BRIGHT_GREEN | Color::BOLD);
+        }
+        kind_highlight(field, self.e_type, self.executable, cli)
+            .or_else(|| unused_highlight(field, self.unused))
+            .or_else(|| stale_highlight(field, self.stale))
+            .or_else(|| recency_highlight(field, self.shown_at))
+            .or_else(|| count_highlight(field, self.entry_count, self.count_error.is_some()))
+            .or_else(|| size_highlight(field, self.e_type, self.len_bytes, cli))
+            .or_else(|| owner_highlight(field, self.owned_by_caller, self.root_owned))
+    }
+
+    fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn exact_name(&self) -> Vec<u8> {
+        exact_name(&self.name, self.raw_name.as_deref(), self.path.as_deref())
+    }
+
+    fn shown_at(&self) -> Option<SystemTime> {
+        self.shown_at
+    }
+}
+
+// Only the name and the `?` type are known of entries whose metadata couldn't be read
+fn needs_metadata(field: Field, error: Option<&str>) -> bool {
+    error.is_some() && !matches!(field, Field::Name | Field::Type)
+}
+
+// What --full-path puts in front of `name`, paths always end with the name they were built from
+fn parent_of<'a>(name: &str, path: Option<&'a str>) -> &'a str {
+    path.and_then(|path| path.strip_suffix(name))
+        .unwrap_or_default()
+}
+
+/// `cell` as a link to `url` under --hyperlink, unchanged otherwise.
+pub fn linked(cell: String, url: Option<&str>) -> String {
+    match url {
+        Some(url) => hyperlink::wrap(&cell, url),
+        None => cell,
+    }
+}
+
+// Control characters never reach the terminal as they are. Matches of --regex/--match
+// are highlighted and --icons are prepended, except in markdown which is meant to be plain text
+// `parent` is put in front of the name under --full-path, e.g. `src/`
+pub fn name_cell(name: &str, parent: &str, is_dir: bool, cli: &Cli) -> String {
+    let render = |part: &str| names::sanitize(part, cli.escape);
+    let quote = cli.quote_names && names::needs_quoting(&render(&format!("{}{}", parent, name)));
+    if cli.markdown {
+        let plain = render(&format!("{}{}", parent, name));
+        return if quote {
+            names::shell_quote(&plain)
+        } else {
+            plain
+        };
+    }
+    let ranges: Vec<_> = name_patterns(cli)
+        .flat_map(|pattern| pattern.ranges(name))
+        .collect();
+    let highlighted = render(parent) + &pattern::highlight(name, &ranges, render);
+    let highlighted = if quote {
+        names::shell_quote(&highlighted)
+    } else {
+        highlighted
+    };
+
+    if cli.icons == Some(IconsMode::Always) {
+        format!("{} {}", icons::icon_for(name, is_dir), highlighted)
+    } else {
+        highlighted
+    }
+}
+
+// The attribute names under --xattrs, indented below the name in the same cell
+fn xattr_lines(xattrs: Option<&[String]>, cli: &Cli) -> String {
+    let Some(xattrs) = xattrs.filter(|_| !cli.markdown) else {
+        return String::default();
+    };
+    xattrs
+        .iter()
+        .map(|name| format!("\n  {}", names::sanitize(name, cli.escape)))
+        .collect()
+}
+
+// Under --link-sizes a symlink's own size is followed by its target's, `?` when the link
+// dangles
+fn link_size_cell(size: String, target_len: Option<u64>, dangling: bool, cli: &Cli) -> String {
+    match (target_len, dangling) {
+        (Some(len), _) => format!("{} -> {}", size, size_cell(len, cli)),
+        (None, true) => format!("{} -> ?", size),
+        (None, false) => size,
+    }
+}
+
+// Devices have no meaningful length, ls shows their major and minor numbers instead
+fn size_or_device(e_type: EntryType, len_bytes: u64, rdev: u64, cli: &Cli) -> String {
+    if e_type.is_device() {
+        device_numbers(rdev)
+    } else {
+        size_cell(len_bytes, cli)
+    }
+}
+
+/// The major and minor numbers of a device file, like `8, 1`.
+#[cfg(unix)]
+pub fn device_numbers(rdev: u64) -> String {
+    format!("{}, {}", libc::major(rdev), libc::minor(rdev))
+}
+
+// Only Unix has device files
+#[cfg(not(unix))]
+pub fn device_numbers(rdev: u64) -> String {
+    rdev.to_string()
+}
+
+fn number_cell(number: Option<u64>) -> String {
+    number.map_or_else(|| "-".to_string(), |n| n.to_string())
+}
+
+pub fn size_cell(len_bytes: u64, cli: &Cli) -> String {
+    format_size(len_bytes, size_style(cli))
+}
+
+// JSON always carries RFC 3339, --relative-time and --time-style only change how tables show it
+fn time_cell(time: Option<SystemTime>, cli: &Cli) -> String {
+    // e.g. --time created on a filesystem without birth times
+    let Some(time) = time else {
+        return "-".to_string();
+    };
+    cli.relative_time
+        .then(|| duration::relative_time(time, SystemTime::now()))
+        .flatten()
+        .unwrap_or_else(|| time_style::format_time(time, cli.time_style.as_ref(), cli.utc))
+}
+
+// A path ends in the lossy name, which the bytes of the name take the place of
+fn exact_name(name: &str, raw_name: Option<&[u8]>, path: Option<&str>) -> Vec<u8> {
+    let bytes = raw_name.unwrap_or(name.as_bytes());
+    match path {
+        Some(path) => match path.strip_suffix(name) {
+            Some(parent) => [parent.as_bytes(), bytes].concat(),
+            None => path.as_bytes().to_vec(),
+        },
+        None => bytes.to_vec(),
+    }
+}
+
+fn csv_time(time: Option<SystemTime>, cli: &Cli) -> String {
+    match (time, &cli.time_style) {
+        (Some(time), Some(style)) => time_style::format_time(time, Some(style), cli.utc),
+        _ => iso_timestamp(time, cli),
+    }
+}
+
+// Less than half of the logical size on disk means holes, or a compressing filesystem
+fn allocated_cell(len_bytes: u64, allocated_bytes: u64, cli: &Cli) -> String {
+    let allocated = size_cell(allocated_bytes, cli);
+    if allocated_bytes.saturating_mul(2) < len_bytes {
+        format!("{} sparse", allocated)
+    } else {
+        allocated
+    }
+}
+
+// `?` when the directory couldn't be read
+fn count_cell(entry_count: Option<u64>) -> String {
+    match entry_count {
+        Some(0) => "empty".to_string(),
+        Some(1) => "1 entry".to_string(),
+        Some(count) => format!("{} entries", count),
+        None => "?".to_string(),
+    }
+}
+
+fn hash_cell(hash: Option<&str>, failed: bool, cli: &Cli) -> String {
+    const SHORT_LEN: usize = 12;
+    match hash {
+        Some(hash) if !cli.hash_full && hash.len() > SHORT_LEN => {
+            format!("{}…", &hash[..SHORT_LEN])
+        }
+        Some(hash) => hash.to_string(),
+        None if failed => "error".to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn accessed_cell(accessed_at: Option<SystemTime>, unused: bool, cli: &Cli) -> String {
+    let accessed = time_cell(accessed_at, cli);
+    if unused {
+        format!("{} unused", accessed)
+    } else {
+        accessed
+    }
+}
+
+fn age_cell(age_seconds: Option<u64>) -> String {
+    age_seconds.map_or_else(
+        || "-".to_string(),
+        |seconds| duration::short_age(Duration::from_secs(seconds)),
+    )
+}
+
+fn kind_highlight(field: Field, e_type: EntryType, executable: bool, cli: &Cli) -> Option<Color> {
+    if e_type == EntryType::Unknown {
+        return matches!(field, Field::Name | Field::Type).then_some(Color::FG_RED);
+    }
+    if field == Field::Name && executable {
+        return Some(cli.theme.kind("executable", ThemeColor::BrightGreen).cell());
+    }
+    let (kind, default) = e_type.theme_kind()?;
+    matches!(field, Field::Name | Field::Type).then(|| cli.theme.kind(kind, default).cell())
+}
+
+/// The `ls -F` indicator shown after a name under --classify, never part of JSON or CSV.
+pub fn classify_suffix(e_type: EntryType, executable: bool, cli: &Cli) -> &'static str {
+    if !cli.classify {
+        return "";
+    }
+    match e_type {
+        EntryType::Dir => "/",
+        EntryType::Symlink => "@",
+        EntryType::Fifo => "|",
+        EntryType::Socket => "=",
+        EntryType::File if executable => "*",
+        _ => "",
+    }
+}
+
+// Only regular files count, directories always have their search bits set
+pub fn is_executable(e_type: EntryType, mode: u32) -> bool {
+    e_type == EntryType::File && mode & 0o111 != 0
+}
+
+// Recently touched entries stand out, anything older than a week keeps the column color
+fn recency_highlight(field: Field, time: Option<SystemTime>) -> Option<Color> {
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+    const WEEK: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+    if field != Field::Modified {
+        return None;
+    }
+    // Timestamps in the future count as just now
+    let age = SystemTime::now()
+        .duration_since(time?)
+        .unwrap_or(Duration::ZERO);
+    match age {
+        age if age < HOUR => Some(Color::FG_BRIGHT_GREEN),
+        age if age < DAY => Some(Color::FG_GREEN),
+        age if age < WEEK => Some(Color::FG_YELLOW),
+        _ => None,
+    }
+}
+
+// Directory sizes only say something under --du, device sizes never do
+fn size_highlight(field: Field, e_type: EntryType, len_bytes: u64, cli: &Cli) -> Option<Color> {
+    let meaningful = !(e_type == EntryType::Dir && !cli.du || e_type.is_device());
+    if field != Field::Size || !meaningful {
+        return None;
+    }
+    cli.theme.size(len_bytes)
+}
+
+fn count_highlight(field: Field, entry_count: Option<u64>, failed: bool) -> Option<Color> {
+    match (field, entry_count) {
+        (Field::Size, Some(0)) => Some(Color::FG_BRIGHT_BLACK),
+        (Field::Size, None) if failed => Some(Color::FG_RED),
+        _ => None,
+    }
+}
+
+fn unused_highlight(field: Field, unused: bool) -> Option<Color> {
+    (field == Field::Accessed && unused).then(|| Color::FG_BRIGHT_RED | Color::BOLD)
+}
+
+// Owners other than the caller are dimmed, root in yellow as it's rarely a mistake
+fn owner_highlight(field: Field, owned_by_caller: Option<bool>, root_owned: bool) -> Option<Color> {
+    match (field, owned_by_caller) {
+        (Field::Owner, Some(false)) if root_owned => Some(Color::FG_YELLOW),
+        (Field::Owner, Some(false)) => Some(Color::FG_BRIGHT_BLACK),
+        _ => None,
+    }
+}
+
+fn stale_highlight(field: Field, stale: bool) -> Option<Color> {
+    (field == Field::Age && stale).then(|| Color::FG_BRIGHT_RED | Color::BOLD)
+}
+
+fn json_text(value: serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s,
+        serde_json::Value::Null => String::default(),
+        other => other.to_string(),
+    }
+}
+
+/// The metadata of a directory entry that the listings are built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawEntry {
+    // Lossy for names that aren't UTF-8, their bytes are kept in `raw_name`
+    pub name: String,
+    pub raw_name: Option<Vec<u8>>,
+    // Set for --full-path by whoever knows the directory, never cached
+    #[serde(skip)]
+    pub path: Option<String>,
+    // Whether it is or points to a directory
+    pub is_dir: bool,
+    pub e_type: EntryType,
+    pub len: u64,
+    pub rdev: u64,
+    // Bytes actually allocated on disk
+    pub allocated: u64,
+    pub mode: u32,
+    pub nlink: u64,
+    pub uid: u32,
+    pub gid: u32,
+    pub dev: u64,
+    pub ino: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub changed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+    // What the contents look like, only sniffed for --kind and never cached
+    #[serde(skip)]
+    pub kind: Option<String>,
+    // The digest for --hash, or why the file couldn't be hashed
+    #[serde(skip)]
+    pub hash: Option<Result<String, String>>,
+    // The shallow entry count of a directory for --dir-counts, or why it couldn't be read
+    #[serde(skip)]
+    pub entry_count: Option<Result<u64, String>>,
+    // Names of the entry's extended attributes, only read for long listings
+    #[serde(skip)]
+    pub xattrs: Option<Vec<String>>,
+    // The SELinux context, only read for -Z
+    #[serde(skip)]
+    pub context: Option<String>,
+    // The file:// URL of the entry, only for --hyperlink
+    #[serde(skip)]
+    pub url: Option<String>,
+    // What a symlink points to, read once for `is_dir` and reused by --dereference and
+    // --link-sizes, or why it can't be read. Never cached
+    #[serde(skip)]
+    pub target: Option<Result<fs::Metadata, String>>,
+    // The size of a symlink's target for --link-sizes, or why the target can't be read
+    #[serde(skip)]
+    pub target_len: Option<Result<u64, String>>,
+    // Set when only the name could be read, such entries are never cached
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+// The entries this thread read the metadata of, for the tests that show which reads the
+// cache saves
+#[cfg(test)]
+thread_local! {
+    pub static STATS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+impl RawEntry {
+    pub fn from_dir_entry(file: &fs::DirEntry) -> io::Result<RawEntry> {
+        let file_name = file.file_name();
+        let name = file_name.to_string_lossy().to_string();
+        // Usually answered from the directory read itself or a relative fstatat
+        let mut raw = RawEntry::from_metadata(&file.path(), name, file.metadata()?);
+        if file_name.to_str().is_none() {
+            raw.raw_name = Some(file_name.as_encoded_bytes().to_vec());
+        }
+        Ok(raw)
+    }
+
+    /// A row for an entry whose metadata couldn't be read, everything but the name is unknown.
+    pub fn unreadable(file: &fs::DirEntry, e: &io::Error) -> RawEntry {
+        let file_name = file.file_name();
+        RawEntry {
+            name: file_name.to_string_lossy().to_string(),
+            raw_name: file_name
+                .to_str()
+                .is_none()
+                .then(|| file_name.as_encoded_bytes().to_vec()),
+            path: None,
+            is_dir: false,
+            e_type: EntryType::Unknown,
+            len: 0,
+            rdev: 0,
+            allocated: 0,
+            mode: 0,
+            nlink: 0,
+            uid: 0,
+            gid: 0,
+            dev: 0,
+            ino: 0,
+            modified: None,
+            accessed: None,
+            changed: None,
+            created: None,
+            kind: None,
+            hash: None,
+            entry_count: None,
+            xattrs: None,
+            context: None,
+            target: None,
+            target_len: None,
+            url: None,
+            error: Some(e.to_string()),
+        }
+    }
+
+    /// A member of an archive, with what its header records. There are no device or
+    /// inode numbers, nor links to count.
+    pub fn member(name: String, e_type: EntryType, len: u64, mode: u32) -> RawEntry {
+        RawEntry {
+            name,
+            raw_name: None,
+            path: None,
+            is_dir: e_type == EntryType::Dir,
+            e_type,
+            len,
+            rdev: 0,
+            allocated: len,
+            mode,
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            dev: 0,
+            ino: 0,
+            modified: None,
+            accessed: None,
+            changed: None,
+            created: None,
+            kind: None,
+            hash: None,
+            entry_count: None,
+            xattrs: None,
+            context: None,
+            target: None,
+            target_len: None,
+            url: None,
+            error: None,
+        }
+    }
+
+    /// The name as the filesystem has it, which `name` only approximates when it isn't UTF-8.
+    pub fn name_bytes(&self) -> &[u8] {
+        self.raw_name.as_deref().unwrap_or(self.name.as_bytes())
+    }
+
+    pub fn escaped_name(&self) -> Option<String> {
+        self.raw_name.as_deref().map(names::escape_bytes)
+    }
+
+    /// Where the entry lives in `dir`, built from the exact bytes of its name.
+    #[cfg(unix)]
+    pub fn path_in(&self, dir: &Path) -> PathBuf {
+        use std::os::unix::ffi::OsStrExt;
+        dir.join(std::ffi::OsStr::from_bytes(self.name_bytes()))
+    }
+
+    /// Where the entry lives in `dir`. Names that aren't valid Unicode only survive as
+    /// bytes on Unix, elsewhere they are lost to the replacement character.
+    #[cfg(not(unix))]
+    pub fn path_in(&self, dir: &Path) -> PathBuf {
+        dir.join(&self.name)
+    }
+
+    pub fn from_path(path: &Path, name: String) -> io::Result<RawEntry> {
+        Ok(RawEntry::from_metadata(
+            path,
+            name,
+            fs::symlink_metadata(path)?,
+        ))
+    }
+
+    // Everything is the entry's own, symlinks cost a second stat to learn whether they
+    // point to a directory
+    pub fn from_metadata(path: &Path, name: String, meta: fs::Metadata) -> RawEntry {
+        #[cfg(test)]
+        STATS.with(|stats| stats.set(stats.get() + 1));
+        let target = meta
+            .file_type()
+            .is_symlink()
+            .then(|| fs::metadata(path).map_err(|e| e.to_string()));
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut raw = RawEntry {
+            name,
+            raw_name: None,
+            path: None,
+            is_dir: match &target {
+                Some(Ok(target)) => target.is_dir(),
+                _ => meta.is_dir(),
+            },
+            e_type: EntryType::of(meta.file_type()),
+            len: meta.len(),
+            rdev: 0,
+            allocated: meta.len(),
+            // Without mode bits all that is known is whether the entry can be written
+            mode: if meta.permissions().readonly() {
+                0o444
+            } else {
+                0o666
+            },
+            nlink: 1,
+            uid: 0,
+            gid: 0,
+            dev: 0,
+            ino: 0,
+            modified: meta.modified().ok(),
+            accessed: meta.accessed().ok(),
+            changed: None,
+            // Not every filesystem records a birth time
+            created: meta.created().ok(),
+            kind: None,
+            hash: None,
+            entry_count: None,
+            xattrs: None,
+            context: None,
+            target: None,
+            target_len: None,
+            url: None,
+            error: None,
+        };
+        #[cfg(unix)]
+        raw.read_unix_metadata(&meta);
+        raw.target = target;
+        raw
+    }
+
+    #[cfg(unix)]
+    fn read_unix_metadata(&mut self, meta: &fs::Metadata) {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        self.rdev = meta.rdev();
+        // st_blocks is always in 512 byte units, whatever the filesystem block size
+        self.allocated = meta.blocks() * 512;
+        self.mode = meta.permissions().mode();
+        self.nlink = meta.nlink();
+        self.uid = meta.uid();
+        self.gid = meta.gid();
+        // A symlink's own identity, so -i and hard links never mistake it for its target
+        self.dev = meta.dev();
+        self.ino = meta.ino();
+        self.changed = u64::try_from(meta.ctime())
+            .ok()
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::new(secs, meta.ctime_nsec() as u32));
+    }
+
+    /// Replaces the metadata of a symlink by that of its target, for --dereference, the
+    /// only place where a link gets its target's. The name stays the link's, a dangling
+    /// link keeps its own metadata and gets an error.
+    pub fn dereference(&mut self, path: &Path) {
+        if self.e_type != EntryType::Symlink || self.error.is_some() {
+            return;
+        }
+        match self.take_target(path) {
+            Ok(meta) => {
+                let raw_name = self.raw_name.take();
+                *self = RawEntry::from_metadata(path, std::mem::take(&mut self.name), meta);
+                self.raw_name = raw_name;
+            }
+            Err(e) => self.error = Some(format!("dangling symlink: {}", e)),
+        }
+    }
+
+    // The target read with the link, only cached entries need another stat
+    fn take_target(&mut self, path: &Path) -> Result<fs::Metadata, String> {
+        self.target
+            .take()
+            .unwrap_or_else(|| fs::metadata(path).map_err(|e| e.to_string()))
+    }
+
+    /// For --link-sizes, keeps the size of a symlink's target in `target_len`, next to the
+    /// size of the link itself, the length of the path it holds. Anything but a symlink is
+    /// left as it is.
+    pub fn read_target_len(&mut self, path: &Path) {
+        if self.e_type != EntryType::Symlink || self.error.is_some() {
+            return;
+        }
+        let target = self.take_target(path);
+        self.target_len = Some(target.as_ref().map(|meta| meta.len()).map_err(Clone::clone));
+        self.target = Some(target);
+    }
+
+    /// The size --sort size and --largest go by, that of its target for a symlink under
+    /// --link-sizes.
+    pub fn ranked_len(&self) -> u64 {
+        match self.target_len {
+            Some(Ok(len)) => len,
+            _ => self.len,
+        }
+    }
+
+    /// `.` or `..`, which -a adds to listings.
+    pub fn is_dot_entry(&self) -> bool {
+        self.name == "." || self.name == ".."
+    }
+
+    // Unlike `is_dir`, false for symlinks to directories, which are filtered, summed and
+    // walked as files
+    pub fn is_real_dir(&self) -> bool {
+        self.e_type == EntryType::Dir
+    }
+
+    pub fn time(&self, kind: TimeKind) -> Option<SystemTime> {
+        match kind {
+            TimeKind::Modified => self.modified,
+            TimeKind::Accessed => self.accessed,
+            TimeKind::Changed => self.changed,
+            TimeKind::Created => self.created,
+        }
+    }
+}
+
+pub fn map_short_data(raw: RawEntry, cli: &Cli) -> FileEntryShort {
+    let unused = is_unused(&raw, cli);
+    let (age_seconds, stale) = age(&raw, cli);
+    let v2 = cli.json_schema == JsonSchema::V2;
+    let shown_at = raw.time(cli.time);
+    FileEntryShort {
+        name_bytes: raw.escaped_name(),
+        raw_name: raw.raw_name.clone(),
+        path: raw.path,
+        url: raw.url,
+        name: raw.name,
+        e_type: raw.e_type,
+        type_name: raw.e_type.json_name(cli.json_schema),
+        kind: raw.kind,
+        len_bytes: raw.len,
+        target_len_bytes: raw
+            .target_len
+            .as_ref()
+            .and_then(|len| len.as_ref().ok().copied()),
+        dangling: raw.target_len.as_ref().is_some_and(Result::is_err),
+        allocated_bytes: raw.allocated,
+        rdev: raw.rdev,
+        executable: is_executable(raw.e_type, raw.mode),
+        modified: iso_timestamp(raw.modified, cli),
+        modified_iso: v2.then(|| iso_timestamp(raw.modified, cli)),
+        modified_epoch: raw.modified.filter(|_| v2).map(time_style::epoch_seconds),
+        shown_at,
+        dev: UNIX_METADATA.then_some(raw.dev),
+        ino: UNIX_METADATA.then_some(raw.ino),
+        accessed: raw.accessed.map(|t| time_style::rfc3339(t, cli.utc)),
+        accessed_at: raw.accessed,
+        changed: raw.changed.map(|t| time_style::rfc3339(t, cli.utc)),
+        created: raw.created.map(|t| time_style::rfc3339(t, cli.utc)),
+        unused,
+        age_seconds,
+        stale,
+        hash_error: raw.hash.as_ref().and_then(|h| h.as_ref().err().cloned()),
+        hash: raw.hash.and_then(Result::ok),
+        count_error: raw
+            .entry_count
+            .as_ref()
+            .and_then(|c| c.as_ref().err().cloned()),
+        entry_count: raw.entry_count.and_then(Result::ok),
+        error: raw.error,
+    }
+}
+
+// Whether --unused applies to the entry, entries without an access time never count
+fn is_unused(raw: &RawEntry, cli: &Cli) -> bool {
+    let (Some(threshold), Some(accessed)) = (cli.unused, raw.accessed) else {
+        return false;
+    };
+    SystemTime::now()
+        .duration_since(accessed)
+        .is_ok_and(|age| age > threshold)
+}
+
+// Under --age and --stale-after, the seconds since the entry was modified and whether that
+// is past --stale-after. Modification times in the future count as no age at all.
+fn age(raw: &RawEntry, cli: &Cli) -> (Option<u64>, bool) {
+    if !needs_ages(cli) {
+        return (None, false);
+    }
+    let Some(modified) = raw.modified else {
+        return (None, false);
+    };
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    let stale = cli.stale_after.is_some_and(|threshold| age > threshold);
+    if stale {
+        failures::record_stale();
+    }
+    (Some(age.as_secs()), stale)
+}
+
+pub fn map_long_data(raw: RawEntry, cli: &Cli) -> FileEntryLong {
+    let unused = is_unused(&raw, cli);
+    let (age_seconds, stale) = age(&raw, cli);
+    let shown_at = raw.time(cli.time);
+    // Mode and uid are zero when the metadata is missing, which would look like root's
+    let v2 = cli.json_schema == JsonSchema::V2;
+    // Only real mode bits and ids, never the zeros of missing metadata
+    let numeric = v2 && UNIX_METADATA && raw.error.is_none();
+    let owned_by_caller =
+        (UNIX_METADATA && raw.error.is_none()).then(|| owners::caller_uid() == Some(raw.uid));
+    let (permissions, owner) = if raw.error.is_some() {
+        (String::default(), None)
+    } else {
+        let marker = raw
+            .xattrs
+            .as_deref()
+            .map(xattrs::marker)
+            .unwrap_or_default();
+        (
+            format!("{}{}", mode_text(raw.e_type, raw.mode, cli), marker),
+            UNIX_METADATA.then(|| owners::owner_name(raw.uid, cli.numeric_ids)),
+        )
+    };
+
+    FileEntryLong {
+        permissions,
+        mode: numeric.then_some(raw.mode & 0o7777),
+        uid: numeric.then_some(raw.uid),
+        gid: numeric.then_some(raw.gid),
+        nlink: UNIX_METADATA.then_some(raw.nlink),
+        owner,
+        owned_by_caller,
+        root_owned: raw.uid == 0,
+        name_bytes: raw.escaped_name(),
+        raw_name: raw.raw_name.clone(),
+        path: raw.path,
+        url: raw.url,
+        name: raw.name,
+        e_type: raw.e_type,
+        type_name: raw.e_type.json_name(cli.json_schema),
+        kind: raw.kind,
+        len_bytes: raw.len,
+        target_len_bytes: raw
+            .target_len
+            .as_ref()
+            .and_then(|len| len.as_ref().ok().copied()),
+        dangling: raw.target_len.as_ref().is_some_and(Result::is_err),
+        allocated_bytes: raw.allocated,
+        rdev: raw.rdev,
+        executable: is_executable(raw.e_type, raw.mode),
+        modified: iso_timestamp(raw.modified, cli),
+        modified_iso: v2.then(|| iso_timestamp(raw.modified, cli)),
+        modified_epoch: raw.modified.filter(|_| v2).map(time_style::epoch_seconds),
+        shown_at,
+        dev: UNIX_METADATA.then_some(raw.dev),
+        ino: UNIX_METADATA.then_some(raw.ino),
+        accessed: raw.accessed.map(|t| time_style::rfc3339(t, cli.utc)),
+        accessed_at: raw.accessed,
+        changed: raw.changed.map(|t| time_style::rfc3339(t, cli.utc)),
+        created: raw.created.map(|t| time_style::rfc3339(t, cli.utc)),
+        unused,
+        age_seconds,
+        stale,
+        hash_error: raw.hash.as_ref().and_then(|h| h.as_ref().err().cloned()),
+        hash: raw.hash.and_then(Result::ok),
+        count_error: raw
+            .entry_count
+            .as_ref()
+            .and_then(|c| c.as_ref().err().cloned()),
+        entry_count: raw.entry_count.and_then(Result::ok),
+        xattrs: raw.xattrs.filter(|_| cli.xattrs),
+        context: raw.context,
+        error: raw.error,
+    }
+}
+
+fn mode_text(e_type: EntryType, mode: u32, cli: &Cli) -> String {
+    match cli.permissions {
+        PermissionStyle::Octal => permissions_text(mode),
+        PermissionStyle::Symbolic => permissions::symbolic(e_type, mode),
+    }
+}
+
+#[cfg(unix)]
+fn permissions_text(mode: u32) -> String {
+    format!("{:o}", mode & 0o777)
+}
+
+// Without mode bits, whether the entry is read only
+#[cfg(not(unix))]
+fn permissions_text(mode: u32) -> String {
+    if mode & 0o222 == 0 { "ro" } else { "rw" }.to_string()
+}
+
+fn iso_timestamp(time: Option<SystemTime>, cli: &Cli) -> String {
+    time.map(|t| time_style::rfc3339(t, cli.utc))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, cli};
+
+    // Latin-1 spellings of café and cafè, which aren't UTF-8
+    #[cfg(unix)]
+    #[test]
+    fn names_that_arent_utf8_round_trip() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let fixture = Fixture::new();
+        for (name, contents) in [(b"caf\xe9", "acute"), (b"caf\xe8", "grave")] {
+            fs::write(fixture.path().join(OsStr::from_bytes(name)), contents).unwrap();
+        }
+        let listing = crate::collect::collect_entries(fixture.path(), &cli(&[]), |raw, _| raw);
+
+        let names: Vec<_> = listing
+            .entries
+            .iter()
+            .map(|raw| raw.name.as_str())
+            .collect();
+        assert_eq!(names, ["caf\u{fffd}", "caf\u{fffd}"]);
+        // The bytes decide the order and keep the two apart
+        assert_eq!(listing.entries[0].name_bytes(), b"caf\xe8");
+        assert_eq!(listing.entries[1].name_bytes(), b"caf\xe9");
+        let contents: Vec<_> = listing
+            .entries
+            .iter()
+            .map(|raw| fs::read_to_string(raw.path_in(fixture.path())).unwrap())
+            .collect();
+        assert_eq!(contents, ["grave", "acute"]);
+
+        let json =
+            serde_json::to_value(map_short_data(listing.entries[1].clone(), &cli(&[]))).unwrap();
+        assert_eq!(json["name"], "caf\u{fffd}");
+        assert_eq!(json["name_bytes"], "caf\\xe9");
+    }
+
+    // A name spelling out `\xff` and one holding the byte can't print the same
+    #[cfg(unix)]
+    #[test]
+    fn escaped_names_stay_apart_in_csv() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let fixture = Fixture::new();
+        fixture.file("bad\\xff", b"");
+        fs::write(fixture.path().join(OsStr::from_bytes(b"bad\xff")), b"").unwrap();
+        let cli = cli(&[]);
+        let listing = crate::collect::collect_entries(fixture.path(), &cli, map_short_data);
+        let names: Vec<_> = listing
+            .entries
+            .iter()
+            .map(|entry| entry.csv(Field::Name, &cli))
+            .collect();
+        assert_eq!(names, ["bad\\\\xff", "bad\\xff"]);
+    }
+
+    #[cfg(unix)]
+    fn links() -> Fixture {
+        let fixture = Fixture::new();
+        fixture
+            .file("a.txt", b"target")
+            .dir("d")
+            .symlink("a.txt", "link")
+            .symlink("d", "dl")
+            .symlink("nowhere", "dead");
+        fixture
+    }
+
+    #[cfg(unix)]
+    fn entry(fixture: &Fixture, name: &str) -> RawEntry {
+        RawEntry::from_path(&fixture.join(name), name.to_string()).unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_keep_their_own_metadata() {
+        let fixture = links();
+        let link = entry(&fixture, "link");
+        let target = entry(&fixture, "a.txt");
+        assert_eq!(link.e_type, EntryType::Symlink);
+        // The length of the path it holds
+        assert_eq!(link.len, 5);
+        assert_ne!(link.ino, target.ino);
+        assert_eq!(link.mode & 0o777, 0o777);
+        assert!(!link.is_dir);
+        assert!(matches!(&link.target, Some(Ok(meta)) if meta.len() == 6));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn links_to_directories_are_walked_as_files() {
+        let link = entry(&links(), "dl");
+        assert_eq!(link.e_type, EntryType::Symlink);
+        assert!(link.is_dir);
+        assert!(!link.is_real_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_links_are_still_listed() {
+        let fixture = links();
+        let mut dead = entry(&fixture, "dead");
+        assert!(!dead.is_dir);
+        assert!(matches!(dead.target, Some(Err(_))));
+        assert_eq!(dead.error, None);
+        dead.dereference(&fixture.join("dead"));
+        assert!(dead.error.unwrap().starts_with("dangling symlink: "));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dereference_puts_the_target_in_place() {
+        let fixture = links();
+        let mut link = entry(&fixture, "link");
+        link.dereference(&fixture.join("link"));
+        let target = entry(&fixture, "a.txt");
+        assert_eq!(link.name, "link");
+        assert_eq!(link.e_type, EntryType::File);
+        assert_eq!((link.len, link.ino), (target.len, target.ino));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn link_sizes_keep_both() {
+        let fixture = links();
+        let mut link = entry(&fixture, "link");
+        link.read_target_len(&fixture.join("link"));
+        assert_eq!(link.len, 5);
+        assert_eq!(link.target_len, Some(Ok(6)));
+        assert_eq!(link.ranked_len(), 6);
+        // Still there for --dereference
+        assert!(link.target.is_some());
+
+        let mut file = entry(&fixture, "a.txt");
+        file.read_target_len(&fixture.join("a.txt"));
+        assert_eq!(file.target_len, None);
+    }
+
+    // Every entry is read from its own metadata once, however many there are
+    #[test]
+    fn large_directories() {
+        let fixture = Fixture::new();
+        for i in 0..5000 {
+            if i % 50 == 0 {
+                fixture.dir(&format!("dir{:04}", i));
+            } else {
+                fixture.file(&format!("file{:04}", i), &vec![b'x'; i % 7]);
+            }
+        }
+        let listing = crate::collect::collect_entries(fixture.path(), &cli(&[]), |raw, _| raw);
+        assert_eq!(listing.entries.len(), 5000);
+        assert_eq!(listing.summary.directories, 100);
+        let expected: u64 = (0..5000).filter(|i| i % 50 != 0).map(|i| i % 7).sum();
+        let files: u64 = listing
+            .entries
+            .iter()
+            .filter(|raw| !raw.is_dir)
+            .map(|raw| raw.len)
+            .sum();
+        assert_eq!(files, expected);
+
+        let tree = crate::tree::tree_data(fixture.path(), &cli(&["--tree", "--depth", "1"]));
+        let children = tree.children.unwrap();
+        assert_eq!(children.len(), 5000);
+        assert_eq!(children[99].entry.name, "dir4950");
+        assert_eq!(children[100].entry.name, "file0001");
+    }
+
+    #[test]
+    fn utf8_names_have_no_bytes_field() {
+        let fixture = Fixture::new();
+        fixture.file("café", b"");
+        let raw = RawEntry::from_path(&fixture.join("café"), "café".to_string()).unwrap();
+        assert_eq!(raw.escaped_name(), None);
+        let json = serde_json::to_value(map_short_data(raw, &cli(&[]))).unwrap();
+        assert!(json.get("name_bytes").is_none());
+    }
+}
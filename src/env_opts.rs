@@ -0,0 +1,230 @@
+use clap::error::{ContextKind, ContextValue, Error};
+use std::env;
+use std::ffi::OsString;
+
+/// Options read before those of the command line, e.g. `--long --sort time`.
+pub const VAR: &str = "BETTER_LS_OPTS";
+
+/// The words of $BETTER_LS_OPTS, split like a shell would: on whitespace, with single
+/// and double quotes and backslashes to keep spaces in a word. Empty when it isn't set.
+pub fn words() -> Result<Vec<String>, String> {
+    words_of(env::var(VAR))
+}
+
+fn words_of(value: Result<String, env::VarError>) -> Result<Vec<String>, String> {
+    match value {
+        Ok(text) => split(&text),
+        Err(env::VarError::NotPresent) => Ok(Vec::new()),
+        Err(env::VarError::NotUnicode(_)) => Err("not valid UTF-8".to_string()),
+    }
+}
+
+/// The options behind `e` that were only given in the variable, `words`, and not among
+/// the arguments after them, e.g. a `--json` there that conflicts with `--csv` here.
+pub fn blamed(e: &Error, words: &[OsString], args: &[OsString]) -> Vec<String> {
+    let given = |options: &[OsString], flag: &str| {
+        options.iter().take_while(|arg| *arg != "--").any(|arg| {
+            let arg = arg.to_string_lossy();
+            arg == flag || arg.starts_with(&format!("{}=", flag))
+        })
+    };
+    let mut blamed = Vec::new();
+    for (kind, value) in e.context() {
+        let values = match (kind, value) {
+            (ContextKind::InvalidArg | ContextKind::PriorArg, ContextValue::String(value)) => {
+                vec![value.as_str()]
+            }
+            (ContextKind::InvalidArg | ContextKind::PriorArg, ContextValue::Strings(values)) => {
+                values.iter().map(String::as_str).collect()
+            }
+            _ => continue,
+        };
+        // Options are named with their value, like `--sort <KEY>`
+        for flag in values
+            .into_iter()
+            .filter_map(|v| v.split_whitespace().next())
+        {
+            if given(words, flag) && !given(args, flag) && !blamed.iter().any(|b| b == flag) {
+                blamed.push(flag.to_string());
+            }
+        }
+    }
+    blamed
+}
+
+/// Splits `text` into words. There are no variables, globs or other expansions.
+pub fn split(text: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        // Only these lose their backslash, like in sh
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_default().push(c),
+                None => return Err("trailing backslash".to_string()),
+            },
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use crate::sort::SortKey;
+
+    fn split_ok(text: &str) -> Vec<String> {
+        split(text).unwrap_or_else(|e| panic!("{:?} doesn't split: {}", text, e))
+    }
+
+    #[test]
+    fn words_are_split_on_whitespace() {
+        assert_eq!(
+            split_ok("--long  --all\t--sort time\n"),
+            ["--long", "--all", "--sort", "time"]
+        );
+        assert_eq!(split_ok("   "), Vec::<String>::new());
+        assert_eq!(split_ok(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn quotes_and_backslashes_keep_spaces() {
+        assert_eq!(
+            split_ok("--ignore-glob '*.o *.a'"),
+            ["--ignore-glob", "*.o *.a"]
+        );
+        assert_eq!(
+            split_ok(r#"--time-style "+%d %b""#),
+            ["--time-style", "+%d %b"]
+        );
+        assert_eq!(split_ok(r"my\ dir"), ["my dir"]);
+        assert_eq!(split_ok(r#"a'b c'"d e"f"#), ["ab cd ef"]);
+        assert_eq!(split_ok("'' x"), ["", "x"]);
+        // Inside single quotes nothing is special, inside double quotes only a few escapes
+        assert_eq!(split_ok(r"'a\b'"), [r"a\b"]);
+        assert_eq!(split_ok(r#""\"\\\$\`\n""#), [r#""\$`\n"#]);
+    }
+
+    #[test]
+    fn unfinished_words_are_errors() {
+        assert_eq!(
+            split("--sort 'time").unwrap_err(),
+            "unterminated single quote"
+        );
+        assert_eq!(
+            split(r#"--sort "time"#).unwrap_err(),
+            "unterminated double quote"
+        );
+        assert_eq!(split(r#""time\"#).unwrap_err(), "unterminated double quote");
+        assert_eq!(split(r"--all \").unwrap_err(), "trailing backslash");
+    }
+
+    #[test]
+    fn an_unset_variable_has_no_words() {
+        assert_eq!(words_of(Err(env::VarError::NotPresent)), Ok(Vec::new()));
+        assert_eq!(words_of(Ok("-l -a".to_string())).unwrap(), ["-l", "-a"]);
+        let invalid = std::ffi::OsString::from("-l");
+        assert_eq!(
+            words_of(Err(env::VarError::NotUnicode(invalid))).unwrap_err(),
+            "not valid UTF-8"
+        );
+    }
+
+    // The command line the binary parses: the variable's words, then the arguments
+    fn with_opts(opts: &str, args: &[&str]) -> Cli {
+        let words = split_ok(opts);
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+        crate::fixture::cli(&[&words[..], args].concat())
+    }
+
+    #[test]
+    fn the_variable_sets_defaults() {
+        let cli = with_opts("--long --all --sort time", &[]);
+        assert!(cli.long && cli.all);
+        assert_eq!(cli.sort, SortKey::Time);
+    }
+
+    #[test]
+    fn the_command_line_overrides_the_variable() {
+        let cli = with_opts(
+            "--long --all --sort time",
+            &["--no-long", "--no-all", "--sort", "size"],
+        );
+        assert!(!cli.long && !cli.all && !cli.almost_all);
+        assert_eq!(cli.sort, SortKey::Size);
+    }
+
+    #[test]
+    fn sort_and_t_undo_each_other() {
+        assert_eq!(with_opts("-t", &["--sort", "name"]).sort, SortKey::Name);
+        assert_eq!(with_opts("--sort name", &["-t"]).sort, SortKey::Time);
+        assert_eq!(with_opts("-t", &[]).sort, SortKey::Time);
+        assert_eq!(with_opts("-t", &["-U"]).sort, SortKey::None);
+        assert_eq!(with_opts("-U", &["-t"]).sort, SortKey::Time);
+        assert_eq!(with_opts("-U", &["--sort=size"]).sort, SortKey::Size);
+    }
+
+    fn blamed_for(opts: &str, args: &[&str]) -> Vec<String> {
+        let words: Vec<OsString> = split_ok(opts).into_iter().map(OsString::from).collect();
+        let args: Vec<OsString> = args.iter().map(OsString::from).collect();
+        let all =
+            std::iter::once(OsString::from("best-ls")).chain(words.iter().chain(&args).cloned());
+        let e = <Cli as clap::CommandFactory>::command()
+            .try_get_matches_from(all)
+            .expect_err("the options don't conflict");
+        blamed(&e, &words, &args)
+    }
+
+    #[test]
+    fn conflicts_name_the_options_from_the_variable() {
+        assert_eq!(blamed_for("--json -l", &["--csv"]), ["--json"]);
+        assert_eq!(blamed_for("--csv", &["--json"]), ["--csv"]);
+        // Not when the command line has the option as well, or causes the error alone
+        assert!(blamed_for("--json", &["--json", "--csv"]).is_empty());
+        assert!(blamed_for("-l", &["--json", "--csv"]).is_empty());
+        assert!(blamed_for("-l", &["--sort", "sideways"]).is_empty());
+    }
+
+    #[test]
+    fn the_variable_cannot_override_the_command_line() {
+        let cli = with_opts(
+            "--no-long --no-all --sort name",
+            &["-l", "-A", "--sort", "time"],
+        );
+        assert!(cli.long && cli.almost_all);
+        assert_eq!(cli.sort, SortKey::Time);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::RawEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tabled::Tabled;
+
+/// What files without an extension are grouped under, dotfiles like `.bashrc` included.
+pub const NO_EXTENSION: &str = "(none)";
+
+/// One row of `--by-extension`.
+#[derive(Debug, Tabled, Serialize)]
+pub struct ExtensionUsage {
+    #[tabled(rename = "Extension")]
+    pub extension: String,
+    #[tabled(rename = "Files")]
+    pub files: u64,
+    #[tabled(rename = "Size B")]
+    pub len_bytes: u64,
+    #[tabled(rename = "Share", display = "display_share")]
+    pub percent: f64,
+}
+
+fn display_share(percent: &f64) -> String {
+    format!("{:.1}%", percent)
+}
+
+// The last extension of `name` in lowercase, so `photo.JPG` and `scan.jpg` add up
+fn extension_of(name: &str) -> String {
+    Path::new(name)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .unwrap_or_else(|| NO_EXTENSION.to_string())
+}
+
+/// Groups the files among `entries` by extension, largest total size first, directories
+/// are left out. Extensions with the same size are ordered by name so the output is stable.
+pub fn aggregate<'a>(entries: impl IntoIterator<Item = &'a RawEntry>) -> Vec<ExtensionUsage> {
+    let mut by_extension: HashMap<String, ExtensionUsage> = HashMap::new();
+
+    for raw in entries.into_iter().filter(|raw| !raw.is_dir) {
+        let extension = extension_of(&raw.name);
+        let usage = by_extension
+            .entry(extension.clone())
+            .or_insert_with(|| ExtensionUsage {
+                extension,
+                files: 0,
+                len_bytes: 0,
+                percent: 0.0,
+            });
+        usage.files += 1;
+        usage.len_bytes += raw.len;
+    }
+
+    let total: u64 = by_extension.values().map(|u| u.len_bytes).sum();
+    let mut rows: Vec<_> = by_extension.into_values().collect();
+    for row in &mut rows {
+        if total > 0 {
+            row.percent = row.len_bytes as f64 * 100.0 / total as f64;
+        }
+    }
+
+    rows.sort_by(|a, b| {
+        b.len_bytes
+            .cmp(&a.len_bytes)
+            .then_with(|| a.extension.cmp(&b.extension))
+    });
+    rows
+}
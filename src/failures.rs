@@ -0,0 +1,50 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+// Set by whichever walker first fails to read something, decides the exit status
+static PARTIAL: AtomicBool = AtomicBool::new(false);
+// Entries listed by name only, reported once at the end
+static UNREADABLE_ENTRIES: AtomicUsize = AtomicUsize::new(0);
+// Directories marked as unreadable in trees and -R listings, reported once at the end
+static UNREADABLE_DIRS: AtomicUsize = AtomicUsize::new(0);
+// Whether an entry older than --stale-after was listed
+static STALE: AtomicBool = AtomicBool::new(false);
+
+/// Notes that part of the listing couldn't be read, the rest is still shown.
+pub fn record() {
+    PARTIAL.store(true, Ordering::Relaxed);
+}
+
+/// Notes an entry that is listed without its metadata.
+pub fn record_entry() {
+    UNREADABLE_ENTRIES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Notes a directory shown as unreadable rather than as empty.
+pub fn record_unreadable_dir() {
+    UNREADABLE_DIRS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// How many directories were shown as unreadable.
+pub fn unreadable_dirs() -> usize {
+    UNREADABLE_DIRS.load(Ordering::Relaxed)
+}
+
+/// Notes an entry past --stale-after.
+pub fn record_stale() {
+    STALE.store(true, Ordering::Relaxed);
+}
+
+/// Whether any listed entry was past --stale-after.
+pub fn stale() -> bool {
+    STALE.load(Ordering::Relaxed)
+}
+
+/// How many entries were listed without their metadata.
+pub fn entries() -> usize {
+    UNREADABLE_ENTRIES.load(Ordering::Relaxed)
+}
+
+/// Whether anything couldn't be read during this run.
+pub fn any() -> bool {
+    PARTIAL.load(Ordering::Relaxed) || entries() > 0 || unreadable_dirs() > 0
+}
@@ -0,0 +1,90 @@
+use crate::cli::{Cli, shows_hidden};
+use crate::collect::{Probes, is_listed, stat_entries};
+use crate::entry::RawEntry;
+use crate::report::unreadable;
+use crate::sort::compare_names;
+use crate::tree::descends;
+use crate::{deadline, failures, ignore};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+/// Whether `name` contains what --find looks for. Case is ignored unless `needle` has
+/// uppercase letters itself.
+pub fn name_contains(name: &str, needle: &str) -> bool {
+    if needle.chars().any(char::is_uppercase) {
+        name.contains(needle)
+    } else {
+        name.to_lowercase().contains(needle)
+    }
+}
+
+/// Hands every entry below `path`, down to --depth levels, whose name contains `needle`
+/// and that passes the filters to `f` along with the directory it is in. Entries come in
+/// the order of their paths, which are set relative to `path`. Each directory is handed
+/// out before the next one is read, so nothing but the current branch is kept in memory.
+pub fn walk(
+    path: &Path,
+    needle: &str,
+    cli: &Cli,
+    f: &mut impl FnMut(&Path, RawEntry) -> ControlFlow<()>,
+) {
+    let _ = walk_dir(path, Path::new(""), 0, needle, cli, f);
+}
+
+fn walk_dir(
+    dir: &Path,
+    relative: &Path,
+    depth: usize,
+    needle: &str,
+    cli: &Cli,
+    f: &mut impl FnMut(&Path, RawEntry) -> ControlFlow<()>,
+) -> ControlFlow<()> {
+    if depth >= cli.depth || deadline::expired() {
+        return ControlFlow::Continue(());
+    }
+    let files: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.inspect_err(|e| unreadable(cli, dir, e)).ok())
+            .collect(),
+        Err(e) => {
+            unreadable(cli, dir, &e);
+            return ControlFlow::Continue(());
+        }
+    };
+    // Only matches are worth probing, every entry is needed to descend
+    let walking = Probes {
+        dereference: cli.dereference,
+        ..Probes::default()
+    };
+    let mut entries = Vec::new();
+    stat_entries(&files, walking, |raw| {
+        entries.push(raw);
+        ControlFlow::Continue(())
+    })?;
+    entries.sort_by(|a, b| compare_names(a, b, cli.sort_case));
+
+    for mut raw in entries {
+        // Nothing inside a hidden directory is found without --all
+        if !shows_hidden(cli) && raw.name.starts_with('.') {
+            continue;
+        }
+        if ignore::ignores(dir, &raw, cli) {
+            continue;
+        }
+        let entry_relative = raw.path_in(relative);
+        let subdir = descends(dir, &raw, cli).then(|| raw.path_in(dir));
+        if name_contains(&raw.name, needle) && is_listed(&raw, cli) {
+            if raw.error.is_some() {
+                failures::record_entry();
+            }
+            Probes::of(cli).probe(&raw.path_in(dir), &mut raw);
+            raw.path = Some(entry_relative.to_string_lossy().to_string());
+            f(dir, raw)?;
+        }
+        if let Some(subdir) = subdir {
+            walk_dir(&subdir, &entry_relative, depth + 1, needle, cli, f)?;
+        }
+    }
+    ControlFlow::Continue(())
+}
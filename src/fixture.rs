@@ -0,0 +1,93 @@
+//! Temporary directories with known contents and times for the unit tests, and command
+//! lines parsed the way the binary parses them.
+
+use crate::cli::Cli;
+use crate::color::Plain;
+use clap::Parser;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+/// 2023-11-14 22:13:20 UTC, the modification time of everything a fixture creates.
+pub fn mtime() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+}
+
+/// `args` parsed as if they followed `best-ls` on the command line. The config file and
+/// $BETTER_LS_OPTS are only read by the binary, so they never get in the way.
+pub fn cli(args: &[&str]) -> Cli {
+    Cli::try_parse_from(std::iter::once("best-ls").chain(args.iter().copied()))
+        .unwrap_or_else(|e| panic!("{:?} doesn't parse: {}", args, e))
+}
+
+/// What `render` writes, without colors.
+pub fn output(render: impl FnOnce(&mut Plain<Vec<u8>>) -> io::Result<()>) -> String {
+    String::from_utf8(output_bytes(render)).expect("output isn't UTF-8")
+}
+
+/// `output` for names that needn't be UTF-8.
+pub fn output_bytes(render: impl FnOnce(&mut Plain<Vec<u8>>) -> io::Result<()>) -> Vec<u8> {
+    let mut out = Plain::new(Vec::new(), true);
+    render(&mut out).expect("rendering into memory fails");
+    out.into_inner()
+}
+
+/// A directory that is removed again when the fixture is dropped.
+pub struct Fixture {
+    dir: TempDir,
+}
+
+impl Fixture {
+    pub fn new() -> Fixture {
+        Fixture {
+            dir: TempDir::new().expect("cannot create a temporary directory"),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Where `relative` lives in the fixture.
+    pub fn join(&self, relative: &str) -> PathBuf {
+        self.dir.path().join(relative)
+    }
+
+    /// Creates the file `relative` holding `contents`, with its parents, modified at `mtime`.
+    pub fn file(&self, relative: &str, contents: &[u8]) -> &Fixture {
+        let path = self.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("cannot create directories");
+        }
+        let mut file = File::create(&path).expect("cannot create a file");
+        file.write_all(contents).expect("cannot write a file");
+        file.set_modified(mtime())
+            .expect("cannot set a modification time");
+        self
+    }
+
+    /// Creates the directory `relative` with its parents, modified at `mtime`. Directories
+    /// get their times last, creating their entries changes them.
+    pub fn dir(&self, relative: &str) -> &Fixture {
+        let path = self.join(relative);
+        fs::create_dir_all(&path).expect("cannot create directories");
+        self.touch(relative, mtime())
+    }
+
+    /// Sets the modification time of `relative`.
+    pub fn touch(&self, relative: &str, modified: SystemTime) -> &Fixture {
+        File::open(self.join(relative))
+            .and_then(|file| file.set_modified(modified))
+            .expect("cannot set a modification time");
+        self
+    }
+
+    /// Creates the symlink `relative` pointing to `target`.
+    #[cfg(unix)]
+    pub fn symlink(&self, target: &str, relative: &str) -> &Fixture {
+        std::os::unix::fs::symlink(target, self.join(relative)).expect("cannot create a symlink");
+        self
+    }
+}
@@ -0,0 +1,78 @@
+use crate::entry::{EntryType, RawEntry};
+use crate::pool;
+use clap::ValueEnum;
+use sha2::Digest;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+// Files are read in chunks of this size, whatever their size
+const CHUNK_LEN: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// The hex digest of the file at `path`.
+    pub fn digest_file(self, path: &Path) -> io::Result<String> {
+        let file = File::open(path)?;
+        let digest = match self {
+            HashAlgorithm::Sha256 => digest_with::<sha2::Sha256>(file)?,
+            HashAlgorithm::Sha1 => digest_with::<sha1::Sha1>(file)?,
+            HashAlgorithm::Md5 => digest_with::<md5::Md5>(file)?,
+            HashAlgorithm::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                each_chunk(file, |chunk| {
+                    hasher.update(chunk);
+                })?;
+                hasher.finalize().as_bytes().to_vec()
+            }
+        };
+        Ok(hex(&digest))
+    }
+}
+
+/// Hashes the regular files in `entries` in parallel. Files that can't be read get the
+/// error instead of a digest, other kinds of entries are left alone.
+pub fn apply(dir: &Path, algorithm: HashAlgorithm, entries: &mut [RawEntry]) {
+    let mut files: Vec<_> = entries
+        .iter_mut()
+        .filter(|e| e.e_type == EntryType::File)
+        .collect();
+    let paths: Vec<_> = files.iter().map(|e| e.path_in(dir)).collect();
+    let digests = pool::map(&paths, |path| algorithm.digest_file(path));
+
+    for (raw, digest) in files.iter_mut().zip(digests) {
+        // Files nobody got to before --timeout have no digest, like the other columns
+        if let Some(digest) = digest {
+            raw.hash = Some(digest.map_err(|e| e.to_string()));
+        }
+    }
+}
+
+fn digest_with<D: Digest>(file: File) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    each_chunk(file, |chunk| hasher.update(chunk))?;
+    Ok(hasher.finalize().to_vec())
+}
+
+fn each_chunk(mut file: File, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+    let mut buffer = vec![0; CHUNK_LEN];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(n) => f(&buffer[..n]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,142 @@
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HyperlinkMode {
+    Never,
+    /// Only when stdout is a terminal
+    Auto,
+    Always,
+}
+
+impl HyperlinkMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            HyperlinkMode::Never => false,
+            HyperlinkMode::Auto => io::stdout().is_terminal(),
+            HyperlinkMode::Always => true,
+        }
+    }
+}
+
+/// The `file://` URL of `path`, made absolute without resolving symlinks. The host is
+/// named so terminals can tell files on another machine, e.g. over ssh, apart.
+pub fn file_url(path: &Path) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    Some(format!(
+        "file://{}{}",
+        percent_encode(hostname().as_bytes()),
+        percent_encode(&path_bytes(&path))
+    ))
+}
+
+/// Wraps `text` in an OSC 8 hyperlink to `url`. Terminals without support show the text
+/// alone, tabled skips the sequences when measuring cells.
+pub fn wrap(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Escapes every byte but the unreserved characters of RFC 3986 and `/`, so spaces become
+/// `%20` and each byte of a non-ASCII character its own `%XX`.
+pub fn percent_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.as_os_str().as_encoded_bytes().to_vec()
+}
+
+// C:\Users becomes /C:/Users
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    format!("/{}", path.to_string_lossy().replace('\\', "/")).into_bytes()
+}
+
+// Looked up once, every name of the listing links to the same host
+fn hostname() -> &'static str {
+    static HOSTNAME: OnceLock<String> = OnceLock::new();
+    HOSTNAME.get_or_init(read_hostname)
+}
+
+#[cfg(unix)]
+fn read_hostname() -> String {
+    let mut buffer = [0u8; 256];
+    // SAFETY: the buffer is writable for its whole length
+    let result = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if result != 0 {
+        return String::default();
+    }
+    let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    String::from_utf8_lossy(&buffer[..len]).to_string()
+}
+
+// An empty host means the local machine
+#[cfg(not(unix))]
+fn read_hostname() -> String {
+    String::default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unreserved_characters_stay() {
+        assert_eq!(
+            percent_encode(b"/home/ana/notes-2024_v1.txt~"),
+            "/home/ana/notes-2024_v1.txt~"
+        );
+    }
+
+    #[test]
+    fn spaces_and_reserved_characters_are_encoded() {
+        assert_eq!(percent_encode(b"/my file"), "/my%20file");
+        assert_eq!(percent_encode(b"a#b?c%d&e"), "a%23b%3Fc%25d%26e");
+        assert_eq!(percent_encode(b"C:\\x"), "C%3A%5Cx");
+    }
+
+    #[test]
+    fn every_byte_of_non_ascii_is_encoded() {
+        assert_eq!(percent_encode("café".as_bytes()), "caf%C3%A9");
+        assert_eq!(percent_encode("日".as_bytes()), "%E6%97%A5");
+        // Names that aren't UTF-8 keep their bytes
+        assert_eq!(percent_encode(b"caf\xe9"), "caf%E9");
+        assert_eq!(percent_encode(b"\n\x1b"), "%0A%1B");
+    }
+
+    #[test]
+    fn links_are_osc_8() {
+        assert_eq!(
+            wrap("a.txt", "file:///tmp/a.txt"),
+            "\x1b]8;;file:///tmp/a.txt\x1b\\a.txt\x1b]8;;\x1b\\"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn urls_are_absolute_and_name_the_host() {
+        let host = percent_encode(hostname().as_bytes());
+        assert_eq!(
+            file_url(Path::new("/srv/my files/café")).unwrap(),
+            format!("file://{}/srv/my%20files/caf%C3%A9", host)
+        );
+        let here = std::env::current_dir().unwrap();
+        let expected = format!(
+            "file://{}{}",
+            host,
+            percent_encode(&path_bytes(&here.join("x")))
+        );
+        assert_eq!(file_url(Path::new("x")).unwrap(), expected);
+    }
+}
@@ -0,0 +1,76 @@
+use clap::ValueEnum;
+use std::io::{self, IsTerminal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum IconsMode {
+    Never,
+    /// Only when stdout is a terminal
+    Auto,
+    Always,
+}
+
+impl IconsMode {
+    pub fn enabled(self) -> bool {
+        match self {
+            IconsMode::Never => false,
+            IconsMode::Auto => io::stdout().is_terminal(),
+            IconsMode::Always => true,
+        }
+    }
+}
+
+const DIRECTORY: char = '\u{f115}';
+const FILE: char = '\u{f15b}';
+
+// Names that say more than their extension
+const SPECIAL_NAMES: &[(&str, char)] = &[
+    (".git", '\u{e5fb}'),
+    (".gitignore", '\u{f1d3}'),
+    ("Cargo.toml", '\u{e7a8}'),
+    ("Cargo.lock", '\u{e7a8}'),
+    ("Makefile", '\u{f489}'),
+    ("Dockerfile", '\u{f308}'),
+    ("LICENSE", '\u{f02d}'),
+    ("README.md", '\u{f48a}'),
+];
+
+const EXTENSIONS: &[(&[&str], char)] = &[
+    (&["rs"], '\u{e7a8}'),
+    (&["py"], '\u{e606}'),
+    (&["js", "mjs"], '\u{e74e}'),
+    (&["ts"], '\u{e628}'),
+    (&["go"], '\u{e626}'),
+    (&["c", "h"], '\u{e61e}'),
+    (&["cpp", "hpp", "cc"], '\u{e61d}'),
+    (&["java"], '\u{e738}'),
+    (&["sh", "bash", "zsh"], '\u{f489}'),
+    (&["md", "txt", "readme"], '\u{f48a}'),
+    (&["json", "yaml", "yml", "toml", "xml"], '\u{e60b}'),
+    (&["png", "jpg", "jpeg", "gif", "svg", "webp"], '\u{f1c5}'),
+    (&["zip", "tar", "gz", "xz", "bz2", "zst", "7z"], '\u{f410}'),
+    (&["pdf"], '\u{f1c1}'),
+    (&["lock"], '\u{f023}'),
+];
+
+/// The nerd font glyph shown in front of `name`, exact names win over extensions.
+pub fn icon_for(name: &str, is_dir: bool) -> char {
+    if let Some((_, icon)) = SPECIAL_NAMES.iter().find(|(special, _)| *special == name) {
+        return *icon;
+    }
+    if is_dir {
+        return DIRECTORY;
+    }
+
+    let extension = name
+        .rsplit_once('.')
+        .filter(|(stem, _)| !stem.is_empty())
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    extension
+        .and_then(|ext| {
+            EXTENSIONS
+                .iter()
+                .find(|(extensions, _)| extensions.contains(&ext.as_str()))
+        })
+        .map(|(_, icon)| *icon)
+        .unwrap_or(FILE)
+}
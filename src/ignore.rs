@@ -0,0 +1,300 @@
+use crate::cli::Cli;
+use crate::entry::RawEntry;
+use crate::pattern::{NamePattern, parse_glob, parse_path_glob};
+use crate::report::warn;
+use owo_colors::OwoColorize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{MAIN_SEPARATOR, Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The file listing what to leave out of a directory and everything below it.
+pub const FILE_NAME: &str = ".betterls-ignore";
+
+struct Rule {
+    pattern: NamePattern,
+    // `!pattern` brings back what an earlier pattern left out
+    negated: bool,
+    // `pattern/` only matches directories
+    dirs_only: bool,
+    // With a `/` before its end a pattern matches paths from the ignore file's directory,
+    // without one it matches names at any depth
+    anchored: bool,
+}
+
+impl Rule {
+    // None for blank lines and comments
+    fn parse(line: &str) -> Option<Result<Rule, String>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let (dirs_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        // Anchored patterns are matched against `/` and the path, so `**/` can match no
+        // directory at all
+        let pattern = if !anchored {
+            parse_glob(line)
+        } else if line.starts_with("**/") {
+            parse_path_glob(line)
+        } else {
+            parse_path_glob(&format!("/{}", line.trim_start_matches('/')))
+        };
+        Some(pattern.map(|pattern| Rule {
+            pattern,
+            negated,
+            dirs_only,
+            anchored,
+        }))
+    }
+
+    fn matches(&self, relative: &str, name: &str, is_dir: bool) -> bool {
+        if self.dirs_only && !is_dir {
+            return false;
+        }
+        if self.anchored {
+            self.pattern.is_match(relative)
+        } else {
+            self.pattern.is_match(name)
+        }
+    }
+}
+
+// The rules of one ignore file, stacked on those of the directories above it that the
+// walk came through
+struct Rules {
+    dir: PathBuf,
+    rules: Vec<Rule>,
+    parent: Option<Arc<Rules>>,
+}
+
+impl Rules {
+    // The deepest ignore file with a pattern matching the entry decides, and within it
+    // the last such pattern, like git
+    fn ignore(&self, dir: &Path, name: &str, is_dir: bool) -> bool {
+        let mut level = Some(self);
+        while let Some(rules) = level {
+            let below = dir.strip_prefix(&rules.dir).unwrap_or(Path::new(""));
+            let relative =
+                format!("/{}", below.join(name).to_string_lossy()).replace(MAIN_SEPARATOR, "/");
+            if let Some(rule) = rules
+                .rules
+                .iter()
+                .rev()
+                .find(|rule| rule.matches(&relative, name, is_dir))
+            {
+                return !rule.negated;
+            }
+            level = rules.parent.as_deref();
+        }
+        false
+    }
+}
+
+// Each directory's stack of rules is read once, None when no ignore file applies to it
+static RULES: OnceLock<Mutex<HashMap<PathBuf, Option<Arc<Rules>>>>> = OnceLock::new();
+
+/// Whether the ignore files of `dir` and the directories above it leave out `raw`, one
+/// of its entries. Walks read a directory's entries before those of its subdirectories,
+/// so the rules of a parent are known by the time its subdirectories are listed. Ignore
+/// files above the directory a walk started from are never read.
+pub fn ignores(dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+    if cli.no_ignore_files || raw.is_dot_entry() {
+        return false;
+    }
+    rules_for(dir, cli).is_some_and(|rules| rules.ignore(dir, &raw.name, raw.is_dir))
+}
+
+/// Forgets the rules read so far, for --watch to pick up edited ignore files.
+pub fn clear() {
+    if let Some(cache) = RULES.get() {
+        cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+fn rules_for(dir: &Path, cli: &Cli) -> Option<Arc<Rules>> {
+    let cache = RULES.get_or_init(Default::default);
+    let cached = |dir: &Path| {
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(dir)
+            .cloned()
+    };
+    if let Some(rules) = cached(dir) {
+        return rules;
+    }
+    let parent = dir.parent().and_then(cached).flatten();
+    let rules = match read(dir, cli) {
+        rules if rules.is_empty() => parent,
+        rules => Some(Arc::new(Rules {
+            dir: dir.to_path_buf(),
+            rules,
+            parent,
+        })),
+    };
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(dir.to_path_buf(), rules.clone());
+    rules
+}
+
+// A missing or unreadable file has no rules, invalid patterns are skipped with a warning
+fn read(dir: &Path, cli: &Cli) -> Vec<Rule> {
+    let path = dir.join(FILE_NAME);
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Vec::new();
+    };
+    let mut rules = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        match Rule::parse(line) {
+            Some(Ok(rule)) => rules.push(rule),
+            Some(Err(e)) => warn(
+                cli,
+                format!("{}:{}: {}", path.display(), number + 1, e).yellow(),
+            ),
+            None => {}
+        }
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::EntryType;
+    use crate::fixture::{Fixture, cli};
+
+    fn rules(dir: &str, text: &str, parent: Option<Arc<Rules>>) -> Arc<Rules> {
+        Arc::new(Rules {
+            dir: PathBuf::from(dir),
+            rules: text
+                .lines()
+                .filter_map(Rule::parse)
+                .collect::<Result<_, _>>()
+                .unwrap(),
+            parent,
+        })
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_no_rules() {
+        assert!(Rule::parse("").is_none());
+        assert!(Rule::parse("   ").is_none());
+        assert!(Rule::parse("# *.log").is_none());
+        let rule = Rule::parse("!build/").unwrap().unwrap();
+        assert!(rule.negated && rule.dirs_only && !rule.anchored);
+        assert!(Rule::parse("docs/*.md").unwrap().unwrap().anchored);
+    }
+
+    #[test]
+    fn the_last_matching_pattern_decides() {
+        let negated_last = rules("/p", "*.log\n!keep.log\n", None);
+        assert!(negated_last.ignore(Path::new("/p"), "debug.log", false));
+        assert!(!negated_last.ignore(Path::new("/p"), "keep.log", false));
+        assert!(!negated_last.ignore(Path::new("/p"), "main.rs", false));
+        // Unanchored patterns match at any depth
+        assert!(negated_last.ignore(Path::new("/p/a/b"), "debug.log", false));
+
+        let negated_first = rules("/p", "!keep.log\n*.log\n", None);
+        assert!(negated_first.ignore(Path::new("/p"), "keep.log", false));
+    }
+
+    #[test]
+    fn anchored_and_directory_patterns() {
+        let rules = rules("/p", "/target\ndocs/*.md\nout/\n", None);
+        assert!(rules.ignore(Path::new("/p"), "target", true));
+        assert!(!rules.ignore(Path::new("/p/sub"), "target", true));
+        assert!(rules.ignore(Path::new("/p/docs"), "guide.md", false));
+        assert!(!rules.ignore(Path::new("/p"), "guide.md", false));
+        assert!(rules.ignore(Path::new("/p/sub"), "out", true));
+        assert!(!rules.ignore(Path::new("/p/sub"), "out", false));
+    }
+
+    #[test]
+    fn a_negation_below_re_includes_what_a_parent_ignores() {
+        let parent = rules("/p", "*.log\nsecrets/\n", None);
+        let child = rules("/p/logs", "!keep.log\n", Some(parent.clone()));
+        assert!(!child.ignore(Path::new("/p/logs"), "keep.log", false));
+        assert!(!child.ignore(Path::new("/p/logs/old"), "keep.log", false));
+        // The parent's other patterns still apply below the child's ignore file
+        assert!(child.ignore(Path::new("/p/logs"), "debug.log", false));
+        assert!(child.ignore(Path::new("/p/logs"), "secrets", true));
+        // And the negation doesn't reach above it
+        assert!(parent.ignore(Path::new("/p"), "keep.log", false));
+    }
+
+    fn entry(name: &str, e_type: EntryType) -> RawEntry {
+        RawEntry::member(name.to_string(), e_type, 0, 0o644)
+    }
+
+    #[test]
+    fn ignore_files_on_disk_stack() {
+        let fixture = Fixture::new();
+        fixture
+            .file(FILE_NAME, b"# build output\n*.log\n")
+            .file("logs/.betterls-ignore", b"!keep.log\n")
+            .file("logs/keep.log", b"")
+            .file("logs/debug.log", b"")
+            .file("top.log", b"");
+        let logs = fixture.join("logs");
+        let cli = cli(&[]);
+        // A walk asks about the parent's entries first
+        assert!(ignores(
+            fixture.path(),
+            &entry("top.log", EntryType::File),
+            &cli
+        ));
+        assert!(!ignores(
+            fixture.path(),
+            &entry("logs", EntryType::Dir),
+            &cli
+        ));
+        assert!(!ignores(&logs, &entry("keep.log", EntryType::File), &cli));
+        assert!(ignores(&logs, &entry("debug.log", EntryType::File), &cli));
+        assert!(!ignores(fixture.path(), &entry(".", EntryType::Dir), &cli));
+
+        let no_ignore = crate::fixture::cli(&["--no-ignore-files"]);
+        assert!(!ignores(
+            fixture.path(),
+            &entry("top.log", EntryType::File),
+            &no_ignore
+        ));
+        assert!(!ignores(
+            &logs,
+            &entry("debug.log", EntryType::File),
+            &no_ignore
+        ));
+    }
+
+    #[test]
+    fn recursive_listings_follow_the_stack() {
+        let fixture = Fixture::new();
+        fixture
+            .file(FILE_NAME, b"*.log\n")
+            .file("logs/.betterls-ignore", b"!keep.log\n")
+            .file("logs/keep.log", b"")
+            .file("logs/debug.log", b"")
+            .file("top.log", b"")
+            .file("main.rs", b"");
+        let cli = cli(&["-R", "-1", "-q"]);
+        let text = crate::fixture::output(|out| {
+            crate::render::print_listings(
+                out,
+                &[fixture.path().to_path_buf()],
+                &cli,
+                crate::entry::map_short_data,
+            )
+            .map(|_| ())
+        });
+        assert_eq!(text, "logs\nmain.rs\n\nkeep.log\n");
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+// Enough for every signature below, tar's sits at offset 257
+const SNIFF_LEN: u64 = 512;
+
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x7fELF", "ELF binary"),
+    (0, b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (0, b"\xff\xd8\xff", "JPEG image"),
+    (0, b"GIF8", "GIF image"),
+    (0, b"%PDF-", "PDF document"),
+    (0, b"\x1f\x8b", "gzip archive"),
+    (0, b"PK\x03\x04", "zip archive"),
+    (0, b"PK\x05\x06", "zip archive"),
+    (0, b"BZh", "bzip2 archive"),
+    (0, b"\xfd7zXZ\x00", "xz archive"),
+    (0, b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (257, b"ustar", "tar archive"),
+];
+
+/// What the first bytes of the regular file at `path` say it is, e.g. `PNG image`,
+/// `python3 script` or `UTF-8 text`. Symlinks are never followed and files that can't be
+/// opened have no kind.
+pub fn detect(path: &Path) -> Option<String> {
+    let mut options = fs::OpenOptions::new();
+    options.read(true);
+    // A fifo swapped in after the listing was read must not block the open
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(
+        &mut options,
+        libc::O_NOFOLLOW | libc::O_NONBLOCK,
+    );
+    #[cfg(not(unix))]
+    if fs::symlink_metadata(path).ok()?.file_type().is_symlink() {
+        return None;
+    }
+    let file = options.open(path).ok()?;
+    if !file.metadata().ok()?.is_file() {
+        return None;
+    }
+    let head = read_head(file).ok()?;
+    Some(kind_of(&head))
+}
+
+fn read_head(file: File) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(SNIFF_LEN as usize);
+    file.take(SNIFF_LEN).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+fn kind_of(head: &[u8]) -> String {
+    if head.is_empty() {
+        return "empty".to_string();
+    }
+    if let Some(interpreter) = head.strip_prefix(b"#!").and_then(interpreter) {
+        return format!("{} script", interpreter);
+    }
+    let signature = SIGNATURES.iter().find(|(offset, magic, _)| {
+        head.get(*offset..offset + magic.len())
+            .is_some_and(|bytes| bytes == *magic)
+    });
+    if let Some((_, _, kind)) = signature {
+        return kind.to_string();
+    }
+    if is_text(head) {
+        "UTF-8 text".to_string()
+    } else {
+        "binary data".to_string()
+    }
+}
+
+// The program named by a shebang line, looking through `env` to the one it runs
+fn interpreter(line: &[u8]) -> Option<String> {
+    let line = line.split(|&b| b == b'\n').next()?;
+    let line = String::from_utf8_lossy(line);
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    (!program.is_empty()).then(|| program.to_string())
+}
+
+// A character cut in half at the end of the sniffed bytes still counts as text
+fn is_text(head: &[u8]) -> bool {
+    if head.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(head) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
@@ -0,0 +1,56 @@
+//! The listing engine behind `best-ls`: reading directories, turning entries into rows and
+//! writing them as tables, trees, JSON, YAML or CSV to any `io::Write`.
+
+pub mod archive;
+pub mod cache;
+pub mod capabilities;
+pub mod changes;
+pub mod cli;
+pub mod collect;
+pub mod color;
+pub mod columns;
+pub mod config;
+pub mod counts;
+pub mod cutoff;
+pub mod deadline;
+pub mod diff;
+pub mod du;
+pub mod duplicates;
+pub mod duration;
+pub mod entry;
+pub mod env_opts;
+pub mod extensions;
+pub mod failures;
+pub mod find;
+#[cfg(test)]
+mod fixture;
+pub mod hash;
+pub mod hyperlink;
+pub mod icons;
+pub mod ignore;
+pub mod kind;
+pub mod mounts;
+pub mod names;
+pub mod owners;
+pub mod pager;
+pub mod pattern;
+pub mod permissions;
+pub mod pick;
+pub mod pool;
+pub mod render;
+pub mod report;
+pub mod size;
+pub mod sort;
+pub mod stat;
+pub mod style;
+pub mod summary;
+pub mod terminal;
+pub mod time_style;
+pub mod top;
+pub mod tree;
+pub mod truncate;
+pub mod watch;
+pub mod xattrs;
+
+pub use cli::Cli;
+pub use entry::{EntryType, RawEntry};
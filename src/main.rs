@@ -1,348 +1,461 @@
-use chrono::{DateTime, Utc};
-use clap::Parser;
+use best_ls::cache::CacheMode;
+use best_ls::cli::{Cli, apply_quiet, attach_counts, data_output, needs_long_entries};
+use best_ls::color::{self, Plain};
+use best_ls::entry::{map_long_data, map_short_data};
+use best_ls::hyperlink::HyperlinkMode;
+use best_ls::icons::IconsMode;
+use best_ls::render::{
+    print_by_extension, print_by_owner, print_changes, print_diff, print_duplicates, print_find,
+    print_json, print_listing, print_listings, print_mounts, print_stat, print_summary_only,
+    print_top, print_yaml,
+};
+use best_ls::report::{fail, report_error, warn};
+use best_ls::top::Ranking;
+use best_ls::tree::{print_tree, print_tree_data, tree_data};
+use best_ls::{
+    cache, capabilities, config, deadline, du, env_opts, failures, ignore, owners, pager, pick,
+    watch,
+};
+use clap::{CommandFactory, FromArgMatches};
 use owo_colors::OwoColorize;
-use serde::Serialize;
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::ffi::OsString;
+use std::io::{self, Write};
 use std::{
-    fs,
+    env, fs,
     path::{Path, PathBuf},
 };
-use strum::Display;
-use tabled::settings::{Alignment, Width};
-use tabled::{
-    Table, Tabled,
-    settings::{
-        Color, Style,
-        object::{Columns, Rows},
-    },
-};
-use users::{Users, UsersCache};
-
-#[derive(Debug, Display, Serialize)]
-enum EntryType {
-    File,
-    Dir,
-}
-
-#[derive(Tabled)]
-struct FileEntryShort {
-    #[tabled(rename = "Name")]
-    name: String,
-    #[tabled(rename = "Type")]
-    e_type: EntryType,
-    #[tabled(rename = "Size B")]
-    len_bytes: u64,
-    #[tabled(rename = "Modified")]
-    modified: String,
-}
-
-#[derive(Debug, Tabled, Serialize)]
-struct FileEntryLong {
-    #[tabled(rename = "Permission")]
-    permissions: String,
-    #[tabled(rename = "Owner")]
-    owner: String,
-    #[tabled{rename="Name"}]
-    name: String,
-    #[tabled{rename="Type"}]
-    e_type: EntryType,
-    #[tabled{rename="Size B"}]
-    len_bytes: u64,
-    #[tabled(rename = "Modified")]
-    modified: String,
-}
-
-#[derive(Debug, Parser)]
-#[command(version, about, long_about = "Best ls command ever")]
-struct Cli {
-    path: Option<PathBuf>,
-    #[arg(short, long)]
-    json: bool,
-
-    #[arg(short, long, help = "Show hidden files")]
-    all: bool,
-
-    #[arg(short, long, help = "Use a long listing format")]
-    long: bool,
-
-    #[arg(long, help = "List files in a tree-like format")]
-    tree: bool,
-}
 
 fn main() {
-    let cli = Cli::parse();
-    let path = cli.path.as_ref().cloned().unwrap_or(PathBuf::from("."));
-
-    if let Ok(does_exists) = fs::exists(&path) {
-        if does_exists {
-            if cli.tree {
-                print_tree(&path, &cli);
-            } else if cli.json {
-                let files = get_long_files(&path, &cli);
-                println!(
-                    "{}",
-                    serde_json::to_string_pretty(&files).unwrap_or("cannot parse json".to_string())
-                );
-            } else if cli.long {
-                print_long_table(&path, &cli);
-            } else {
-                print_short_table(&path, &cli)
+    // A closed pipe (e.g. `| head`) ends the process quietly, as it does for other tools
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    let (args, from_env) = arguments();
+    let matches = Cli::command()
+        .try_get_matches_from(&args)
+        .unwrap_or_else(|e| {
+            // Mistakes only the variable and the command line together make
+            let blamed = env_opts::blamed(&e, &args[1..=from_env], &args[from_env + 1..]);
+            if blamed.is_empty() {
+                e.exit();
             }
+            eprint!("{} given in {}: {}", blamed.join(", "), env_opts::VAR, e);
+            std::process::exit(2);
+        });
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Before anything is printed, config warnings included
+    let colored = color::settle(cli.color);
+    if !cli.no_config {
+        let path = cli.paths.first().cloned().unwrap_or(PathBuf::from("."));
+        // The [paths] overrides of the first directory apply to all of them
+        config::Config::load(cli.quiet > 0).apply(&mut cli, &matches, &path);
+    }
+    // --mine is --owner with the effective uid
+    if cli.mine {
+        match owners::caller_uid() {
+            Some(uid) => cli.owner = Some(uid),
+            None => fail(
+                &cli,
+                "--mine needs file owners, which only Unix has".to_string(),
+            ),
+        }
+    }
+    apply_quiet(&mut cli);
+    // Settled once so every cell agrees, even if stdout changes underneath
+    cli.icons = cli.icons.map(|mode| {
+        if mode.enabled() {
+            IconsMode::Always
         } else {
-            println!("{}", "Path does not exists".red());
+            IconsMode::Never
         }
-    } else {
-        println!("{}", "error reading directory".red());
+    });
+    cli.hyperlink = cli.hyperlink.map(|mode| {
+        if mode.enabled() {
+            HyperlinkMode::Always
+        } else {
+            HyperlinkMode::Never
+        }
+    });
+    let cli = cli;
+    let mut paths = cli.paths.clone();
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
     }
-}
-
-fn print_short_table(path: &Path, cli: &Cli) {
-    let files = get_short_files(path, cli);
-    let mut table = Table::new(files);
-
-    table.with(Style::rounded());
-
-    table.modify(Columns::new(..), Alignment::left());
-    table.modify(Columns::new(2..3), Alignment::right());
 
-    table.modify(Columns::new(0..1), Width::increase(15)); // Name
-    table.modify(Columns::new(1..2), Width::increase(8)); // Type
-    table.modify(Columns::new(2..3), Width::increase(10)); // Size
-    table.modify(Columns::new(3..4), Width::increase(15)); // Modified
-
-    table.modify(Rows::first(), Color::FG_BRIGHT_GREEN);
-
-    table.modify(Columns::new(0..1), Color::FG_BRIGHT_CYAN);
-    table.modify(Columns::new(1..2), Color::FG_WHITE);
-    table.modify(Columns::new(2..3), Color::FG_BRIGHT_MAGENTA);
-    table.modify(Columns::new(3..4), Color::FG_BRIGHT_BLUE);
+    if let Some(timeout) = cli.timeout {
+        deadline::start(timeout);
+    }
 
-    println!("{}", table);
-}
+    if let Some(shell) = cli.generate_completions {
+        let mut command = Cli::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+        return;
+    }
 
-fn get_short_files(path: &Path, cli: &Cli) -> Vec<FileEntryShort> {
-    let mut data = Vec::new();
-    if let Ok(read_dir) = fs::read_dir(path) {
-        for entry in read_dir {
-            if let Ok(file) = entry {
-                let file_name_str = file.file_name().to_string_lossy().to_string();
-                if !cli.all && file_name_str.starts_with('.') {
-                    continue;
-                }
-                map_short_data(&mut data, file, cli);
-            }
+    if cli.generate_man {
+        if let Err(e) = clap_mangen::Man::new(Cli::command()).render(&mut io::stdout()) {
+            fail(&cli, format!("cannot write output: {}", e));
         }
+        return;
     }
-    data
-}
-
-fn map_short_data(data: &mut Vec<FileEntryShort>, file: fs::DirEntry, _cli: &Cli) {
-    if let Ok(meta) = fs::metadata(&file.path()) {
-        let file_name = file
-            .file_name()
-            .into_string()
-            .unwrap_or("unknown name".into());
 
-        let display_name = file_name.clone();
+    if cli.dump_capabilities {
+        match capabilities::dump(cli.compact) {
+            Ok(document) => println!("{}", document),
+            Err(e) => fail(&cli, format!("cannot serialize capabilities: {}", e)),
+        }
+        return;
+    }
 
-        data.push(FileEntryShort {
-            name: display_name,
-            e_type: if meta.is_dir() {
-                EntryType::Dir
-            } else {
-                EntryType::File
-            },
-            len_bytes: meta.len(),
-            modified: if let Ok(modi) = meta.modified() {
-                let data: DateTime<Utc> = modi.into();
-                format!("{}", data.format("%a %b %e %Y"))
-            } else {
-                String::default()
-            },
-        });
+    if cli.cache == Some(CacheMode::Clear)
+        && let Err(e) = cache::DirCache::clear()
+    {
+        report_error(&cli, format!("error clearing cache: {}", e));
     }
-}
 
-fn print_long_table(path: &Path, cli: &Cli) {
-    let get_files = get_long_files(path, cli);
-    let mut table = Table::new(get_files);
+    let mut out = Plain::new(io::stdout().lock(), !colored);
+    if cli.mounts {
+        if let Err(e) = print_mounts(&mut out, &cli) {
+            fail(&cli, format!("cannot write output: {}", e));
+        }
+        exit_if_timed_out(&cli);
+        return;
+    }
 
-    table.with(Style::rounded());
+    if paths.len() > 1
+        && (cli.changed.is_some()
+            || cli.summary_only
+            || cli.duplicates
+            || cli.find.is_some()
+            || cli.by_owner
+            || cli.by_extension
+            || cli.watch
+            || cli.pick
+            || Ranking::of(&cli).is_some())
+    {
+        fail(
+            &cli,
+            "--changed, --summary-only, --duplicates, --find, --by-owner, --by-extension, --watch, --pick, --newest and --largest take a single path"
+                .to_string(),
+        );
+    }
 
-    table.modify(Columns::new(..), Alignment::left());
-    table.modify(Columns::new(4..5), Alignment::right());
+    if cli.diff && paths.len() != 2 {
+        fail(&cli, "--diff takes two directories".to_string());
+    }
 
-    // Set minimum widths to prevent cramping
-    table.modify(Columns::new(0..1), Width::increase(12)); // Permissions
-    table.modify(Columns::new(1..2), Width::increase(12)); // Owner
-    table.modify(Columns::new(2..3), Width::increase(20)); // Name
-    table.modify(Columns::new(3..4), Width::increase(6)); // Type
-    table.modify(Columns::new(4..5), Width::increase(10)); // Size
-    table.modify(Columns::new(5..6), Width::increase(15));
+    // One missing directory among several still leaves the others to list
+    let several = paths.len() > 1 && !cli.diff;
+    paths.retain(|path| match accessible(path) {
+        Ok(()) => true,
+        Err(message) if several => {
+            failures::record();
+            report_error(&cli, message);
+            false
+        }
+        Err(message) => fail(&cli, message),
+    });
+    if paths.is_empty() {
+        std::process::exit(2);
+    }
 
-    table.modify(Rows::first(), Color::FG_BRIGHT_GREEN);
+    if cli.pick {
+        pick(&mut out, &paths[0], &cli);
+    }
 
-    table.modify(Columns::new(0..1), Color::FG_BRIGHT_YELLOW); // Permissions
-    table.modify(Columns::new(1..2), Color::FG_BRIGHT_WHITE); // Owner
-    table.modify(Columns::new(2..3), Color::FG_BRIGHT_CYAN); // Name
-    table.modify(Columns::new(3..4), Color::FG_WHITE); // Type
-    table.modify(Columns::new(4..5), Color::FG_BRIGHT_MAGENTA); // Size
-    table.modify(Columns::new(5..6), Color::FG_BRIGHT_BLUE); // Modified
+    if cli.watch {
+        let path = &paths[0];
+        let watched = watch::watch(&mut out, path, cli.tree, |out| {
+            // Directory sizes have to be summed again for every redraw, ignore files read again
+            du::clear();
+            ignore::clear();
+            render(out, &paths, &cli).map(|_| ())
+        });
+        if let Err(e) = watched {
+            fail(&cli, format!("cannot watch {}: {}", path.display(), e));
+        }
+        return;
+    }
 
-    println!("{}", table);
+    let paged = if cli.paging.wanted(data_output(&cli)) {
+        let mut output = Plain::new(Vec::new(), !colored);
+        render(&mut output, &paths, &cli).and_then(|truncated| {
+            pager::show(&mut out, &output.into_inner(), cli.paging).map(|_| truncated)
+        })
+    } else {
+        render(&mut out, &paths, &cli)
+    };
+    let truncated = paged.unwrap_or_else(|e| fail(&cli, format!("cannot write output: {}", e)));
+
+    let unreadable = failures::entries();
+    if unreadable > 0 {
+        let entries = if unreadable == 1 { "entry" } else { "entries" };
+        warn(
+            &cli,
+            format!(
+                "warning: {} {} could not be fully read",
+                unreadable, entries
+            )
+            .yellow(),
+        );
+    }
+    let unreadable_dirs = failures::unreadable_dirs();
+    if unreadable_dirs > 0 {
+        let directories = if unreadable_dirs == 1 {
+            "directory"
+        } else {
+            "directories"
+        };
+        warn(
+            &cli,
+            format!(
+                "warning: {} {} could not be read",
+                unreadable_dirs, directories
+            )
+            .yellow(),
+        );
+    }
+    if truncated {
+        warn(
+            &cli,
+            format!(
+                "warning: stopped after {} entries, the listing is incomplete (raise or disable with --max-entries)",
+                cli.max_entries
+            )
+            .bright_red()
+            .bold(),
+        );
+    }
+    exit_if_timed_out(&cli);
+    let status = exit_status(truncated, failures::any(), failures::stale());
+    if status != 0 {
+        std::process::exit(status);
+    }
 }
 
-fn get_long_files(path: &Path, cli: &Cli) -> Vec<FileEntryLong> {
-    let mut data = Vec::new();
-    if let Ok(read_dir) = fs::read_dir(path) {
-        for entry in read_dir {
-            if let Ok(file) = entry {
-                let file_name_str = file.file_name().to_string_lossy().to_string();
-                if !cli.all && file_name_str.starts_with(".") {
-                    continue;
-                }
-                map_long_data(&mut data, file, cli);
-            }
-        }
+// Like coreutils: 1 when some entries couldn't be listed, 2 (from `fail`) when nothing
+// could. 4 for stale entries only when the listing itself was complete
+fn exit_status(truncated: bool, failed: bool, stale: bool) -> i32 {
+    if truncated || failed {
+        1
+    } else if stale {
+        4
+    } else {
+        0
     }
-    data
 }
 
-fn map_long_data(data: &mut Vec<FileEntryLong>, file: fs::DirEntry, _cli: &Cli) {
-    let cache = UsersCache::new();
-    if let Ok(meta) = fs::metadata(&file.path()) {
-        let owner = cache
-            .get_user_by_uid(meta.uid())
-            .map(|u| u.name().to_string_lossy().to_string())
-            .unwrap_or_else(|| meta.uid().to_string());
-
-        // Get the raw file name
-        let file_name = file
-            .file_name()
-            .into_string()
-            .unwrap_or("unknown name".into());
-
-        let display_name = file_name.clone();
-
-        data.push(FileEntryLong {
-            permissions: format!("{:o}", meta.permissions().mode() & 0o777),
-            owner,
-            name: display_name, // Use the colored name here
-            e_type: if meta.is_dir() {
-                EntryType::Dir
-            } else {
-                EntryType::File
-            },
-            len_bytes: meta.len(),
-            modified: if let Ok(modi) = meta.modified() {
-                let date: DateTime<Utc> = modi.into();
-                format!("{}", date.format("%a %b %e %Y"))
-            } else {
-                String::default()
-            },
-        });
+fn accessible(path: &Path) -> Result<(), String> {
+    match fs::metadata(path) {
+        Err(e) => Err(format!("cannot access {}: {}", path.display(), e)),
+        Ok(meta) if meta.is_dir() => fs::read_dir(path)
+            .map(|_| ())
+            .map_err(|e| format!("cannot open directory {}: {}", path.display(), e)),
+        Ok(_) => Ok(()),
     }
 }
 
-fn print_tree(path: &Path, cli: &Cli) {
-    // Print the root directory name
-    let root_name = path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| path.to_string_lossy().to_string());
-
-    println!("{}", root_name.bright_blue().bold());
-
-    print_tree_recursive(path, "", cli, 0, 3, true);
+// Prints the picked paths and exits, 130 like an interrupted shell command when nothing was
+// picked
+fn pick(out: &mut impl Write, path: &Path, cli: &Cli) -> ! {
+    let picked = match pick::run(path, cli) {
+        Ok(Some(picked)) => picked,
+        Ok(None) => std::process::exit(130),
+        Err(e) => fail(cli, format!("cannot pick: {}", e)),
+    };
+    let written = picked.iter().try_for_each(|path| {
+        out.write_all(path.as_os_str().as_encoded_bytes())?;
+        writeln!(out)
+    });
+    if let Err(e) = written.and_then(|_| out.flush()) {
+        fail(cli, format!("cannot write output: {}", e));
+    }
+    std::process::exit(0);
 }
 
-fn print_tree_recursive(
-    path: &Path,
-    prefix: &str,
-    cli: &Cli,
-    current_depth: usize,
-    max_depth: usize,
-    _is_root: bool,
-) {
-    if current_depth >= max_depth {
-        return;
+/// Writes the view the flags asked for, returns whether the listing was truncated.
+fn render(out: &mut impl Write, paths: &[PathBuf], cli: &Cli) -> io::Result<bool> {
+    let path = paths[0].as_path();
+    if cli.diff {
+        print_diff(out, path, &paths[1], cli)
+    } else if cli.stat {
+        print_stat(out, paths, cli).map(|_| false)
+    } else if let Some(manifest) = &cli.changed {
+        print_changes(out, path, manifest, cli)
+    } else if cli.summary_only {
+        print_summary_only(out, path, cli)
+    } else if cli.duplicates {
+        print_duplicates(out, path, cli).map(|_| false)
+    } else if let Some(needle) = &cli.find {
+        if needs_long_entries(cli) {
+            print_find(out, path, needle, cli, map_long_data)
+        } else {
+            print_find(out, path, needle, cli, map_short_data)
+        }
+        .map(|_| false)
+    } else if cli.by_owner {
+        print_by_owner(out, path, cli)
+    } else if cli.by_extension {
+        print_by_extension(out, path, cli)
+    } else if let Some(top) = Ranking::of(cli) {
+        let found = if needs_long_entries(cli) {
+            print_top(out, path, top, cli, map_long_data)
+        } else {
+            print_top(out, path, top, cli, map_short_data)
+        }?;
+        // Nothing to rank is an answer of its own, scripts can tell it by the status
+        if !found {
+            std::process::exit(1);
+        }
+        Ok(false)
+    } else if cli.tree && (cli.json || cli.yaml) && cli.paths.len() > 1 {
+        print_trees_data(out, paths, cli).map(|_| false)
+    } else if cli.tree && (cli.json || cli.yaml) {
+        print_tree_data(out, path, cli).map(|_| false)
+    } else if cli.tree {
+        // The root line of each tree already names its directory
+        for (index, path) in paths.iter().enumerate() {
+            if index > 0 {
+                writeln!(out)?;
+            }
+            print_tree(out, path, cli)?;
+        }
+        Ok(false)
+    } else if cli.paths.len() > 1 || cli.recursive {
+        // Headers even when only one of several operands could be accessed
+        if needs_long_entries(cli) {
+            print_listings(out, paths, cli, map_long_data)
+        } else {
+            print_listings(out, paths, cli, map_short_data)
+        }
+    } else if needs_long_entries(cli) {
+        print_listing(out, path, cli, map_long_data)
+    } else {
+        print_listing(out, path, cli, map_short_data)
     }
+}
 
-    let Ok(entries) = fs::read_dir(path) else {
+// Running out of --timeout takes precedence over other partial listings
+fn exit_if_timed_out(cli: &Cli) {
+    let (true, Some(timeout)) = (deadline::hit(), cli.timeout) else {
         return;
     };
+    let lower_bounds = if cli.by_owner {
+        ", the per-owner totals are lower bounds"
+    } else if cli.by_extension {
+        ", the per-extension totals are lower bounds"
+    } else {
+        ""
+    };
+    warn(
+        cli,
+        format!(
+            "partial results: timed out after {}s{}",
+            timeout.as_secs(),
+            lower_bounds
+        )
+        .bright_red()
+        .bold(),
+    );
+    std::process::exit(3);
+}
 
-    let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
-
-    // Sort entries: directories first, then files, both alphabetically
-    entries.sort_by(|a, b| {
-        let a_is_dir = a.path().is_dir();
-        let b_is_dir = b.path().is_dir();
-
-        match (a_is_dir, b_is_dir) {
-            (true, false) => std::cmp::Ordering::Less,
-            (false, true) => std::cmp::Ordering::Greater,
-            _ => a.file_name().cmp(&b.file_name()),
-        }
-    });
+// The command line with the words of $BETTER_LS_OPTS in front, so the command line wins
+// where both set an option, and how many words that were. The variable is checked on its
+// own first, so its mistakes aren't blamed on the command line.
+fn arguments() -> (Vec<OsString>, usize) {
+    let mut args = env::args_os();
+    let program = args.next().unwrap_or_else(|| "best-ls".into());
+    let args = attach_counts(args.collect());
+    let no_config = args
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .any(|arg| arg == "--no-config");
+    let words = if no_config {
+        Vec::new()
+    } else {
+        // Plain, --color isn't known before the arguments are parsed
+        env_opts::words().unwrap_or_else(|e| {
+            eprintln!("{}: {}", env_opts::VAR, e);
+            std::process::exit(2);
+        })
+    };
+    if words.is_empty() {
+        return (std::iter::once(program).chain(args).collect(), 0);
+    }
+    let words = attach_counts(words.into_iter().map(OsString::from).collect());
+    if let Err(e) =
+        Cli::command().try_get_matches_from(std::iter::once(program.clone()).chain(words.clone()))
+    {
+        eprint!("in {}: {}", env_opts::VAR, e);
+        std::process::exit(2);
+    }
+    let count = words.len();
+    let combined = std::iter::once(program).chain(words).chain(args).collect();
+    (combined, count)
+}
 
-    // Filter out hidden files if needed
-    let visible_entries: Vec<_> = entries
-        .into_iter()
-        .filter(|entry| {
-            let file_name_str = entry.file_name().to_string_lossy().to_string();
-            cli.all || !file_name_str.starts_with(".")
+// Several trees as an object keyed by path
+fn print_trees_data(out: &mut impl Write, paths: &[PathBuf], cli: &Cli) -> io::Result<()> {
+    let trees: serde_json::Map<_, _> = paths
+        .iter()
+        .map(|path| {
+            let tree = serde_json::to_value(tree_data(path, cli)).unwrap_or_default();
+            (path.to_string_lossy().to_string(), tree)
         })
         .collect();
+    if cli.yaml {
+        print_yaml(out, &trees)
+    } else {
+        print_json(out, &trees, cli)
+    }
+}
 
-    for (index, entry) in visible_entries.iter().enumerate() {
-        let is_last = index == visible_entries.len() - 1;
-        let file_name_str = entry.file_name().to_string_lossy().to_string();
-        let is_directory = entry.path().is_dir();
-
-        // Choose the appropriate tree characters
-        let (connector, next_prefix) = if is_last {
-            ("└── ", format!("{}    ", prefix))
-        } else {
-            ("├── ", format!("{}│   ", prefix))
-        };
-
-        // Color the file name based on type
-        let colored_name = if is_directory {
-            file_name_str.bright_blue().bold().to_string()
-        } else {
-            // Check file extension for different colors
-            let extension = Path::new(&file_name_str)
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .unwrap_or("");
-
-            match extension {
-                "rs" | "py" | "js" | "ts" | "go" | "cpp" | "c" | "java" => {
-                    file_name_str.bright_green().to_string()
-                }
-                "txt" | "md" | "readme" => file_name_str.bright_yellow().to_string(),
-                "json" | "yaml" | "yml" | "toml" | "xml" => file_name_str.bright_cyan().to_string(),
-                "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" => {
-                    file_name_str.bright_magenta().to_string()
-                }
-                _ => file_name_str.white().to_string(),
-            }
-        };
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_listings_exit_with_one() {
+        assert_eq!(exit_status(false, false, false), 0);
+        assert_eq!(exit_status(false, true, false), 1);
+        assert_eq!(exit_status(true, false, false), 1);
+        assert_eq!(exit_status(false, true, true), 1);
+        assert_eq!(exit_status(false, false, true), 4);
+    }
 
-        // Print the current entry
-        println!("{}{}{}", prefix, connector, colored_name);
+    #[test]
+    fn inaccessible_paths_name_the_path_and_the_error() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("file"), b"").unwrap();
+
+        assert_eq!(accessible(dir.path()), Ok(()));
+        assert_eq!(accessible(&dir.path().join("file")), Ok(()));
+        let missing = dir.path().join("missing");
+        let cause = fs::metadata(&missing).unwrap_err();
+        assert_eq!(
+            accessible(&missing),
+            Err(format!("cannot access {}: {}", missing.display(), cause))
+        );
+    }
 
-        // Recursively print subdirectories
-        if is_directory {
-            print_tree_recursive(
-                &entry.path(),
-                &next_prefix,
-                cli,
-                current_depth + 1,
-                max_depth,
-                false,
-            );
+    // Permissions don't stop root
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directories_cannot_be_opened() {
+        use std::os::unix::fs::PermissionsExt;
+        if users::get_effective_uid() == 0 {
+            return;
         }
+        let dir = tempfile::tempdir().unwrap();
+        let locked = dir.path().join("locked");
+        fs::create_dir(&locked).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let error = accessible(&locked);
+        // Or the temporary directory can't be removed
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(error.unwrap_err().starts_with("cannot open directory"));
     }
 }
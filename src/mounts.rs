@@ -0,0 +1,423 @@
+use crate::deadline;
+use crate::entry::RawEntry;
+use crate::sort::SortKey;
+use serde::Serialize;
+use std::{fs, io, path::Path};
+use tabled::Tabled;
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+// Filesystems that only expose kernel state and have no real storage behind them
+const PSEUDO_FS_TYPES: &[&str] = &[
+    "autofs",
+    "binfmt_misc",
+    "bpf",
+    "cgroup",
+    "cgroup2",
+    "configfs",
+    "debugfs",
+    "devpts",
+    "efivarfs",
+    "fusectl",
+    "hugetlbfs",
+    "mqueue",
+    "nsfs",
+    "proc",
+    "pstore",
+    "rpc_pipefs",
+    "securityfs",
+    "selinuxfs",
+    "sysfs",
+    "tracefs",
+];
+
+/// One line of `/proc/self/mountinfo`, see proc(5) for the field layout.
+#[derive(Debug, Clone)]
+pub struct MountInfo {
+    pub mount_point: String,
+    pub mount_options: String,
+    pub fs_type: String,
+    pub source: String,
+}
+
+impl MountInfo {
+    pub fn is_pseudo(&self) -> bool {
+        PSEUDO_FS_TYPES.contains(&self.fs_type.as_str())
+    }
+
+    pub fn has_option(&self, option: &str) -> bool {
+        self.mount_options.split(',').any(|o| o == option)
+    }
+}
+
+/// The device `meta` is on, None where metadata has no device numbers.
+#[cfg(unix)]
+pub fn device(meta: &fs::Metadata) -> Option<u64> {
+    Some(std::os::unix::fs::MetadataExt::dev(meta))
+}
+
+#[cfg(not(unix))]
+pub fn device(_: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// Whether the entry `raw` of `dir` is on another device than `dir` itself, i.e. it is the
+/// mount point of another file system. A bind mount from the same device is not.
+pub fn other_device(dir: &Path, raw: &RawEntry) -> bool {
+    raw.error.is_none()
+        && crosses_device(
+            fs::metadata(dir).ok().and_then(|meta| device(&meta)),
+            Some(raw.dev),
+        )
+}
+
+/// Whether something on device `dev` is on another device than `start`. Unknown devices
+/// never count as another one, there is nothing to compare.
+pub fn crosses_device(start: Option<u64>, dev: Option<u64>) -> bool {
+    start.zip(dev).is_some_and(|(start, dev)| start != dev)
+}
+
+/// Finds the mount a path lives on, i.e. the longest mount point that prefixes it.
+/// Later lines win ties since they are mounted on top of earlier ones.
+pub fn mount_for(path: &Path) -> Option<MountInfo> {
+    let path = fs::canonicalize(path).ok()?;
+    read_mountinfo()
+        .ok()?
+        .into_iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.len())
+}
+
+pub fn read_mountinfo() -> io::Result<Vec<MountInfo>> {
+    let content = fs::read_to_string(MOUNTINFO_PATH)?;
+    Ok(parse_mountinfo(&content))
+}
+
+/// Parses the contents of a mountinfo file, skipping lines that don't have the expected shape.
+pub fn parse_mountinfo(content: &str) -> Vec<MountInfo> {
+    content.lines().filter_map(parse_mountinfo_line).collect()
+}
+
+fn parse_mountinfo_line(line: &str) -> Option<MountInfo> {
+    let mut fields = line.split_whitespace();
+
+    // Mount id, parent id, major:minor and root aren't needed yet
+    fields.nth(3)?;
+    let mount_point = unescape(fields.next()?);
+    let mount_options = fields.next()?.to_string();
+
+    // Optional fields are terminated by a single hyphen
+    for field in fields.by_ref() {
+        if field == "-" {
+            break;
+        }
+    }
+
+    let fs_type = fields.next()?.to_string();
+    let source = unescape(fields.next()?);
+
+    Some(MountInfo {
+        mount_point,
+        mount_options,
+        fs_type,
+        source,
+    })
+}
+
+// The kernel escapes space, tab, newline and backslash as three digit octal sequences
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 4)
+            .filter(|_| bytes[i] == b'\\')
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+
+        if let Some(value) = escaped {
+            out.push(value);
+            i += 4;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Capacity numbers of a mounted filesystem in bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct FsUsage {
+    pub size: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+impl FsUsage {
+    /// Percentage of the space usable by unprivileged users that is taken, rounded up like df.
+    pub fn use_percent(&self) -> Option<u64> {
+        let usable = self.used + self.available;
+        if usable == 0 {
+            return None;
+        }
+        Some((self.used * 100).div_ceil(usable))
+    }
+}
+
+#[cfg(unix)]
+pub fn statvfs(path: &Path) -> io::Result<FsUsage> {
+    use std::{ffi::CString, mem::MaybeUninit, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+
+    // SAFETY: c_path is a valid NUL terminated string and stat points to writable memory
+    let result = unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) };
+    if result != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: statvfs returned success so the struct has been filled in
+    let stat = unsafe { stat.assume_init() };
+
+    let fragment = stat.f_frsize;
+    let size = stat.f_blocks * fragment;
+    let free = stat.f_bfree * fragment;
+
+    Ok(FsUsage {
+        size,
+        used: size.saturating_sub(free),
+        available: stat.f_bavail * fragment,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn statvfs(_: &Path) -> io::Result<FsUsage> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "filesystem usage is only available on Unix",
+    ))
+}
+
+#[derive(Debug, Tabled, Serialize)]
+pub struct MountEntry {
+    #[tabled(rename = "Filesystem")]
+    pub device: String,
+    #[tabled(rename = "Type")]
+    pub fs_type: String,
+    #[tabled(rename = "Size")]
+    pub size: u64,
+    #[tabled(rename = "Used")]
+    pub used: u64,
+    #[tabled(rename = "Avail")]
+    pub available: u64,
+    #[tabled(rename = "Use%", display = "display_percent")]
+    pub use_percent: Option<u64>,
+    #[tabled(rename = "Mounted on")]
+    pub mount_point: String,
+    #[tabled(rename = "Options")]
+    pub options: String,
+}
+
+/// Orders the rows of --mounts by `key`: by mount point, or largest first with ties by
+/// mount point. Mounts have no times to sort by, so `time` keeps the mount table order.
+pub fn sort_mounts(mounts: &mut [MountEntry], key: SortKey) {
+    match key {
+        SortKey::Name => mounts.sort_by(|a, b| a.mount_point.cmp(&b.mount_point)),
+        SortKey::Size => mounts.sort_by(|a, b| {
+            b.size
+                .cmp(&a.size)
+                .then_with(|| a.mount_point.cmp(&b.mount_point))
+        }),
+        SortKey::Time | SortKey::Accessed | SortKey::None => {}
+    }
+}
+
+fn display_percent(percent: &Option<u64>) -> String {
+    percent.map(|p| format!("{}%", p)).unwrap_or("-".into())
+}
+
+/// Lists the mounted filesystems, leaving out pseudo filesystems and
+/// filesystems without any blocks unless `include_all` is set.
+pub fn get_mounts(include_all: bool) -> io::Result<Vec<MountEntry>> {
+    let mut data = Vec::new();
+
+    for mount in read_mountinfo()? {
+        // statvfs blocks on unresponsive network mounts
+        if deadline::expired() {
+            break;
+        }
+        if !include_all && mount.is_pseudo() {
+            continue;
+        }
+
+        let usage = statvfs(Path::new(&mount.mount_point)).ok();
+        if !include_all && usage.is_none_or(|u| u.size == 0) {
+            continue;
+        }
+
+        data.push(MountEntry {
+            device: mount.source,
+            fs_type: mount.fs_type,
+            size: usage.map(|u| u.size).unwrap_or_default(),
+            used: usage.map(|u| u.used).unwrap_or_default(),
+            available: usage.map(|u| u.available).unwrap_or_default(),
+            use_percent: usage.and_then(|u| u.use_percent()),
+            mount_point: mount.mount_point,
+            options: mount.mount_options,
+        });
+    }
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DESKTOP: &str = include_str!("../tests/data/mountinfo-desktop");
+    const CONTAINER: &str = include_str!("../tests/data/mountinfo-container");
+
+    fn mount_points(mounts: &[MountInfo]) -> Vec<&str> {
+        mounts.iter().map(|m| m.mount_point.as_str()).collect()
+    }
+
+    #[cfg(unix)]
+    fn dir_on(dev: u64) -> RawEntry {
+        let mut raw = RawEntry::member("mnt".to_string(), crate::entry::EntryType::Dir, 0, 0o755);
+        raw.dev = dev;
+        raw
+    }
+
+    fn mount(mount_point: &str, size: u64) -> MountEntry {
+        MountEntry {
+            device: "/dev/sda1".to_string(),
+            fs_type: "ext4".to_string(),
+            size,
+            used: 0,
+            available: size,
+            use_percent: Some(0),
+            mount_point: mount_point.to_string(),
+            options: "rw".to_string(),
+        }
+    }
+
+    #[test]
+    fn mounts_are_sorted_by_the_key() {
+        let table = || vec![mount("/home", 500), mount("/", 100), mount("/boot", 500)];
+        let sorted = |key| {
+            let mut mounts = table();
+            sort_mounts(&mut mounts, key);
+            mounts
+                .into_iter()
+                .map(|m| m.mount_point)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sorted(SortKey::Name), ["/", "/boot", "/home"]);
+        // Equal sizes go by mount point
+        assert_eq!(sorted(SortKey::Size), ["/boot", "/home", "/"]);
+        assert_eq!(sorted(SortKey::None), ["/home", "/", "/boot"]);
+        assert_eq!(sorted(SortKey::Time), ["/home", "/", "/boot"]);
+    }
+
+    #[test]
+    fn only_other_devices_are_crossed() {
+        assert!(crosses_device(Some(64769), Some(64770)));
+        // A bind mount keeps the device of what it shows
+        assert!(!crosses_device(Some(64769), Some(64769)));
+        // Without device numbers, as on Windows, everything is one file system
+        assert!(!crosses_device(None, Some(64769)));
+        assert!(!crosses_device(Some(64769), None));
+        assert!(!crosses_device(None, None));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn mount_points_are_entries_on_another_device() {
+        use std::os::unix::fs::MetadataExt;
+        let fixture = crate::fixture::Fixture::new();
+        let here = fs::metadata(fixture.path()).unwrap().dev();
+        assert!(!other_device(fixture.path(), &dir_on(here)));
+        assert!(other_device(fixture.path(), &dir_on(here + 1)));
+
+        // What couldn't be read has no device to compare
+        let mut unreadable = dir_on(here + 1);
+        unreadable.error = Some("Permission denied".to_string());
+        assert!(!other_device(fixture.path(), &unreadable));
+        // Neither has a directory that is gone
+        assert!(!other_device(&fixture.join("gone"), &dir_on(here + 1)));
+    }
+
+    #[test]
+    fn fields_around_the_optional_ones() {
+        let mounts = parse_mountinfo(DESKTOP);
+        assert_eq!(mounts.len(), 10);
+
+        let root = &mounts[5];
+        assert_eq!(root.mount_point, "/");
+        assert_eq!(root.mount_options, "rw,relatime");
+        assert_eq!(root.fs_type, "ext4");
+        assert_eq!(root.source, "/dev/nvme0n1p2");
+
+        // Two optional fields before the hyphen
+        let drive = &mounts[8];
+        assert_eq!(drive.fs_type, "fuse.rclone");
+        assert!(drive.has_option("nodev"));
+        assert!(!drive.has_option("no"));
+    }
+
+    #[test]
+    fn escaped_whitespace_is_restored() {
+        let mounts = parse_mountinfo(DESKTOP);
+        assert_eq!(mounts[8].mount_point, "/home/ana/My Drive");
+        assert_eq!(mounts[8].source, "Cloud Backup:photos");
+        assert_eq!(unescape(r"back\134slash\011tab"), "back\\slash\ttab");
+        assert_eq!(unescape(r"not\08"), r"not\08");
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let mounts = parse_mountinfo(CONTAINER);
+        assert_eq!(mounts.len(), 9);
+        assert!(parse_mountinfo("1 2 0:3 / /mnt rw shared:1").is_empty());
+        assert!(parse_mountinfo("").is_empty());
+    }
+
+    #[test]
+    fn pseudo_filesystems_are_recognized() {
+        let real: Vec<_> = parse_mountinfo(DESKTOP)
+            .into_iter()
+            .filter(|m| !m.is_pseudo())
+            .collect();
+        assert_eq!(
+            mount_points(&real),
+            ["/dev", "/run", "/", "/boot/efi", "/home/ana/My Drive"]
+        );
+
+        let real: Vec<_> = parse_mountinfo(CONTAINER)
+            .into_iter()
+            .filter(|m| !m.is_pseudo())
+            .collect();
+        assert_eq!(mount_points(&real), ["/", "/dev", "/data", "/etc/hostname"]);
+    }
+
+    #[test]
+    fn use_percent_rounds_up_like_df() {
+        let usage = FsUsage {
+            size: 1000,
+            used: 1,
+            available: 999,
+        };
+        assert_eq!(usage.use_percent(), Some(1));
+        let empty = FsUsage {
+            size: 0,
+            used: 0,
+            available: 0,
+        };
+        assert_eq!(empty.use_percent(), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::fmt::Write;
+
+/// Spells out a name as text: the valid UTF-8 as it is and every other byte as `\xNN`.
+/// Backslashes are doubled, in valid names too, so the result can be decoded again.
+pub fn escape_bytes(bytes: &[u8]) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        escaped.push_str(&chunk.valid().replace('\\', "\\\\"));
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+        }
+    }
+    escaped
+}
+
+/// Makes a name safe to print on a terminal: control characters (newlines, escape
+/// sequences, ...) become `?`, or C-style escapes such as `\n` and `\x1b` with `escape`,
+/// which also doubles backslashes so escaped names stay unambiguous.
+pub fn sanitize(name: &str, escape: bool) -> String {
+    if !name.chars().any(|c| c.is_control() || escape && c == '\\') {
+        return name.to_string();
+    }
+    let mut out = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            _ if !escape && c.is_control() => out.push('?'),
+            '\n' if escape => out.push_str("\\n"),
+            '\t' if escape => out.push_str("\\t"),
+            '\r' if escape => out.push_str("\\r"),
+            '\\' if escape => out.push_str("\\\\"),
+            c if escape && c.is_control() => {
+                let _ = write!(out, "\\x{:02x}", u32::from(c));
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether a shell would read `name` as anything but itself, because of spaces,
+/// quotes, globs or other metacharacters.
+pub fn needs_quoting(name: &str) -> bool {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./+:@%=,".contains(c);
+    name.is_empty() || !name.chars().all(safe)
+}
+
+/// Single quotes `name` if it needs quoting, embedded single quotes become `'\''`.
+pub fn shell_quote(name: &str) -> String {
+    if !needs_quoting(name) {
+        return name.to_string();
+    }
+    format!("'{}'", name.replace('\'', "'\\''"))
+}
+
+/// `shell_quote` for the bytes of a name, which a shell keeps as they are between single
+/// quotes whether or not they are UTF-8.
+pub fn shell_quote_bytes(name: &[u8]) -> Vec<u8> {
+    if let Ok(name) = std::str::from_utf8(name) {
+        return shell_quote(name).into_bytes();
+    }
+    let mut quoted = vec![b'\''];
+    for &byte in name {
+        match byte {
+            b'\'' => quoted.extend_from_slice(b"'\\''"),
+            byte => quoted.push(byte),
+        }
+    }
+    quoted.push(b'\'');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_are_spelled_out() {
+        assert_eq!(escape_bytes(b"caf\xe9.txt"), "caf\\xe9.txt");
+        assert_eq!(escape_bytes(b"\xff\xfe"), "\\xff\\xfe");
+        assert_eq!(escape_bytes("café".as_bytes()), "café");
+        // A truncated sequence is invalid too
+        assert_eq!(escape_bytes(b"a\xc3"), "a\\xc3");
+    }
+
+    #[test]
+    fn backslashes_stay_unambiguous() {
+        // Otherwise a name spelling `\xe9` would look like the byte
+        assert_eq!(escape_bytes(b"\\xe9\xe9"), "\\\\xe9\\xe9");
+        assert_ne!(escape_bytes(b"\\xe9"), escape_bytes(b"\xe9"));
+    }
+
+    #[test]
+    fn control_characters_become_question_marks() {
+        assert_eq!(sanitize("foo\nbar", false), "foo?bar");
+        assert_eq!(sanitize("\x1b[31mred\x1b[0m", false), "?[31mred?[0m");
+        assert_eq!(sanitize("tab\there\r", false), "tab?here?");
+        assert_eq!(sanitize("del\x7f", false), "del?");
+        // C1 controls can start escape sequences too
+        assert_eq!(sanitize("csi\u{9b}2J", false), "csi?2J");
+        assert_eq!(sanitize("back\\slash", false), "back\\slash");
+    }
+
+    #[test]
+    fn escape_spells_them_out() {
+        assert_eq!(sanitize("foo\nbar", true), "foo\\nbar");
+        assert_eq!(sanitize("a\tb\rc", true), "a\\tb\\rc");
+        assert_eq!(sanitize("\x1b[0m", true), "\\x1b[0m");
+        assert_eq!(sanitize("nul\0", true), "nul\\x00");
+        assert_eq!(sanitize("back\\slash", true), "back\\\\slash");
+        // `\n` spelled out and a real newline don't look the same
+        assert_ne!(sanitize("a\\nb", true), sanitize("a\nb", true));
+    }
+
+    #[test]
+    fn printable_names_are_left_alone() {
+        for name in [
+            "README.md",
+            "my file.txt",
+            "日本語",
+            "naïve",
+            "emoji 🎉",
+            "'quoted'",
+        ] {
+            assert_eq!(sanitize(name, false), name);
+            assert_eq!(sanitize(name, true), name);
+        }
+    }
+
+    #[test]
+    fn names_that_need_quoting() {
+        for name in [
+            "a.txt",
+            "src/main.rs",
+            "v1.2-rc+3",
+            "user@host:x",
+            "100%",
+            "a=b,c",
+        ] {
+            assert!(!needs_quoting(name), "{}", name);
+        }
+        for name in [
+            "", "my file", "it's", "*.rs", "a;b", "$HOME", "a|b", "~", "x\ny", "(1)", "café",
+        ] {
+            assert!(needs_quoting(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(shell_quote("plain.txt"), "plain.txt");
+        assert_eq!(shell_quote("my file"), "'my file'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("$(rm -rf ~)"), "'$(rm -rf ~)'");
+    }
+
+    #[test]
+    fn bytes_are_quoted_as_they_are() {
+        assert_eq!(shell_quote_bytes(b"plain.txt"), b"plain.txt");
+        assert_eq!(shell_quote_bytes("my file".as_bytes()), b"'my file'");
+        assert_eq!(shell_quote_bytes(b"caf\xe9"), b"'caf\xe9'");
+        assert_eq!(shell_quote_bytes(b"it's\xff"), b"'it'\\''s\xff'");
+    }
+}
@@ -0,0 +1,238 @@
+use crate::RawEntry;
+use serde::Serialize;
+use std::collections::HashMap;
+#[cfg(unix)]
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use tabled::Tabled;
+#[cfg(unix)]
+use users::{Groups, Users, UsersCache};
+
+// One cache for the whole run, so every uid is looked up in the user database only once
+#[cfg(unix)]
+static USERS: OnceLock<Mutex<UsersCache>> = OnceLock::new();
+
+#[cfg(unix)]
+fn users() -> MutexGuard<'static, UsersCache> {
+    USERS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Name of the user owning `uid`, or the numeric id when it doesn't resolve
+/// (e.g. files extracted from an archive or left behind by a deleted account).
+/// With `numeric` (`--numeric-ids`) nothing is looked up.
+#[cfg(unix)]
+pub fn owner_name(uid: u32, numeric: bool) -> String {
+    if numeric {
+        return uid.to_string();
+    }
+    users()
+        .get_user_by_uid(uid)
+        .map(|u| u.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| uid.to_string())
+}
+
+/// The effective uid of this process, looked up once. None outside of Unix, where
+/// entries have no owner to compare it with.
+#[cfg(unix)]
+pub fn caller_uid() -> Option<u32> {
+    static CALLER: OnceLock<u32> = OnceLock::new();
+    Some(*CALLER.get_or_init(users::get_effective_uid))
+}
+
+#[cfg(not(unix))]
+pub fn caller_uid() -> Option<u32> {
+    None
+}
+
+// Entries have no uid outside of Unix, nor a user database to look one up in
+#[cfg(not(unix))]
+pub fn owner_name(uid: u32, _numeric: bool) -> String {
+    uid.to_string()
+}
+
+/// Name of the group `gid`, or the numeric id when it doesn't resolve.
+#[cfg(unix)]
+pub fn group_name(gid: u32, numeric: bool) -> String {
+    if numeric {
+        return gid.to_string();
+    }
+    users()
+        .get_group_by_gid(gid)
+        .map(|g| g.name().to_string_lossy().to_string())
+        .unwrap_or_else(|| gid.to_string())
+}
+
+#[cfg(not(unix))]
+pub fn group_name(gid: u32, _numeric: bool) -> String {
+    gid.to_string()
+}
+
+/// Resolves the argument of `--owner` to a uid, numbers are taken as uids as they are.
+pub fn parse_owner(input: &str) -> Result<u32, String> {
+    if let Ok(uid) = input.parse() {
+        return Ok(uid);
+    }
+    #[cfg(unix)]
+    let uid = users().get_user_by_name(input).map(|u| u.uid());
+    #[cfg(not(unix))]
+    let uid = None;
+    uid.ok_or_else(|| format!("unknown user '{}'", input))
+}
+
+/// Resolves the argument of `--group` to a gid, numbers are taken as gids as they are.
+pub fn parse_group(input: &str) -> Result<u32, String> {
+    if let Ok(gid) = input.parse() {
+        return Ok(gid);
+    }
+    #[cfg(unix)]
+    let gid = users().get_group_by_name(input).map(|g| g.gid());
+    #[cfg(not(unix))]
+    let gid = None;
+    gid.ok_or_else(|| format!("unknown group '{}'", input))
+}
+
+/// One row of `--by-owner`.
+#[derive(Debug, Tabled, Serialize)]
+pub struct OwnerUsage {
+    #[tabled(rename = "Owner")]
+    pub owner: String,
+    #[tabled(rename = "Files")]
+    pub files: u64,
+    #[tabled(rename = "Dirs")]
+    pub dirs: u64,
+    #[tabled(rename = "Size B")]
+    pub len_bytes: u64,
+    #[tabled(rename = "Share", display = "display_share")]
+    pub percent: f64,
+}
+
+fn display_share(percent: &f64) -> String {
+    format!("{:.1}%", percent)
+}
+
+/// Groups entries by owner, largest total size first. Owners with the same size are
+/// ordered by name so the output is stable.
+pub fn aggregate<'a>(
+    entries: impl IntoIterator<Item = &'a RawEntry>,
+    numeric: bool,
+) -> Vec<OwnerUsage> {
+    let mut by_uid: HashMap<u32, OwnerUsage> = HashMap::new();
+
+    for raw in entries {
+        let usage = by_uid.entry(raw.uid).or_insert_with(|| OwnerUsage {
+            owner: owner_name(raw.uid, numeric),
+            files: 0,
+            dirs: 0,
+            len_bytes: 0,
+            percent: 0.0,
+        });
+        if raw.is_dir {
+            usage.dirs += 1;
+        } else {
+            usage.files += 1;
+        }
+        usage.len_bytes += raw.len;
+    }
+
+    let total: u64 = by_uid.values().map(|u| u.len_bytes).sum();
+    let mut rows: Vec<_> = by_uid.into_values().collect();
+    for row in &mut rows {
+        if total > 0 {
+            row.percent = row.len_bytes as f64 * 100.0 / total as f64;
+        }
+    }
+
+    rows.sort_by(|a, b| {
+        b.len_bytes
+            .cmp(&a.len_bytes)
+            .then_with(|| a.owner.cmp(&b.owner))
+    });
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::EntryType;
+
+    fn entry(uid: u32, e_type: EntryType, len: u64) -> RawEntry {
+        let mut raw = RawEntry::member(format!("{}-{}", uid, len), e_type, len, 0o644);
+        raw.uid = uid;
+        raw
+    }
+
+    #[test]
+    fn rows_add_up_per_owner() {
+        let entries = [
+            entry(1000, EntryType::File, 300),
+            entry(1001, EntryType::File, 100),
+            entry(1000, EntryType::Dir, 4096),
+            entry(1001, EntryType::File, 500),
+            entry(1000, EntryType::File, 104),
+        ];
+        let rows = aggregate(&entries, true);
+
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|row| (row.owner.as_str(), row.files, row.dirs, row.len_bytes))
+            .collect();
+        assert_eq!(summary, [("1000", 2, 1, 4500), ("1001", 2, 0, 600)]);
+        assert!((rows[0].percent - 4500.0 * 100.0 / 5100.0).abs() < 1e-9);
+        assert!((rows.iter().map(|row| row.percent).sum::<f64>() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn equal_sizes_are_ordered_by_name() {
+        let entries = [
+            entry(30, EntryType::File, 10),
+            entry(2, EntryType::File, 10),
+            entry(100, EntryType::File, 10),
+        ];
+        let owners: Vec<_> = aggregate(&entries, true)
+            .into_iter()
+            .map(|row| row.owner)
+            .collect();
+        assert_eq!(owners, ["100", "2", "30"]);
+    }
+
+    #[test]
+    fn empty_files_have_no_share() {
+        let rows = aggregate(&[entry(7, EntryType::File, 0)], true);
+        assert_eq!(rows[0].files, 1);
+        assert_eq!(rows[0].percent, 0.0);
+        assert!(aggregate(&[], true).is_empty());
+    }
+
+    #[test]
+    fn unknown_uids_keep_their_number() {
+        // Far above what any user database hands out
+        let rows = aggregate(&[entry(3_999_999_999, EntryType::File, 1)], false);
+        assert_eq!(rows[0].owner, "3999999999");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn known_uids_are_named() {
+        let rows = aggregate(&[entry(0, EntryType::File, 1)], false);
+        assert_eq!(rows[0].owner, "root");
+        assert_eq!(owner_name(0, true), "0");
+    }
+
+    // Files can only be given away as root, so on disk everything is the caller's
+    #[cfg(unix)]
+    #[test]
+    fn files_on_disk_group_under_their_owner() {
+        let fixture = crate::fixture::Fixture::new();
+        fixture.file("a", b"12345").file("sub/b", b"123").dir("sub");
+        let entries: Vec<_> = ["a", "sub", "sub/b"]
+            .iter()
+            .map(|name| RawEntry::from_path(&fixture.join(name), name.to_string()).unwrap())
+            .collect();
+        let rows = aggregate(&entries, true);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].owner, caller_uid().unwrap().to_string());
+        assert_eq!((rows[0].files, rows[0].dirs), (2, 1));
+    }
+}
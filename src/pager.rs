@@ -0,0 +1,72 @@
+use crate::terminal;
+use clap::ValueEnum;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+// less: keep colors, quit when it fits on one screen, leave the listing on the screen
+const DEFAULT_PAGER: &str = "less -RFX";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PagingMode {
+    /// Only output taller than the terminal, never JSON, YAML or CSV
+    Auto,
+    Always,
+    Never,
+}
+
+impl PagingMode {
+    /// Whether output is rendered into memory first, to be handed to `show`. `data` is
+    /// set for formats meant for other programs, which aren't paged unless forced.
+    pub fn wanted(self, data: bool) -> bool {
+        match self {
+            PagingMode::Never => false,
+            PagingMode::Auto => !data && io::stdout().is_terminal(),
+            PagingMode::Always => true,
+        }
+    }
+}
+
+/// Writes the already rendered `output` through `$PAGER`, or straight to `out` when it
+/// fits on the terminal (`auto`), paging is turned off with an empty `$PAGER` or `cat`,
+/// or the pager can't be started.
+pub fn show(out: &mut impl Write, output: &[u8], mode: PagingMode) -> io::Result<()> {
+    let lines = output.iter().filter(|&&b| b == b'\n').count();
+    let fits = terminal::rows().is_some_and(|rows| lines < rows);
+    let pager = env::var("PAGER").unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let mut words = pager.split_whitespace();
+    let program = words.next().filter(|&p| p != "cat");
+
+    let Some(program) = program.filter(|_| mode == PagingMode::Always || !fits) else {
+        return write_all(out, output);
+    };
+    let mut child = match Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(_) => return write_all(out, output),
+    };
+
+    // Quitting the pager early closes the pipe, that's not an error here. Ctrl-C is
+    // the pager's to handle while it's open.
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_IGN);
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+    }
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(output) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    child.wait()?;
+    Ok(())
+}
+
+fn write_all(out: &mut impl Write, output: &[u8]) -> io::Result<()> {
+    out.write_all(output)?;
+    out.flush()
+}
@@ -0,0 +1,158 @@
+use regex::Regex;
+use std::ops::Range;
+
+// Reverse video on and off, leaving any color around it untouched
+const HIGHLIGHT_ON: &str = "\x1b[7m";
+const HIGHLIGHT_OFF: &str = "\x1b[27m";
+
+/// A name filter from `--regex` or `--match`, globs are translated into an anchored regex.
+#[derive(Debug, Clone)]
+pub struct NamePattern {
+    regex: Regex,
+    // Globs always match the whole name, so their literal parts are what gets highlighted
+    glob: bool,
+    literal_len: usize,
+}
+
+impl NamePattern {
+    pub fn is_match(&self, name: &str) -> bool {
+        self.regex.is_match(name)
+    }
+
+    /// How much of the pattern is literal text, used to prefer `~/src/app` over `~/src/*`.
+    pub fn specificity(&self) -> usize {
+        self.literal_len
+    }
+
+    /// Byte ranges of `name` that the pattern matched.
+    pub fn ranges(&self, name: &str) -> Vec<Range<usize>> {
+        if !self.glob {
+            return self
+                .regex
+                .find_iter(name)
+                .map(|m| m.range())
+                .filter(|r| !r.is_empty())
+                .collect();
+        }
+        let Some(captures) = self.regex.captures(name) else {
+            return Vec::new();
+        };
+        captures
+            .iter()
+            .skip(1)
+            .flatten()
+            .map(|m| m.range())
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+}
+
+pub fn parse_regex(input: &str) -> Result<NamePattern, String> {
+    let regex = Regex::new(input).map_err(|e| e.to_string())?;
+    Ok(NamePattern {
+        regex,
+        glob: false,
+        literal_len: 0,
+    })
+}
+
+/// Parses a shell glob (`*`, `?` and `[...]` classes), each run of literal characters
+/// becomes a capture group so it can be highlighted.
+pub fn parse_glob(input: &str) -> Result<NamePattern, String> {
+    glob_to_regex(input, false)
+}
+
+/// Parses a glob over a whole path, where `*` stays within one component and `**` crosses them.
+pub fn parse_path_glob(input: &str) -> Result<NamePattern, String> {
+    glob_to_regex(input, true)
+}
+
+fn glob_to_regex(input: &str, path: bool) -> Result<NamePattern, String> {
+    // Names can contain newlines, which a glob wildcard matches like any other character
+    let mut regex = String::from("(?s)^");
+    let mut literal = String::new();
+    let literal_len = input.chars().filter(|c| !"*?[]!\\".contains(*c)).count();
+    let mut chars = input.chars().peekable();
+
+    let flush = |regex: &mut String, literal: &mut String| {
+        if !literal.is_empty() {
+            regex.push_str(&format!("({})", regex::escape(literal)));
+            literal.clear();
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                flush(&mut regex, &mut literal);
+                if path && chars.next_if_eq(&'*').is_none() {
+                    regex.push_str("[^/]*");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '?' => {
+                flush(&mut regex, &mut literal);
+                regex.push_str(if path { "[^/]" } else { "." });
+            }
+            '[' => {
+                flush(&mut regex, &mut literal);
+                let mut class = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == ']' && !class.is_empty() && class != "!" {
+                        closed = true;
+                        break;
+                    }
+                    class.push(c);
+                }
+                if !closed {
+                    return Err(format!("unclosed '[' in glob '{}'", input));
+                }
+                let negated = class.strip_prefix('!');
+                let body = negated.unwrap_or(&class).replace('\\', "\\\\");
+                regex.push('[');
+                if negated.is_some() {
+                    regex.push('^');
+                }
+                regex.push_str(&body.replace('[', "\\["));
+                regex.push(']');
+            }
+            '\\' => literal.push(chars.next().unwrap_or('\\')),
+            c => literal.push(c),
+        }
+    }
+    flush(&mut regex, &mut literal);
+    regex.push('$');
+
+    let regex = Regex::new(&regex).map_err(|e| format!("invalid glob '{}': {}", input, e))?;
+    Ok(NamePattern {
+        regex,
+        glob: true,
+        literal_len,
+    })
+}
+
+/// Wraps the given byte ranges of `name` in reverse video, overlapping ranges are merged.
+/// Every piece of the name goes through `render` on the way, so it can be escaped without
+/// the ranges going out of step.
+pub fn highlight(name: &str, ranges: &[Range<usize>], render: impl Fn(&str) -> String) -> String {
+    let mut ranges = ranges.to_vec();
+    ranges.sort_by_key(|r| r.start);
+
+    let mut out = String::with_capacity(name.len());
+    let mut pos = 0;
+    for range in ranges {
+        let start = range.start.max(pos);
+        if start >= range.end {
+            continue;
+        }
+        out.push_str(&render(&name[pos..start]));
+        out.push_str(HIGHLIGHT_ON);
+        out.push_str(&render(&name[start..range.end]));
+        out.push_str(HIGHLIGHT_OFF);
+        pos = range.end;
+    }
+    out.push_str(&render(&name[pos..]));
+    out
+}
@@ -0,0 +1,64 @@
+use crate::entry::EntryType;
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PermissionStyle {
+    /// `755`
+    Octal,
+    /// `drwxr-xr-x`, colored bit by bit in tables
+    Symbolic,
+}
+
+/// The mode of an entry the way `ls -l` writes it, e.g. `drwxr-xr-x` or `-rwsr-xr-t`.
+/// Setuid, setgid and sticky take the place of the execute bit they share, in capitals
+/// when that bit isn't set.
+pub fn symbolic(e_type: EntryType, mode: u32) -> String {
+    let mut text = String::with_capacity(10);
+    text.push(type_char(e_type));
+    let special = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+    for (shift, (special_bit, special_char)) in [6, 3, 0].into_iter().zip(special) {
+        let bits = mode >> shift;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special_bit != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+fn type_char(e_type: EntryType) -> char {
+    match e_type {
+        EntryType::Dir => 'd',
+        EntryType::Symlink => 'l',
+        EntryType::Fifo => 'p',
+        EntryType::Socket => 's',
+        EntryType::BlockDevice => 'b',
+        EntryType::CharDevice => 'c',
+        EntryType::File => '-',
+        EntryType::Unknown => '?',
+    }
+}
+
+/// Colors each character of a `symbolic` string on its own: the type in bold blue, read
+/// bits yellow, write bits red, execute bits green and unset bits dimmed. Setuid, setgid
+/// and sticky stand out in bold magenta. Anything after the ten mode characters, like the
+/// `+` of an ACL, is left as it is.
+pub fn paint(text: &str) -> String {
+    text.chars()
+        .enumerate()
+        .map(|(index, c)| match c {
+            _ if index == 0 && c != '-' => c.bright_blue().bold().to_string(),
+            '-' => c.default_color().dimmed().to_string(),
+            'r' => c.yellow().to_string(),
+            'w' => c.red().to_string(),
+            'x' => c.green().to_string(),
+            's' | 'S' | 't' | 'T' if index > 0 => c.bright_magenta().bold().to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,335 @@
+use crate::cli::{Cli, skipped_in_tree};
+use crate::collect::collect_entries;
+use crate::tree::{descends, tree_entries};
+use crate::{names, truncate};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+use owo_colors::OwoColorize;
+use std::collections::BTreeSet;
+use std::io::{self, IsTerminal, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+
+// The query line above the entries and the key help below them
+const CHROME_LINES: usize = 2;
+
+struct Candidate {
+    // What is printed once picked, relative to the current directory
+    path: PathBuf,
+    // What the filter is matched against, the path below the listed directory
+    name: String,
+    depth: usize,
+    is_dir: bool,
+}
+
+/// Lets the user pick entries of `path` in a full screen list drawn on stderr, so stdout
+/// only ever gets the picked paths. Returns them in listing order, or None when the user
+/// gave up. The terminal is restored however this ends, panics included.
+pub fn run(path: &Path, cli: &Cli) -> io::Result<Option<Vec<PathBuf>>> {
+    if !io::stderr().is_terminal() {
+        return Err(io::Error::other(
+            "--pick draws on stderr, which isn't a terminal",
+        ));
+    }
+    let candidates = candidates(path, cli);
+    let _screen = Screen::enter()?;
+    Picker::new(&candidates).run(cli)
+}
+
+// The entries a listing would show, or under --tree every entry of the tree in its order
+fn candidates(path: &Path, cli: &Cli) -> Vec<Candidate> {
+    // `./src` would be as good, `src` is what people type
+    let prefix = if path == Path::new(".") {
+        Path::new("")
+    } else {
+        path
+    };
+    let mut found = Vec::new();
+    if cli.tree {
+        tree_candidates(path, prefix, Path::new(""), 0, cli, &mut found);
+        return found;
+    }
+    for raw in collect_entries(path, cli, |raw, _| raw).entries {
+        found.push(Candidate {
+            path: raw.path_in(prefix),
+            name: raw.name.clone(),
+            depth: 0,
+            is_dir: raw.is_dir,
+        });
+    }
+    found
+}
+
+fn tree_candidates(
+    dir: &Path,
+    prefix: &Path,
+    relative: &Path,
+    depth: usize,
+    cli: &Cli,
+    found: &mut Vec<Candidate>,
+) {
+    if depth >= cli.depth {
+        return;
+    }
+    for raw in tree_entries(dir, cli) {
+        let entry_relative = raw.path_in(relative);
+        found.push(Candidate {
+            path: prefix.join(&entry_relative),
+            name: entry_relative.to_string_lossy().to_string(),
+            depth,
+            is_dir: raw.is_dir,
+        });
+        if descends(dir, &raw, cli) && !skipped_in_tree(&raw, cli) {
+            let subdir = raw.path_in(dir);
+            tree_candidates(&subdir, prefix, &entry_relative, depth + 1, cli, found);
+        }
+    }
+}
+
+// Raw mode on an alternate screen for as long as it lives
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Screen> {
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            hook(info)
+        }));
+        terminal::enable_raw_mode()?;
+        execute!(io::stderr(), EnterAlternateScreen, cursor::Hide)?;
+        Ok(Screen)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+fn restore() {
+    let _ = execute!(io::stderr(), cursor::Show, LeaveAlternateScreen);
+    let _ = terminal::disable_raw_mode();
+}
+
+struct Picker<'a> {
+    candidates: &'a [Candidate],
+    query: String,
+    // Typing filters once it started, with `/` or any key but j and k
+    filtering: bool,
+    // Indices of the candidates matching the query, best first
+    matches: Vec<usize>,
+    cursor: usize,
+    // The first match on screen
+    offset: usize,
+    marked: BTreeSet<usize>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(candidates: &'a [Candidate]) -> Picker<'a> {
+        Picker {
+            candidates,
+            query: String::new(),
+            filtering: false,
+            matches: (0..candidates.len()).collect(),
+            cursor: 0,
+            offset: 0,
+            marked: BTreeSet::new(),
+        }
+    }
+
+    fn run(mut self, cli: &Cli) -> io::Result<Option<Vec<PathBuf>>> {
+        let mut err = io::stderr();
+        loop {
+            self.draw(&mut err, cli)?;
+            let Event::Key(key) = event::read()? else {
+                // Resizes only need a redraw
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(picked) = self.handle(key) {
+                return Ok(picked);
+            }
+        }
+    }
+
+    // Some(None) when the user gave up, Some(paths) once they picked
+    fn handle(&mut self, key: KeyEvent) -> Option<Option<Vec<PathBuf>>> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Some(None),
+            KeyCode::Char('c') if control => return Some(None),
+            KeyCode::Enter => return self.picked().map(Some),
+            KeyCode::Up => self.move_by(-1),
+            KeyCode::Down => self.move_by(1),
+            KeyCode::Char('k' | 'p') if control => self.move_by(-1),
+            KeyCode::Char('j' | 'n') if control => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-(self.page() as isize)),
+            KeyCode::PageDown => self.move_by(self.page() as isize),
+            KeyCode::Home => self.move_by(isize::MIN),
+            KeyCode::End => self.move_by(isize::MAX),
+            KeyCode::Tab => {
+                if let Some(&index) = self.matches.get(self.cursor)
+                    && !self.marked.remove(&index)
+                {
+                    self.marked.insert(index);
+                }
+                self.move_by(1);
+            }
+            KeyCode::Backspace => {
+                if self.query.pop().is_none() {
+                    self.filtering = false;
+                }
+                self.refilter();
+            }
+            KeyCode::Char('k') if !self.filtering => self.move_by(-1),
+            KeyCode::Char('j') if !self.filtering => self.move_by(1),
+            KeyCode::Char('/') if !self.filtering => self.filtering = true,
+            KeyCode::Char(c) if !control => {
+                self.filtering = true;
+                self.query.push(c);
+                self.refilter();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    // The marked entries, or the highlighted one when none are
+    fn picked(&self) -> Option<Vec<PathBuf>> {
+        let path = |&index: &usize| self.candidates[index].path.clone();
+        if self.marked.is_empty() {
+            return self.matches.get(self.cursor).map(|index| vec![path(index)]);
+        }
+        Some(self.marked.iter().map(path).collect())
+    }
+
+    fn move_by(&mut self, step: isize) {
+        let last = self.matches.len().saturating_sub(1);
+        self.cursor = self.cursor.saturating_add_signed(step).min(last);
+    }
+
+    fn refilter(&mut self) {
+        let query: Vec<char> = self.query.to_lowercase().chars().collect();
+        let mut scored: Vec<((usize, usize), usize)> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .filter_map(|(index, candidate)| Some((fuzzy_score(&candidate.name, &query)?, index)))
+            .collect();
+        // Stable, entries that match equally well keep the listing's order
+        scored.sort_by_key(|&(score, _)| score);
+        self.matches = scored.into_iter().map(|(_, index)| index).collect();
+        self.cursor = 0;
+        self.offset = 0;
+    }
+
+    fn page(&self) -> usize {
+        let rows = terminal::size().map_or(24, |(_, rows)| rows as usize);
+        rows.saturating_sub(CHROME_LINES).max(1)
+    }
+
+    fn draw(&mut self, err: &mut impl Write, cli: &Cli) -> io::Result<()> {
+        let (columns, _) = terminal::size()?;
+        let columns = columns as usize;
+        let page = self.page();
+        // Keep the highlight on screen
+        if self.cursor < self.offset {
+            self.offset = self.cursor;
+        } else if self.cursor >= self.offset + page {
+            self.offset = self.cursor + 1 - page;
+        }
+
+        queue!(err, cursor::MoveTo(0, 0), Clear(ClearType::All))?;
+        let prompt = if self.filtering {
+            format!("{} {}", ">".bright_green().bold(), self.query)
+        } else {
+            format!("{}", "> type or / to filter".dimmed())
+        };
+        write!(err, "{}", prompt)?;
+
+        for (row, &index) in self.matches.iter().enumerate().skip(self.offset).take(page) {
+            let candidate = &self.candidates[index];
+            let mark = if self.marked.contains(&index) {
+                "* "
+            } else {
+                "  "
+            };
+            let highlighted = row == self.cursor;
+            let name = candidate_label(candidate, !highlighted, cli);
+            let width = columns.saturating_sub(mark.len()).max(1);
+            let line = format!("{}{}", mark, truncate::middle(&name, width));
+            let screen_row = (row - self.offset + 1) as u16;
+            queue!(err, cursor::MoveTo(0, screen_row))?;
+            if highlighted {
+                write!(err, "{}", line.reversed())?;
+            } else {
+                write!(err, "{}", line)?;
+            }
+        }
+
+        let status = format!(
+            "{}/{}{}  enter: pick  tab: mark  esc: quit",
+            self.matches.len(),
+            self.candidates.len(),
+            if self.marked.is_empty() {
+                String::default()
+            } else {
+                format!(", {} marked", self.marked.len())
+            }
+        );
+        queue!(err, cursor::MoveTo(0, (page + 1) as u16))?;
+        write!(
+            err,
+            "{}",
+            truncate::middle(&status, columns.max(1)).dimmed()
+        )?;
+        err.flush()
+    }
+}
+
+// Indented under --tree, directories with a trailing slash and in bold blue when `colored`
+fn candidate_label(candidate: &Candidate, colored: bool, cli: &Cli) -> String {
+    let name = match candidate.name.rsplit_once('/') {
+        Some((_, base)) if candidate.depth > 0 => base,
+        _ => &candidate.name,
+    };
+    let name = names::sanitize(name, cli.escape);
+    let indent = "  ".repeat(candidate.depth);
+    if candidate.is_dir && colored {
+        format!("{}{}", indent, format!("{}/", name).bright_blue().bold())
+    } else if candidate.is_dir {
+        format!("{}{}/", indent, name)
+    } else {
+        format!("{}{}", indent, name)
+    }
+}
+
+// How well `name` matches `query`, a lowercase sequence of characters that must all appear
+// in `name` in order but not necessarily next to each other. Lower is better: the
+// characters skipped between the first and the last match, then how late the match starts.
+fn fuzzy_score(name: &str, query: &[char]) -> Option<(usize, usize)> {
+    let mut wanted = query.iter().peekable();
+    let (mut first, mut last) = (None, 0);
+    for (position, c) in name.to_lowercase().chars().enumerate() {
+        let Some(&&next) = wanted.peek() else {
+            break;
+        };
+        if c == next {
+            first.get_or_insert(position);
+            last = position;
+            wanted.next();
+        }
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+    let Some(first) = first else {
+        return Some((0, 0));
+    };
+    Some((last - first + 1 - query.len(), first))
+}
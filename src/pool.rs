@@ -0,0 +1,89 @@
+use crate::deadline::{self, Deadline};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Runs `f` on every item using one thread per core and returns the results in the order
+/// of `items`. Items nobody got to before the deadline expired have no result.
+pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<Option<R>> {
+    map_within(&deadline::RUN, items, f)
+}
+
+fn map_within<T: Sync, R: Send>(
+    deadline: &Deadline,
+    items: &[T],
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<Option<R>> {
+    let workers = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(items.len());
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    if workers == 0 {
+        return results;
+    }
+
+    let next = AtomicUsize::new(0);
+    let done: Vec<(usize, R)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(index) else {
+                            break;
+                        };
+                        if deadline.expired() {
+                            break;
+                        }
+                        done.push((index, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_default())
+            .collect()
+    });
+
+    for (index, result) in done {
+        results[index] = Some(result);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn results_keep_the_order_of_the_items() {
+        let items: Vec<u64> = (0..100).collect();
+        let results = map_within(&Deadline::new(), &items, |n| n * n);
+        let expected: Vec<_> = items.iter().map(|n| Some(n * n)).collect();
+        assert_eq!(results, expected);
+        assert!(map_within(&Deadline::new(), &[] as &[u64], |n| *n).is_empty());
+    }
+
+    // Every item takes as long as a stat on a mount that stopped answering
+    #[test]
+    fn workers_stop_at_the_deadline() {
+        let deadline = Deadline::new();
+        deadline.start(Duration::from_millis(100));
+        let items: Vec<u64> = (0..10_000).collect();
+        let started = Instant::now();
+        let results = map_within(&deadline, &items, |n| {
+            std::thread::sleep(Duration::from_millis(10));
+            *n
+        });
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(deadline.hit());
+        assert_eq!(results.len(), items.len());
+        let done = results.iter().filter(|r| r.is_some()).count();
+        assert!(done > 0 && done < items.len());
+        assert!(results.last().unwrap().is_none());
+    }
+}
@@ -0,0 +1,1899 @@
+use crate::cli::{
+    Cli, active_fields, hash_algorithm, mounted_elsewhere, shows_hidden, shows_path_headers,
+    size_style,
+};
+use crate::collect::{Listing, collect_entries, finish_listing, size_matches, walk_entries};
+use crate::columns::Field;
+use crate::duplicates::{DuplicateGroup, GroupKind};
+use crate::entry::{EntryType, FieldSource, RawEntry, UNIX_METADATA, device_numbers, size_cell};
+use crate::hash::HashAlgorithm;
+use crate::report::{fail, unreadable, warn};
+use crate::size::{SizeStyle, format_size};
+use crate::sort::SortKey;
+use crate::stat::{self, StatEntry};
+use crate::summary::{Overview, Summary};
+use crate::time_style::TimeStyle;
+use crate::top::{Ranking, Top};
+use crate::tree::{descends, read_error, tree_entries, tree_walk};
+use crate::{
+    archive, changes, columns, counts, deadline, diff, du, duplicates, duration, extensions,
+    failures, find, mounts, names, owners, permissions, style, summary, terminal, time_style,
+    truncate,
+};
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::ops::{ControlFlow, Range};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tabled::settings::{Alignment, Format, Remove, Width, object::Object};
+use tabled::{
+    Table,
+    builder::Builder,
+    settings::{
+        Color, Style,
+        object::{Columns, Rows},
+    },
+};
+
+/// Writes the listing of `path` in whichever output format was selected to `out`,
+/// returns whether the listing was truncated.
+pub fn print_listing<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    path: &Path,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+) -> io::Result<bool> {
+    let fields = active_fields(cli);
+
+    if cli.json_lines {
+        print_json_lines(out, path, cli, map, &fields)?;
+        return Ok(false);
+    }
+
+    let names_only = cli.print0 || cli.shell_quote || cli.oneline;
+    // Without a sort or limit nothing needs the whole listing, names go out as they are read
+    if names_only && cli.sort == SortKey::None && cli.limit == 0 {
+        stream_entries(out, path, cli, map, |out, entry| {
+            write_name(out, &entry, cli)
+        })?;
+        return Ok(false);
+    }
+
+    let files = collect_entries(path, cli, map);
+    print_collected(out, path, &files, &fields, cli)?;
+    Ok(files.truncated)
+}
+
+/// Lists each of `paths` under a header with its path, followed by the directories below
+/// it with -R. JSON and YAML print an object keyed by path instead. Returns whether any
+/// listing was truncated.
+pub fn print_listings<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    paths: &[PathBuf],
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+) -> io::Result<bool> {
+    let mut truncated = false;
+    if cli.json || cli.yaml {
+        let mut listings = serde_json::Map::new();
+        for path in paths {
+            walk_listings(path, cli, &mut |path| {
+                let files = collect_entries(path, cli, map);
+                truncated |= files.truncated;
+                let listing = with_summary(path, &files, &active_fields(cli), cli);
+                listings.insert(path.to_string_lossy().to_string(), listing);
+                Ok(())
+            })?;
+        }
+        if cli.yaml {
+            print_yaml(out, &listings)?;
+        } else {
+            print_json(out, &listings, cli)?;
+        }
+        return Ok(truncated);
+    }
+
+    let mut first = true;
+    for root in paths {
+        walk_listings(root, cli, &mut |path| {
+            if !first && !cli.csv {
+                writeln!(out)?;
+            }
+            first = false;
+            // The operands themselves were checked up front, directories below them are
+            // marked instead of listed as if they were empty
+            if path != root.as_path()
+                && let Some(e) = read_error(path)
+            {
+                failures::record_unreadable_dir();
+                if shows_path_headers(cli) {
+                    let header = format!("{}/", path.to_string_lossy().trim_end_matches('/'));
+                    let marker = format!("[{}]", e.kind());
+                    writeln!(out, "{} {}", header.bold(), marker.red())?;
+                }
+                return Ok(());
+            }
+            if cli.json_lines {
+                print_json_lines(out, path, cli, map, &active_fields(cli))?;
+                return Ok(());
+            }
+            let files = collect_entries(path, cli, map);
+            truncated |= files.truncated;
+            if shows_path_headers(cli) {
+                let header = listing_header(path, files.entries.len() + files.omitted);
+                writeln!(out, "{}", header.bold())?;
+            }
+            print_collected(out, path, &files, &active_fields(cli), cli)
+        })?;
+    }
+    Ok(truncated)
+}
+
+// Hands `path` to `f`, then with -R every directory below it in the order a tree shows them
+fn walk_listings(
+    path: &Path,
+    cli: &Cli,
+    f: &mut impl FnMut(&Path) -> io::Result<()>,
+) -> io::Result<()> {
+    f(path)?;
+    // Directories in an archive are listed with the rest of its members, an unreadable
+    // directory was already reported by `f`
+    if !cli.recursive || archive::opens(path, cli) || read_error(path).is_some() {
+        return Ok(());
+    }
+    for raw in tree_entries(path, cli) {
+        if deadline::expired() {
+            break;
+        }
+        if descends(path, &raw, cli) {
+            walk_listings(&raw.path_in(path), cli, f)?;
+        }
+    }
+    Ok(())
+}
+
+/// The line above each of several listings, e.g. `src/ (14 entries):`.
+pub fn listing_header(path: &Path, entries: usize) -> String {
+    let path = path.to_string_lossy();
+    format!(
+        "{}/ ({} {}):",
+        path.trim_end_matches('/'),
+        entries,
+        if entries == 1 { "entry" } else { "entries" }
+    )
+}
+
+// Every format but the streamed ones, for a listing read in full
+fn print_collected<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    path: &Path,
+    files: &Listing<T>,
+    fields: &[Field],
+    cli: &Cli,
+) -> io::Result<()> {
+    let names_only = cli.print0 || cli.shell_quote || cli.oneline;
+    if names_only {
+        print_names(out, &files.entries, cli)?;
+    } else if cli.yaml {
+        print_yaml(out, &with_summary(path, files, fields, cli))?;
+    } else if cli.markdown {
+        print_markdown(out, &files.entries, fields, cli)?;
+    } else if cli.csv {
+        print_csv(out, &files.entries, fields, cli)?;
+    } else if cli.json {
+        print_json(out, &with_summary(path, files, fields, cli), cli)?;
+    } else {
+        if cli.long && !cli.no_total && cli.quiet == 0 {
+            writeln!(out, "{}", total_header(path, &files.summary, cli))?;
+        }
+        print_recent(out, &files.entries, cli)?;
+        print_table(out, &files.entries, fields, cli)?;
+        if !cli.no_summary {
+            if files.omitted > 0 {
+                writeln!(out, "{}", format!("…and {} more", files.omitted).dimmed())?;
+            }
+            writeln!(out, "{}", summary_footer(&files.summary, cli))?;
+        }
+        warn_atime_caveat(path, cli);
+    }
+    Ok(())
+}
+
+// The --recent section above a table, when there is a terminal to see it and enough
+// entries to choose from
+fn print_recent<T: FieldSource>(out: &mut impl Write, entries: &[T], cli: &Cli) -> io::Result<()> {
+    let Some(count) = cli.recent.filter(|&n| n > 0) else {
+        return Ok(());
+    };
+    if !io::stdout().is_terminal() {
+        return Ok(());
+    }
+    let Some(recent) = most_recent(entries, count) else {
+        return Ok(());
+    };
+
+    let now = SystemTime::now();
+    let mut builder = Builder::default();
+    for entry in recent {
+        let age = entry
+            .shown_at()
+            .and_then(|t| duration::relative_time(t, now))
+            .unwrap_or_else(|| entry.cell(Field::Modified, cli));
+        builder.push_record([
+            entry.cell(Field::Name, cli),
+            age,
+            entry.cell(Field::Size, cli),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    table.modify(Columns::one(1), Color::FG_BRIGHT_GREEN);
+    table.modify(Columns::one(2), Color::FG_BRIGHT_MAGENTA);
+    table.modify(Columns::one(2), Alignment::right());
+    let heading = format!("Recently {}", cli.time.label().to_lowercase());
+    writeln!(out, "{}", heading.bright_yellow().bold())?;
+    writeln!(out, "{}", table)?;
+    writeln!(out)
+}
+
+// The `count` newest of `entries` by the time shown, ties by name, None when there are
+// fewer than `count` to choose from. `.` and `..` are never among them.
+fn most_recent<T: FieldSource>(entries: &[T], count: usize) -> Option<Vec<&T>> {
+    let mut recent: Vec<&T> = entries
+        .iter()
+        .filter(|entry| !matches!(entry.name(), "." | ".."))
+        .collect();
+    if recent.len() < count {
+        return None;
+    }
+    recent.sort_by(|a, b| {
+        b.shown_at()
+            .cmp(&a.shown_at())
+            .then_with(|| a.name().cmp(b.name()))
+    });
+    recent.truncate(count);
+    Some(recent)
+}
+
+// The bare array stays the default so existing consumers keep working
+fn with_summary<T: FieldSource + Serialize>(
+    path: &Path,
+    files: &Listing<T>,
+    fields: &[Field],
+    cli: &Cli,
+) -> serde_json::Value {
+    let entries = serializable_entries(&files.entries, fields, cli);
+    if !cli.summary {
+        return entries;
+    }
+    let mut wrapper = serde_json::json!({
+        "entries": entries,
+        "summary": files.summary,
+    });
+    if cli.limit != 0 {
+        wrapper["omitted"] = files.omitted.into();
+    }
+    if let Ok(usage) = mounts::statvfs(path) {
+        wrapper["filesystem"] = serde_json::json!({
+            "size_bytes": usage.size,
+            "used_bytes": usage.used,
+            "available_bytes": usage.available,
+            "use_percent": usage.use_percent(),
+        });
+    }
+    wrapper
+}
+
+/// The line above long tables, e.g. `total 4.2 MiB in 37 entries · filesystem 213 GiB free
+/// of 500 GiB (57% used)`. The filesystem part is left out where statvfs can't tell, and
+/// turns yellow from 80% used on, red from 95%.
+pub fn total_header(path: &Path, summary: &Summary, cli: &Cli) -> String {
+    const WARN_PERCENT: u64 = 80;
+    const CRITICAL_PERCENT: u64 = 95;
+
+    let style = size_style(cli).for_totals();
+    let entries = summary.files + summary.directories;
+    let total = format!(
+        "total {} in {} {}",
+        format_size(summary.total_bytes, style),
+        entries,
+        if entries == 1 { "entry" } else { "entries" }
+    )
+    .dimmed()
+    .to_string();
+    let Some((usage, percent)) = mounts::statvfs(path)
+        .ok()
+        .and_then(|usage| Some((usage, usage.use_percent()?)))
+    else {
+        return total;
+    };
+    let filesystem = format!(
+        "filesystem {} free of {} ({}% used)",
+        format_size(usage.available, style),
+        format_size(usage.size, style),
+        percent
+    );
+    let filesystem = if percent >= CRITICAL_PERCENT {
+        filesystem.bright_red().bold().to_string()
+    } else if percent >= WARN_PERCENT {
+        filesystem.yellow().to_string()
+    } else {
+        filesystem.dimmed().to_string()
+    };
+    format!("{} {} {}", total, "·".dimmed(), filesystem)
+}
+
+/// The dimmed line under tables and trees, e.g. `3 directories, 17 files, 4.2 MiB total`.
+pub fn summary_footer(summary: &Summary, cli: &Cli) -> String {
+    let skipped = if summary.skipped > 0 {
+        format!(", {} skipped", summary.skipped)
+    } else {
+        String::default()
+    };
+    let unreadable = if summary.unreadable > 0 {
+        format!(", {} unreadable", summary.unreadable)
+    } else {
+        String::default()
+    };
+    format!(
+        "{}, {} total{}{}",
+        summary::counts(summary),
+        format_size(summary.total_bytes, size_style(cli).for_totals()),
+        skipped,
+        unreadable
+    )
+    .dimmed()
+    .to_string()
+}
+
+// Entries keep their full schema unless --fields picked a subset
+fn serializable_entries<T: FieldSource + Serialize>(
+    entries: &[T],
+    fields: &[Field],
+    cli: &Cli,
+) -> serde_json::Value {
+    if cli.fields.is_empty() {
+        return serde_json::to_value(entries).unwrap_or_default();
+    }
+    entries
+        .iter()
+        .map(|entry| selected_fields(entry, fields))
+        .collect()
+}
+
+fn selected_fields<T: FieldSource>(entry: &T, fields: &[Field]) -> serde_json::Value {
+    let mut object: serde_json::Map<_, _> = fields
+        .iter()
+        .map(|f| (columns::spec(*f).json_key.to_string(), entry.json(*f)))
+        .collect();
+    if let Some(error) = entry.error() {
+        object.insert("error".to_string(), error.into());
+    }
+    object.into()
+}
+
+// The time column is named after the timestamp --time picked
+fn column_label(field: Field, cli: &Cli) -> &'static str {
+    match field {
+        Field::Modified => cli.time.label(),
+        _ => columns::spec(field).label,
+    }
+}
+
+// Names longer than `name_width` are cut short in the middle
+fn build_table<T: FieldSource>(
+    entries: &[T],
+    fields: &[Field],
+    header: bool,
+    name_width: Option<usize>,
+    cli: &Cli,
+) -> Table {
+    let mut builder = Builder::default();
+    if header {
+        builder.push_record(fields.iter().map(|f| column_label(*f, cli)));
+    }
+    for entry in entries {
+        builder.push_record(fields.iter().map(|f| match (f, name_width) {
+            (Field::Name, Some(width)) => truncate::middle(&entry.cell(*f, cli), width),
+            _ => entry.cell(*f, cli),
+        }));
+    }
+    builder.build()
+}
+
+// Borders, alignment and minimum widths, everything that decides how wide the table is
+fn lay_out(table: &mut Table, fields: &[Field], header: bool, cli: &Cli) {
+    style::apply(table, cli.style, header);
+
+    table.modify(Columns::new(..), Alignment::left());
+    for (index, field) in fields.iter().enumerate() {
+        let spec = columns::spec(*field);
+        if spec.right_aligned {
+            table.modify(Columns::one(index), Alignment::right());
+        }
+        // Minimum widths to prevent cramping
+        table.modify(Columns::one(index), Width::increase(spec.min_width));
+    }
+}
+
+// How wide names may be for `table` to fit the terminal, None when it fits as it is or
+// isn't shown on a terminal at all
+fn name_width<T: FieldSource>(
+    table: &Table,
+    entries: &[T],
+    fields: &[Field],
+    cli: &Cli,
+) -> Option<usize> {
+    if cli.no_truncate || !fields.contains(&Field::Name) {
+        return None;
+    }
+    let excess = table.total_width().checked_sub(terminal::columns()?)?;
+    let widest = entries
+        .iter()
+        .map(|entry| truncate::display_width(&entry.cell(Field::Name, cli)))
+        .max()?;
+    let width = widest
+        .saturating_sub(excess)
+        .max(columns::spec(Field::Name).min_width);
+    (excess > 0 && width < widest).then_some(width)
+}
+
+fn print_table<T: FieldSource>(
+    out: &mut impl Write,
+    entries: &[T],
+    fields: &[Field],
+    cli: &Cli,
+) -> io::Result<()> {
+    let header = !cli.no_header;
+    let mut table = build_table(entries, fields, header, None, cli);
+    lay_out(&mut table, fields, header, cli);
+    if let Some(width) = name_width(&table, entries, fields, cli) {
+        table = build_table(entries, fields, header, Some(width), cli);
+        lay_out(&mut table, fields, header, cli);
+    }
+
+    if header {
+        table.modify(Rows::first(), cli.theme.header());
+    }
+
+    for (index, field) in fields.iter().enumerate() {
+        let spec = columns::spec(*field);
+        table.modify(
+            Columns::one(index),
+            cli.theme.column(&field.to_string(), &spec.color),
+        );
+    }
+
+    let first_entry_row = usize::from(header);
+    for (row, entry) in entries.iter().enumerate() {
+        for (index, field) in fields.iter().enumerate() {
+            if let Some(color) = entry.highlight(*field, cli) {
+                table.modify((first_entry_row + row, index), color);
+            }
+        }
+    }
+
+    writeln!(out, "{}", table)
+}
+
+fn print_json_lines<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    path: &Path,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+    fields: &[Field],
+) -> io::Result<()> {
+    stream_entries(out, path, cli, map, |out, entry| {
+        write_json_line(out, &entry, fields, cli)
+    })
+}
+
+fn write_json_line<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    entry: &T,
+    fields: &[Field],
+    cli: &Cli,
+) -> io::Result<()> {
+    let line = if cli.fields.is_empty() {
+        serde_json::to_string(entry)
+    } else {
+        serde_json::to_string(&selected_fields(entry, fields))
+    };
+    writeln!(out, "{}", line.map_err(|e| serialize_error("JSON", e))?)
+}
+
+/// Lists the entries below `path` whose names contain `needle`, for --find. Matches are
+/// written as they are found unless a table, --sort or --limit needs all of them first.
+pub fn print_find<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    path: &Path,
+    needle: &str,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+) -> io::Result<()> {
+    let fields = active_fields(cli);
+    let names_only = cli.print0 || cli.shell_quote || cli.oneline;
+    if cli.json_lines || names_only && cli.sort == SortKey::None && cli.limit == 0 {
+        let mut result = Ok(());
+        find::walk(path, needle, cli, &mut |dir, mut raw| {
+            if !fill_in(dir, &mut raw, cli) {
+                return ControlFlow::Continue(());
+            }
+            let entry = map(raw, cli);
+            result = if cli.json_lines {
+                write_json_line(out, &entry, &fields, cli)
+            } else {
+                write_name(out, &entry, cli)
+            }
+            .and_then(|_| out.flush());
+            if result.is_ok() {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+        return result;
+    }
+
+    let mut entries = Vec::new();
+    find::walk(path, needle, cli, &mut |dir, mut raw| {
+        if fill_in(dir, &mut raw, cli) {
+            entries.push(raw);
+        }
+        ControlFlow::Continue(())
+    });
+    let files = finish_listing(entries, false, cli, map);
+    print_collected(out, path, &files, &fields, cli)
+}
+
+/// Writes the entries --newest or --largest asks for, best first, in whatever format was
+/// chosen. With -R every directory below `path` is searched, the entries then carry their
+/// paths relative to it. Returns false when no entry passed the filters.
+pub fn print_top<T: FieldSource + Serialize>(
+    out: &mut impl Write,
+    path: &Path,
+    (ranking, count): (Ranking, usize),
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+) -> io::Result<bool> {
+    let mut top = Top::new(ranking, count);
+    walk_listings(path, cli, &mut |dir| {
+        let relative = dir.strip_prefix(path).unwrap_or(dir);
+        let in_archive = archive::opens(dir, cli);
+        walk_entries(
+            dir,
+            cli,
+            |raw, _| raw,
+            |mut raw| {
+                if raw.is_dot_entry() || !in_archive && !fill_in(dir, &mut raw, cli) {
+                    return ControlFlow::Continue(());
+                }
+                if cli.recursive && raw.path.is_none() {
+                    raw.path = Some(raw.path_in(relative).to_string_lossy().to_string());
+                }
+                top.offer(raw);
+                ControlFlow::Continue(())
+            },
+        );
+        Ok(())
+    })?;
+
+    let entries = top.into_entries();
+    if entries.is_empty() {
+        return Ok(false);
+    }
+    let fields = active_fields(cli);
+    if cli.json_lines {
+        for raw in entries {
+            write_json_line(out, &map(raw, cli), &fields, cli)?;
+        }
+        return Ok(true);
+    }
+    let mut summary = Summary::default();
+    for raw in &entries {
+        summary.add(raw.is_real_dir(), raw.len);
+    }
+    let files = Listing {
+        entries: entries.into_iter().map(|raw| map(raw, cli)).collect(),
+        summary,
+        truncated: false,
+        omitted: 0,
+    };
+    print_collected(out, path, &files, &fields, cli)?;
+    Ok(true)
+}
+
+/// Writes every entry with `write` as soon as it is read, flushing after each so the output
+/// can be watched or cut short while the directory is still being read.
+fn stream_entries<W: Write, T>(
+    out: &mut W,
+    path: &Path,
+    cli: &Cli,
+    map: fn(RawEntry, &Cli) -> T,
+    mut write: impl FnMut(&mut W, T) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut result = Ok(());
+    let in_archive = archive::opens(path, cli);
+    // Entries are mapped once --du had a chance to fill in directory sizes
+    walk_entries(
+        path,
+        cli,
+        |raw, _| raw,
+        |mut raw| {
+            if !in_archive && !fill_in(path, &mut raw, cli) {
+                return ControlFlow::Continue(());
+            }
+            result = write(out, map(raw, cli)).and_then(|_| out.flush());
+            if result.is_ok() {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        },
+    );
+    result
+}
+
+// What collect_entries adds to whole listings, for a single entry of `dir`. False when
+// the size --du found is filtered out.
+fn fill_in(dir: &Path, raw: &mut RawEntry, cli: &Cli) -> bool {
+    if cli.du && raw.is_real_dir() && !mounted_elsewhere(dir, raw, cli) {
+        raw.len = du::total(&raw.path_in(dir), cli.one_file_system);
+        if !size_matches(true, raw.len, cli) {
+            return false;
+        }
+    }
+    if cli.dir_counts && raw.is_real_dir() {
+        raw.entry_count = Some(
+            counts::entry_count(&raw.path_in(dir), shows_hidden(cli)).map_err(|e| e.to_string()),
+        );
+    }
+    if let Some(algorithm) = hash_algorithm(cli)
+        && raw.e_type == EntryType::File
+    {
+        raw.hash = Some(
+            algorithm
+                .digest_file(&raw.path_in(dir))
+                .map_err(|e| e.to_string()),
+        );
+    }
+    true
+}
+
+// Printed once per listing, access times can't be trusted on noatime/relatime mounts
+fn warn_atime_caveat(path: &Path, cli: &Cli) {
+    if !active_fields(cli).contains(&Field::Accessed) {
+        return;
+    }
+    let Some(mount) = mounts::mount_for(path) else {
+        return;
+    };
+
+    if mount.has_option("noatime") {
+        warn(
+            cli,
+            format!(
+                "note: {} is mounted noatime, access times are never updated so every entry may look unused",
+                mount.mount_point
+            )
+            .yellow(),
+        );
+    } else if mount.has_option("relatime") {
+        warn(
+            cli,
+            format!(
+                "note: {} is mounted relatime, access times are only updated about once a day",
+                mount.mount_point
+            )
+            .yellow(),
+        );
+    }
+}
+
+fn print_markdown<T: FieldSource>(
+    out: &mut impl Write,
+    entries: &[T],
+    fields: &[Field],
+    cli: &Cli,
+) -> io::Result<()> {
+    // Markdown tables can't exist without a header
+    let mut table = build_table(entries, fields, true, None, cli);
+
+    table.modify(Columns::new(..), Format::content(escape_markdown));
+    table.with(Style::markdown());
+
+    writeln!(out, "{}", table)
+}
+
+// Pipes would end the cell and backticks would open a code span
+fn escape_markdown(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace('`', "\\`")
+}
+
+pub fn print_yaml<T: Serialize + ?Sized>(out: &mut impl Write, value: &T) -> io::Result<()> {
+    let yaml = serde_yaml::to_string(value).map_err(|e| serialize_error("YAML", e))?;
+    write!(out, "{}", yaml)
+}
+
+/// Writes `value` as indented JSON, or on a single line under --compact.
+pub fn print_json<T: Serialize + ?Sized>(
+    out: &mut impl Write,
+    value: &T,
+    cli: &Cli,
+) -> io::Result<()> {
+    let json = if cli.compact {
+        serde_json::to_string(value)
+    } else {
+        serde_json::to_string_pretty(value)
+    };
+    writeln!(out, "{}", json.map_err(|e| serialize_error("JSON", e))?)
+}
+
+// Nothing is written then, the caller reports it like any other failed write
+fn serialize_error(format: &str, e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("cannot serialize {}: {}", format, e))
+}
+
+fn print_csv<T: FieldSource>(
+    out: &mut impl Write,
+    entries: &[T],
+    fields: &[Field],
+    cli: &Cli,
+) -> io::Result<()> {
+    let header: Vec<String> = fields
+        .iter()
+        .map(|f| column_label(*f, cli).to_string())
+        .collect();
+    let rows = entries
+        .iter()
+        .map(|entry| fields.iter().map(|f| entry.csv(*f, cli)).collect());
+
+    let header = (!cli.no_header).then_some(header);
+    for record in header.into_iter().chain(rows) {
+        let line: Vec<_> = record.iter().map(|field| csv_field(field)).collect();
+        // RFC 4180 wants CRLF line endings
+        write!(out, "{}\r\n", line.join(","))?;
+    }
+    Ok(())
+}
+
+fn print_names<T: FieldSource>(out: &mut impl Write, entries: &[T], cli: &Cli) -> io::Result<()> {
+    for entry in entries {
+        write_name(out, entry, cli)?;
+    }
+    Ok(())
+}
+
+// -1 shows names the way the table would, the other two modes are meant for programs
+fn write_name<T: FieldSource>(out: &mut impl Write, entry: &T, cli: &Cli) -> io::Result<()> {
+    if cli.oneline {
+        return writeln!(out, "{}", entry.cell(Field::Name, cli));
+    }
+    let name = entry.exact_name();
+    if cli.print0 {
+        out.write_all(&name)?;
+        out.write_all(b"\0")
+    } else {
+        out.write_all(&names::shell_quote_bytes(&name))?;
+        out.write_all(b"\n")
+    }
+}
+
+/// Quotes a field when it contains a separator, quote or line break, doubling inner quotes.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn print_mounts(out: &mut impl Write, cli: &Cli) -> io::Result<()> {
+    let mut mounts = match mounts::get_mounts(cli.all_mounts) {
+        Ok(mounts) => mounts,
+        Err(e) => fail(cli, format!("cannot read the mount table: {}", e)),
+    };
+    mounts::sort_mounts(&mut mounts, cli.sort);
+    if cli.reverse {
+        mounts.reverse();
+    }
+
+    if cli.json {
+        return print_json(out, &mounts, cli);
+    }
+
+    if cli.yaml {
+        return print_yaml(out, &mounts);
+    }
+
+    let mut table = Table::new(mounts);
+
+    humanize_sizes(&mut table, Columns::new(2..5), cli);
+
+    table.modify(Columns::new(..), Alignment::left());
+    table.modify(Columns::new(2..6), Alignment::right());
+
+    table.modify(Rows::first(), cli.theme.header());
+
+    color_columns(
+        &mut table,
+        &[
+            (0..1, "filesystem", Color::FG_BRIGHT_CYAN),
+            (1..2, "type", Color::FG_WHITE),
+            (2..5, "size", Color::FG_BRIGHT_MAGENTA), // Size, Used, Avail
+            (5..6, "use_percent", Color::FG_BRIGHT_YELLOW),
+            (6..7, "mounted_on", Color::FG_BRIGHT_BLUE),
+            (7..8, "options", Color::FG_WHITE),
+        ],
+        cli,
+    );
+
+    if cli.no_header {
+        table.with(Remove::row(Rows::first()));
+    }
+    style::apply(&mut table, cli.style, !cli.no_header);
+
+    writeln!(out, "{}", table)
+}
+
+/// Writes how `path` differs from the listing in `manifest`, returns whether the listing was truncated.
+pub fn print_changes(
+    out: &mut impl Write,
+    path: &Path,
+    manifest: &Path,
+    cli: &Cli,
+) -> io::Result<bool> {
+    let old: Vec<changes::ManifestEntry> = match fs::read_to_string(manifest)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(old) => old,
+        Err(e) => fail(
+            cli,
+            format!("cannot read manifest {}: {}", manifest.display(), e),
+        ),
+    };
+
+    let files = collect_entries(path, cli, |raw, cli| changes::ManifestEntry {
+        modified: raw.modified.map(|t| time_style::rfc3339(t, cli.utc)),
+        name: raw.name,
+        len_bytes: raw.len,
+        dev: UNIX_METADATA.then_some(raw.dev),
+        ino: UNIX_METADATA.then_some(raw.ino),
+    });
+    let changes = changes::compare(&old, &files.entries);
+
+    if cli.json {
+        print_json(out, &changes, cli)?;
+        return Ok(files.truncated);
+    }
+
+    for change in changes {
+        let line = match change {
+            changes::Change::Added { name } => format!("added: {}", name).green().to_string(),
+            changes::Change::Removed { name } => format!("removed: {}", name).red().to_string(),
+            changes::Change::Modified { name } => {
+                format!("modified: {}", name).yellow().to_string()
+            }
+            changes::Change::Renamed { from, name } => {
+                format!("renamed: {} -> {}", from, name).cyan().to_string()
+            }
+        };
+        writeln!(out, "{}", line)?;
+    }
+    Ok(files.truncated)
+}
+
+/// Writes the per-owner totals of `path`, returns whether the listing was truncated.
+pub fn print_by_owner(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<bool> {
+    let files = collect_entries(path, cli, |raw, _| raw);
+    let usage = owners::aggregate(&files.entries, cli.numeric_ids);
+
+    if cli.json {
+        print_json(out, &usage, cli)?;
+        return Ok(files.truncated);
+    }
+
+    if cli.yaml {
+        print_yaml(out, &usage)?;
+        return Ok(files.truncated);
+    }
+
+    let mut table = Table::new(usage);
+
+    humanize_sizes(&mut table, Columns::new(3..4), cli);
+
+    table.modify(Columns::new(..), Alignment::left());
+    table.modify(Columns::new(1..5), Alignment::right());
+
+    table.modify(Rows::first(), cli.theme.header());
+
+    color_columns(
+        &mut table,
+        &[
+            (0..1, "owner", Color::FG_BRIGHT_WHITE),
+            (1..2, "files", Color::FG_WHITE),
+            (2..3, "dirs", Color::FG_WHITE),
+            (3..4, "size", Color::FG_BRIGHT_MAGENTA),
+            (4..5, "share", Color::FG_BRIGHT_YELLOW),
+        ],
+        cli,
+    );
+
+    if cli.no_header {
+        table.with(Remove::row(Rows::first()));
+    }
+    style::apply(&mut table, cli.style, !cli.no_header);
+
+    writeln!(out, "{}", table)?;
+    Ok(files.truncated)
+}
+
+/// Writes the files of `path`, or of everything below it under -R, summed up per
+/// extension. Traversal follows -R listings, so hidden directories are only entered with
+/// --all, and the filters apply to every file. Always returns false, nothing is truncated.
+pub fn print_by_extension(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<bool> {
+    let mut files = Vec::new();
+    walk_listings(path, cli, &mut |dir| {
+        walk_entries(
+            dir,
+            cli,
+            |raw, _| raw,
+            |raw| {
+                if !raw.is_dir && !raw.is_dot_entry() {
+                    files.push(raw);
+                }
+                ControlFlow::Continue(())
+            },
+        );
+        Ok(())
+    })?;
+    let usage = extensions::aggregate(&files);
+
+    if cli.json {
+        print_json(out, &usage, cli)?;
+        return Ok(false);
+    }
+
+    if cli.yaml {
+        print_yaml(out, &usage)?;
+        return Ok(false);
+    }
+
+    let mut table = Table::new(usage);
+
+    humanize_sizes(&mut table, Columns::new(2..3), cli);
+
+    table.modify(Columns::new(..), Alignment::left());
+    table.modify(Columns::new(1..4), Alignment::right());
+
+    table.modify(Rows::first(), cli.theme.header());
+
+    color_columns(
+        &mut table,
+        &[
+            (0..1, "extension", Color::FG_BRIGHT_WHITE),
+            (1..2, "files", Color::FG_WHITE),
+            (2..3, "size", Color::FG_BRIGHT_MAGENTA),
+            (3..4, "share", Color::FG_BRIGHT_YELLOW),
+        ],
+        cli,
+    );
+
+    if cli.no_header {
+        table.with(Remove::row(Rows::first()));
+    }
+    style::apply(&mut table, cli.style, !cli.no_header);
+
+    writeln!(out, "{}", table)?;
+    Ok(false)
+}
+
+/// Writes the totals of the listing of `path`, or of everything below it under -R,
+/// returns whether the listing was truncated.
+pub fn print_summary_only(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<bool> {
+    let mut overview = Overview::default();
+    let truncated = if cli.recursive {
+        for (entry_path, raw) in tree_walk(path, cli, true) {
+            // Contents are counted themselves, a --du total would count them twice
+            overview.add(&entry_path, &raw, false, cli.utc);
+        }
+        false
+    } else {
+        let files = collect_entries(path, cli, |raw, _| raw);
+        for raw in &files.entries {
+            overview.add(&raw.path_in(Path::new("")), raw, cli.du, cli.utc);
+        }
+        files.truncated
+    };
+
+    if cli.json {
+        print_json(out, &overview, cli)?;
+        return Ok(truncated);
+    }
+    if cli.yaml {
+        print_yaml(out, &overview)?;
+        return Ok(truncated);
+    }
+
+    let symlinks = if overview.symlinks == 1 {
+        "symlink"
+    } else {
+        "symlinks"
+    };
+    let summary = Summary {
+        directories: overview.directories,
+        files: overview.files,
+        total_bytes: overview.total_bytes,
+        skipped: 0,
+        unreadable: 0,
+    };
+    writeln!(
+        out,
+        "{}, {} {}, {} total",
+        summary::counts(&summary),
+        overview.symlinks,
+        symlinks,
+        format_size(overview.total_bytes, size_style(cli).for_totals())
+    )?;
+    if let Some(largest) = &overview.largest {
+        let name = names::sanitize(&largest.path, cli.escape);
+        writeln!(
+            out,
+            "{} {} ({})",
+            "largest:".bright_black(),
+            name.bright_cyan(),
+            format_size(largest.len_bytes, size_style(cli).for_totals()).bright_magenta()
+        )?;
+    }
+    if let Some(newest) = &overview.newest
+        && let Some(modified) = newest.modified_at
+    {
+        let name = names::sanitize(&newest.path, cli.escape);
+        let modified = time_style::format_time(modified, cli.time_style.as_ref(), cli.utc);
+        writeln!(
+            out,
+            "{} {} ({})",
+            "newest: ".bright_black(),
+            name.bright_cyan(),
+            modified.bright_blue()
+        )?;
+    }
+    Ok(truncated)
+}
+
+/// Writes the groups of identical files below `path`, each under its number and one color.
+pub fn print_duplicates(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<()> {
+    const GROUP_COLORS: [owo_colors::AnsiColors; 6] = [
+        owo_colors::AnsiColors::BrightCyan,
+        owo_colors::AnsiColors::BrightMagenta,
+        owo_colors::AnsiColors::BrightYellow,
+        owo_colors::AnsiColors::BrightGreen,
+        owo_colors::AnsiColors::BrightBlue,
+        owo_colors::AnsiColors::BrightRed,
+    ];
+    let algorithm = cli.hash.unwrap_or(HashAlgorithm::Sha256);
+    let groups = duplicates::find(path, algorithm, cli.recursive, cli);
+
+    if cli.json {
+        return print_json(out, &groups, cli);
+    }
+    if cli.yaml {
+        return print_yaml(out, &groups);
+    }
+
+    for (index, group) in groups.iter().enumerate() {
+        let color = GROUP_COLORS[index % GROUP_COLORS.len()];
+        writeln!(
+            out,
+            "{}",
+            duplicate_heading(index + 1, group, algorithm, cli)
+                .color(color)
+                .bold()
+        )?;
+        for member in &group.paths {
+            writeln!(
+                out,
+                "    {}",
+                names::sanitize(member, cli.escape).color(color)
+            )?;
+        }
+    }
+    if !cli.no_summary {
+        let copies = groups
+            .iter()
+            .filter(|g| g.kind == GroupKind::Duplicates)
+            .count();
+        let reclaimable: u64 = groups.iter().map(DuplicateGroup::reclaimable).sum();
+        let noun = if copies == 1 { "group" } else { "groups" };
+        writeln!(
+            out,
+            "{}",
+            format!(
+                "{} {} of duplicates, {} reclaimable",
+                copies,
+                noun,
+                format_size(reclaimable, size_style(cli).for_totals())
+            )
+            .dimmed()
+        )?;
+    }
+    Ok(())
+}
+
+/// Compares the directories `left` and `right` for --diff, as a table with a status
+/// column or as JSON and YAML. Returns whether either listing was truncated.
+pub fn print_diff(out: &mut impl Write, left: &Path, right: &Path, cli: &Cli) -> io::Result<bool> {
+    let (entries, truncated) = diff::compare(left, right, cli);
+    if cli.json {
+        print_json(out, &entries, cli)?;
+        return Ok(truncated);
+    }
+    if cli.yaml {
+        print_yaml(out, &entries)?;
+        return Ok(truncated);
+    }
+
+    let size = |side: &Option<diff::Side>| {
+        side.as_ref()
+            .map_or_else(|| "-".to_string(), |side| size_cell(side.len_bytes, cli))
+    };
+    let mut builder = Builder::default();
+    if !cli.no_header {
+        builder.push_record(["", "Name", "Left", "Right", "Differs"]);
+    }
+    for entry in &entries {
+        let color = match entry.status {
+            diff::Status::OnlyLeft => owo_colors::AnsiColors::Red,
+            diff::Status::OnlyRight => owo_colors::AnsiColors::Green,
+            diff::Status::Differs => owo_colors::AnsiColors::Yellow,
+            diff::Status::Same => owo_colors::AnsiColors::Default,
+        };
+        builder.push_record([
+            entry.status.symbol().color(color).to_string(),
+            names::sanitize(&entry.name, cli.escape)
+                .color(color)
+                .to_string(),
+            size(&entry.left),
+            size(&entry.right),
+            entry.reasons.join(", "),
+        ]);
+    }
+    let mut table = builder.build();
+    style::apply(&mut table, cli.style, !cli.no_header);
+    for index in [2, 3] {
+        table.modify(Columns::one(index), Alignment::right());
+    }
+    writeln!(out, "{}", table)?;
+
+    if !cli.no_summary {
+        let count = |status| entries.iter().filter(|e| e.status == status).count();
+        writeln!(
+            out,
+            "{}",
+            format!(
+                "{} only left, {} only right, {} differ, {} same",
+                count(diff::Status::OnlyLeft),
+                count(diff::Status::OnlyRight),
+                count(diff::Status::Differs),
+                count(diff::Status::Same)
+            )
+            .dimmed()
+        )?;
+    }
+    Ok(truncated)
+}
+
+/// Describes each of `paths` in a block of labeled lines for --stat, or as one object
+/// per path in JSON and YAML, keyed by path when there are several. Paths that can't be
+/// read are reported and skipped.
+pub fn print_stat(out: &mut impl Write, paths: &[PathBuf], cli: &Cli) -> io::Result<()> {
+    let stats: Vec<(&PathBuf, StatEntry)> = paths
+        .iter()
+        .filter_map(|path| {
+            let stat = stat::of(path, cli).inspect_err(|e| unreadable(cli, path, e));
+            Some((path, stat.ok()?))
+        })
+        .collect();
+
+    if cli.json || cli.yaml {
+        let value = match stats.as_slice() {
+            [(_, stat)] if paths.len() == 1 => serde_json::to_value(stat),
+            _ => stats
+                .iter()
+                .map(|(path, stat)| {
+                    let stat = serde_json::to_value(stat)?;
+                    Ok((path.to_string_lossy().to_string(), stat))
+                })
+                .collect::<serde_json::Result<serde_json::Map<_, _>>>()
+                .map(serde_json::Value::Object),
+        }
+        .map_err(|e| serialize_error("JSON", e))?;
+        return if cli.yaml {
+            print_yaml(out, &value)
+        } else {
+            print_json(out, &value, cli)
+        };
+    }
+
+    for (index, (_, stat)) in stats.iter().enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        let lines = stat_lines(stat, cli);
+        let width = lines
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or(0);
+        for (label, value) in lines {
+            let label = format!("{:>width$}:", label, width = width);
+            writeln!(out, "{} {}", label.bright_blue().bold(), value)?;
+        }
+    }
+    Ok(())
+}
+
+// The labels and values of a --stat block, lines Unix metadata would fill are left out
+// elsewhere
+fn stat_lines(stat: &StatEntry, cli: &Cli) -> Vec<(&'static str, String)> {
+    let bytes = |len: u64| {
+        format!(
+            "{} ({} bytes)",
+            format_size(len, size_style(cli).for_totals()),
+            len
+        )
+    };
+    let id = |name: &Option<String>, id: Option<u32>| match (name, id) {
+        (Some(name), Some(id)) if *name != id.to_string() => Some(format!("{} ({})", name, id)),
+        (Some(name), _) => Some(name.clone()),
+        _ => None,
+    };
+    let style = cli.time_style.as_ref().unwrap_or(&TimeStyle::LongIso);
+    let now = SystemTime::now();
+    let time = |time: Option<SystemTime>| match time {
+        Some(t) => {
+            let shown = time_style::format_time(t, Some(style), cli.utc);
+            match duration::relative_time(t, now) {
+                Some(age) => format!("{} {}", shown, format!("({})", age).dimmed()),
+                None => shown,
+            }
+        }
+        None => "-".dimmed().to_string(),
+    };
+
+    let mut lines = vec![
+        (
+            "Path",
+            names::sanitize(&stat.path, cli.escape).bold().to_string(),
+        ),
+        ("Type", stat.entry_type.to_string()),
+    ];
+    if let Some(target) = &stat.target {
+        lines.push(("Target", names::sanitize(target, cli.escape)));
+    }
+    lines.push(("Size", bytes(stat.len_bytes)));
+    lines.push(("Allocated", bytes(stat.allocated_bytes)));
+    if let Some(numbers) = &stat.device_numbers {
+        lines.push(("Device type", numbers.clone()));
+    }
+    lines.push((
+        "Permissions",
+        format!("{} ({})", permissions::paint(&stat.permissions), stat.mode),
+    ));
+    let optional = [
+        ("Owner", id(&stat.owner, stat.uid)),
+        ("Group", id(&stat.group, stat.gid)),
+        ("Links", stat.nlink.map(|n| n.to_string())),
+        ("Inode", stat.ino.map(|n| n.to_string())),
+        ("Device", stat.dev.map(device_numbers)),
+    ];
+    lines.extend(
+        optional
+            .into_iter()
+            .filter_map(|(label, value)| Some((label, value?))),
+    );
+    let [modified, accessed, changed, created] = stat.times;
+    lines.push(("Modified", time(modified)));
+    lines.push(("Accessed", time(accessed)));
+    lines.push(("Changed", time(changed)));
+    lines.push(("Created", time(created)));
+    if let Some(names) = &stat.xattrs {
+        let names = if names.is_empty() {
+            "-".dimmed().to_string()
+        } else {
+            names.join(", ")
+        };
+        lines.push(("Xattrs", names));
+    }
+    lines
+}
+
+// e.g. `[1] 3 copies of 4.0 KiB, sha256 ca8642fe164b…`
+fn duplicate_heading(
+    number: usize,
+    group: &DuplicateGroup,
+    algorithm: HashAlgorithm,
+    cli: &Cli,
+) -> String {
+    let size = format_size(group.len_bytes, size_style(cli).for_totals());
+    match (group.kind, &group.hash) {
+        (GroupKind::Duplicates, Some(hash)) => format!(
+            "[{}] {} copies of {}, {} {}…",
+            number,
+            group.paths.len(),
+            size,
+            algorithm
+                .to_possible_value()
+                .map(|v| v.get_name().to_string())
+                .unwrap_or_default(),
+            &hash[..hash.len().min(12)]
+        ),
+        _ => format!(
+            "[{}] {} hardlinks to one file of {}",
+            number,
+            group.paths.len(),
+            size
+        ),
+    }
+}
+
+// Colors each range of columns with the `[theme]` color of its column id, or its default
+fn color_columns(table: &mut Table, columns: &[(Range<usize>, &str, Color)], cli: &Cli) {
+    for (range, id, default) in columns {
+        table.modify(Columns::new(range.clone()), cli.theme.column(id, default));
+    }
+}
+
+// Rewrites byte counts in the given columns in the --si, -H or --bytes style, leaving the
+// header row alone
+fn humanize_sizes(table: &mut Table, columns: Columns<Range<usize>>, cli: &Cli) {
+    let style = size_style(cli);
+    if style == SizeStyle::Plain {
+        return;
+    }
+    table.modify(
+        columns.not(Rows::first()),
+        Format::content(move |s| {
+            s.parse()
+                .map(|bytes| format_size(bytes, style))
+                .unwrap_or(s.to_string())
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::{apply_quiet, needs_long_entries};
+    use crate::entry::{map_long_data, map_short_data};
+    use crate::fixture::{Fixture, cli, output};
+
+    // Only files, directory sizes depend on the filesystem the fixture is on
+    fn fixture() -> Fixture {
+        let fixture = Fixture::new();
+        fixture
+            .file("b.txt", b"hello")
+            .file("a.rs", b"fn main() {}\n")
+            .file(".hidden", b"");
+        fixture
+    }
+
+    fn listing(fixture: &Fixture, args: &[&str]) -> String {
+        let args = [&["--utc", "--no-truncate"], args].concat();
+        let mut cli = cli(&args);
+        apply_quiet(&mut cli);
+        output(|out| {
+            if needs_long_entries(&cli) {
+                print_listing(out, fixture.path(), &cli, map_long_data)
+            } else {
+                print_listing(out, fixture.path(), &cli, map_short_data)
+            }
+            .map(|_| ())
+        })
+    }
+
+    #[test]
+    fn short_table() {
+        assert_eq!(
+            listing(&fixture(), &[]),
+            "\
+╭─────────────────┬────────┬────────────┬─────────────────╮
+│ Name            │ Type   │ Size B     │ Modified        │
+├─────────────────┼────────┼────────────┼─────────────────┤
+│ a.rs            │ File   │ 13         │ Tue Nov 14 2023 │
+│ b.txt           │ File   │ 5          │ Tue Nov 14 2023 │
+╰─────────────────┴────────┴────────────┴─────────────────╯
+0 directories, 2 files, 18 B total
+"
+        );
+    }
+
+    #[test]
+    fn picked_fields_without_header() {
+        assert_eq!(
+            listing(&fixture(), &["--fields", "size,name", "--no-header", "-A"]),
+            "\
+╭────────────┬─────────────────╮
+│ 0          │ .hidden         │
+│ 13         │ a.rs            │
+│ 5          │ b.txt           │
+╰────────────┴─────────────────╯
+0 directories, 3 files, 18 B total
+"
+        );
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            listing(&fixture(), &["--csv"]),
+            "Name,Type,Size B,Modified\r\n\
+            a.rs,File,13,2023-11-14T22:13:20Z\r\n\
+            b.txt,File,5,2023-11-14T22:13:20Z\r\n"
+        );
+    }
+
+    #[test]
+    fn json_carries_every_entry() {
+        let text = listing(&fixture(), &["--json"]);
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+        let names: Vec<_> = entries
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.rs", "b.txt"]);
+        assert_eq!(entries[0]["len_bytes"], 13);
+        assert_eq!(entries[1]["e_type"], "File");
+    }
+
+    #[test]
+    fn names_one_per_line() {
+        assert_eq!(listing(&fixture(), &["-1"]), "a.rs\nb.txt\n");
+    }
+
+    #[test]
+    fn names_are_made_safe_to_print() {
+        let fixture = Fixture::new();
+        fixture.file("two\nlines", b"").file("my file", b"");
+        assert_eq!(listing(&fixture, &["-1"]), "my file\ntwo?lines\n");
+        assert_eq!(
+            listing(&fixture, &["-1", "--escape"]),
+            "my file\ntwo\\nlines\n"
+        );
+        assert_eq!(
+            listing(&fixture, &["-1", "--quote-names"]),
+            "'my file'\n'two?lines'\n"
+        );
+        let table = listing(&fixture, &["--fields", "name", "--no-header"]);
+        assert!(table.contains("│ two?lines "), "{}", table);
+
+        let json: Vec<serde_json::Value> =
+            serde_json::from_str(&listing(&fixture, &["--json", "--escape"])).unwrap();
+        assert_eq!(json[1]["name"], "two\nlines");
+    }
+
+    #[test]
+    fn errors_stay_out_of_stdout() {
+        let fixture = fixture();
+        let paths = [fixture.path().to_path_buf(), fixture.join("missing")];
+        let cli = cli(&["-qq", "-1", "--no-header-path"]);
+        let text = output(|out| print_listings(out, &paths, &cli, map_short_data).map(|_| ()));
+        // The separator is all that is left of the missing one
+        assert_eq!(text, "a.rs\nb.txt\n\n");
+        assert!(crate::failures::any());
+    }
+
+    // The OSC 8 sequences of --hyperlink, which `output` leaves alone
+    fn without_links(text: &str) -> String {
+        let mut plain = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("\x1b]8;;") {
+            plain.push_str(&rest[..start]);
+            let end = rest[start..].find("\x1b\\").expect("unterminated link");
+            rest = &rest[start + end + 2..];
+        }
+        plain + rest
+    }
+
+    #[test]
+    fn links_dont_change_the_layout() {
+        let fixture = fixture();
+        let plain = listing(&fixture, &[]);
+        let linked = listing(&fixture, &["--hyperlink=always"]);
+        assert!(linked.contains("\x1b]8;;file://"));
+        assert_eq!(without_links(&linked), plain);
+        assert_eq!(
+            without_links(&listing(&fixture, &["-1", "--hyperlink=always"])),
+            "a.rs\nb.txt\n"
+        );
+    }
+
+    #[test]
+    fn links_stay_out_of_data_formats() {
+        let fixture = fixture();
+        for format in ["--csv", "--json", "--yaml", "--json-lines"] {
+            let text = listing(&fixture, &[format, "--hyperlink=always"]);
+            assert!(!text.contains('\x1b'), "{}: {}", format, text);
+        }
+    }
+
+    #[test]
+    fn quiet_table_is_only_rows() {
+        assert_eq!(
+            listing(&fixture(), &["-q"]),
+            "\
+╭─────────────────┬────────┬────────────┬─────────────────╮
+│ a.rs            │ File   │ 13         │ Tue Nov 14 2023 │
+│ b.txt           │ File   │ 5          │ Tue Nov 14 2023 │
+╰─────────────────┴────────┴────────────┴─────────────────╯
+"
+        );
+    }
+
+    #[test]
+    fn quiet_long_table_has_no_total() {
+        let text = listing(&fixture(), &["-q", "-l", "--fields", "name,size"]);
+        assert_eq!(
+            text,
+            "\
+╭─────────────────┬────────────╮
+│ a.rs            │ 13         │
+│ b.txt           │ 5          │
+╰─────────────────┴────────────╯
+"
+        );
+    }
+
+    #[test]
+    fn quiet_limited_table_has_no_footer() {
+        let text = listing(&fixture(), &["--limit", "1", "--fields", "name"]);
+        assert!(
+            text.ends_with("…and 1 more\n0 directories, 2 files, 18 B total\n"),
+            "{}",
+            text
+        );
+        assert_eq!(
+            listing(&fixture(), &["-q", "--limit", "1", "--fields", "name"]),
+            "\
+╭─────────────────╮
+│ a.rs            │
+╰─────────────────╯
+"
+        );
+    }
+
+    #[test]
+    fn quiet_oneline_and_json_are_unchanged() {
+        let fixture = fixture();
+        assert_eq!(listing(&fixture, &["-q", "-1"]), "a.rs\nb.txt\n");
+        assert_eq!(
+            listing(
+                &fixture,
+                &["-qq", "--json", "--compact", "--fields", "name,size"]
+            ),
+            r#"[{"name":"a.rs","len_bytes":13},{"name":"b.txt","len_bytes":5}]"#.to_owned() + "\n"
+        );
+    }
+
+    #[test]
+    fn quiet_recursion_has_no_path_headers() {
+        let fixture = fixture();
+        fixture.file("sub/c.md", b"# c\n").dir("sub");
+        let mut cli = cli(&["-q", "-R", "-1"]);
+        apply_quiet(&mut cli);
+        let text = output(|out| {
+            print_listings(out, &[fixture.path().to_path_buf()], &cli, map_short_data).map(|_| ())
+        });
+        assert_eq!(text, "a.rs\nb.txt\nsub\n\nc.md\n");
+    }
+
+    // The v2 fields a consumer relies on, read back with their types
+    #[derive(Debug, serde::Deserialize)]
+    struct V2Entry {
+        name: String,
+        e_type: String,
+        len_bytes: u64,
+        permissions: String,
+        mode: u32,
+        #[cfg(unix)]
+        uid: u32,
+        #[cfg(unix)]
+        gid: u32,
+        modified: String,
+        modified_iso: String,
+        modified_epoch: i64,
+    }
+
+    #[test]
+    fn v2_json_reads_back_typed() {
+        let fixture = fixture();
+        let text = listing(&fixture, &["-l", "--json", "--json-schema", "v2"]);
+        let entries: Vec<V2Entry> = serde_json::from_str(&text).expect("v2 JSON doesn't parse");
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["a.rs", "b.txt"]);
+        for entry in &entries {
+            assert_eq!(entry.e_type, "file");
+            assert_eq!(entry.modified_epoch, 1_700_000_000);
+            assert_eq!(entry.modified_iso, "2023-11-14T22:13:20Z");
+            assert_eq!(entry.modified, entry.modified_iso);
+            assert_eq!(format!("{:o}", entry.mode & 0o777), entry.permissions);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+                let metadata = fs::metadata(fixture.join(&entry.name)).unwrap();
+                assert_eq!(entry.mode, metadata.mode() & 0o7777);
+                assert_eq!((entry.uid, entry.gid), (metadata.uid(), metadata.gid()));
+            }
+        }
+        assert_eq!(entries[0].len_bytes, 13);
+    }
+
+    #[test]
+    fn v1_json_keeps_its_shape() {
+        let text = listing(&fixture(), &["-l", "--json", "--compact"]);
+        let entries: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+        assert_eq!(entries[0]["e_type"], "File");
+        for v2_only in ["mode", "uid", "gid", "modified_iso", "modified_epoch"] {
+            assert!(entries[0].get(v2_only).is_none(), "{} is in v1", v2_only);
+        }
+    }
+
+    // Created out of order and churned between runs, so readdir order differs each time
+    #[test]
+    fn default_order_is_the_same_every_run() {
+        let fixture = Fixture::new();
+        for name in ["zeta.rs", "Beta.md", "alpha.txt", "Gamma", "delta"] {
+            fixture.file(name, b"");
+        }
+        fixture.dir("Charlie");
+        let table = ["--fields", "name,type", "--no-summary"];
+        let expected = "\
+╭─────────────────┬────────╮
+│ Name            │ Type   │
+├─────────────────┼────────┤
+│ alpha.txt       │ File   │
+│ Beta.md         │ File   │
+│ Charlie         │ Dir    │
+│ delta           │ File   │
+│ Gamma           │ File   │
+│ zeta.rs         │ File   │
+╰─────────────────┴────────╯
+";
+        let json = r#"["alpha.txt","Beta.md","Charlie","delta","Gamma","zeta.rs"]"#;
+        for run in 0..5 {
+            assert_eq!(listing(&fixture, &table), expected, "run {}", run);
+            let text = listing(&fixture, &["--json", "--fields", "name"]);
+            let entries: Vec<serde_json::Value> = serde_json::from_str(&text).unwrap();
+            let names: Vec<_> = entries.iter().map(|e| e["name"].clone()).collect();
+            assert_eq!(serde_json::to_string(&names).unwrap(), json, "run {}", run);
+            let long = listing(&fixture, &["-l", "-1"]);
+            assert_eq!(long, listing(&fixture, &["-1"]), "run {}", run);
+            // Recreated files move around in most directory layouts
+            let churned = ["zeta.rs", "Beta.md", "delta"][run % 3];
+            fs::remove_file(fixture.join(churned)).unwrap();
+            fixture.file(churned, b"");
+        }
+    }
+
+    fn most_recent_names(fixture: &Fixture, args: &[&str], count: usize) -> Option<Vec<String>> {
+        let cli = cli(&[&["--utc"], args].concat());
+        let listing = collect_entries(fixture.path(), &cli, map_short_data);
+        most_recent(&listing.entries, count)
+            .map(|recent| recent.iter().map(|e| e.name().to_string()).collect())
+    }
+
+    fn aged_fixture() -> Fixture {
+        let fixture = Fixture::new();
+        let at = |secs| crate::fixture::mtime() + std::time::Duration::from_secs(secs);
+        fixture
+            .file("old.txt", b"")
+            .file("new.log", b"")
+            .file("tie-b", b"")
+            .file("tie-a", b"")
+            .file(".hidden", b"")
+            .touch("new.log", at(300))
+            .touch("tie-b", at(200))
+            .touch("tie-a", at(200))
+            .touch(".hidden", at(400));
+        fixture
+    }
+
+    #[test]
+    fn recent_entries_are_newest_first_ties_by_name() {
+        let fixture = aged_fixture();
+        assert_eq!(
+            most_recent_names(&fixture, &[], 3).unwrap(),
+            ["new.log", "tie-a", "tie-b"]
+        );
+        assert_eq!(most_recent_names(&fixture, &[], 1).unwrap(), ["new.log"]);
+    }
+
+    #[test]
+    fn recent_entries_follow_filters_and_all() {
+        let fixture = aged_fixture();
+        assert_eq!(
+            most_recent_names(&fixture, &["-a"], 2).unwrap(),
+            [".hidden", "new.log"]
+        );
+        // `.` and `..` are listed with -a but aren't recent entries
+        assert_eq!(most_recent_names(&fixture, &["-a"], 5).unwrap().len(), 5);
+        assert_eq!(most_recent_names(&fixture, &["-a"], 6), None);
+        assert_eq!(
+            most_recent_names(&fixture, &["--match", "tie-*"], 2).unwrap(),
+            ["tie-a", "tie-b"]
+        );
+    }
+
+    #[test]
+    fn too_few_entries_have_no_recent_section() {
+        let fixture = aged_fixture();
+        assert_eq!(most_recent_names(&fixture, &[], 4).unwrap().len(), 4);
+        assert_eq!(most_recent_names(&fixture, &[], 5), None);
+    }
+
+    // Names a program could take for options, or that only survive as bytes: a name
+    // spelling out `\xff` and one with the byte itself
+    #[cfg(unix)]
+    fn awkward_names() -> (Fixture, Vec<Vec<u8>>) {
+        use std::os::unix::ffi::OsStrExt;
+        let names: Vec<Vec<u8>> = [
+            &b"-l"[..],
+            b"--json",
+            b"-",
+            b"it's here",
+            b"line\nbreak",
+            b"bad\\xff",
+            b"bad\xff",
+        ]
+        .iter()
+        .map(|name| name.to_vec())
+        .collect();
+        let fixture = Fixture::new();
+        for name in &names {
+            fs::write(fixture.path().join(std::ffi::OsStr::from_bytes(name)), b"").unwrap();
+        }
+        (fixture, names)
+    }
+
+    #[cfg(unix)]
+    fn names_only(fixture: &Fixture, args: &[&str]) -> Vec<u8> {
+        let cli = cli(args);
+        crate::fixture::output_bytes(|out| {
+            print_listing(out, fixture.path(), &cli, map_short_data).map(|_| ())
+        })
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn print0_writes_the_exact_bytes() {
+        let (fixture, mut names) = awkward_names();
+        let text = names_only(&fixture, &["--print0"]);
+        let mut printed: Vec<Vec<u8>> = text
+            .strip_suffix(b"\0")
+            .unwrap()
+            .split(|&b| b == 0)
+            .map(|name| name.to_vec())
+            .collect();
+        printed.sort();
+        names.sort();
+        assert_eq!(printed, names);
+
+        let prefix = format!("{}/", fixture.path().display());
+        let text = names_only(&fixture, &["--print0", "--full-path"]);
+        assert!(
+            text.split(|&b| b == 0)
+                .any(|path| path == [prefix.as_bytes(), b"bad\xff"].concat())
+        );
+    }
+
+    // The output goes to bash as it is, an array takes the names whatever lines they span
+    #[cfg(unix)]
+    #[test]
+    fn shell_quoted_names_evaluate_back() {
+        use std::os::unix::ffi::OsStrExt;
+        let (fixture, mut names) = awkward_names();
+        let text = names_only(&fixture, &["--shell-quote"]);
+        let lines: Vec<&[u8]> = text
+            .strip_suffix(b"\n")
+            .unwrap()
+            .split(|&b| b == b'\n')
+            .collect();
+        // The quoted newline keeps its line, so one name takes up two
+        assert_eq!(lines.len(), names.len() + 1);
+        let script = [
+            &b"names=(\n"[..],
+            &text,
+            b"); printf '%s\\0' \"${names[@]}\"",
+        ]
+        .concat();
+        let evaluated = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(std::ffi::OsStr::from_bytes(&script))
+            .output()
+            .expect("cannot run bash");
+        assert!(evaluated.status.success());
+        let mut printed: Vec<Vec<u8>> = evaluated
+            .stdout
+            .strip_suffix(b"\0")
+            .unwrap()
+            .split(|&b| b == 0)
+            .map(|name| name.to_vec())
+            .collect();
+        printed.sort();
+        names.sort();
+        assert_eq!(printed, names);
+    }
+
+    #[test]
+    fn names_after_a_double_dash_are_paths() {
+        let cli = cli(&["--print0", "--", "-l", "--json", "-"]);
+        assert!(cli.print0 && !cli.long && !cli.json);
+        assert_eq!(
+            cli.paths,
+            ["-l", "--json", "-"].map(std::path::PathBuf::from)
+        );
+    }
+
+    // `text` without the whitespace between JSON tokens, strings kept as they are
+    #[cfg(unix)]
+    fn squeezed(text: &str) -> String {
+        let mut kept = String::new();
+        let (mut in_string, mut escaped) = (false, false);
+        for c in text.chars() {
+            if in_string {
+                in_string = escaped || c != '"';
+                escaped = !escaped && c == '\\';
+            } else if c.is_whitespace() {
+                continue;
+            } else {
+                in_string = c == '"';
+            }
+            kept.push(c);
+        }
+        kept
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn compact_json_is_pretty_json_without_whitespace() {
+        let fixture = fixture();
+        fixture
+            .file("say \"hi\".txt", b"")
+            .file("back\\slash", b"")
+            .file("line\nbreak", b"")
+            .file("tab\there", b"")
+            .file("caf\u{e9} \u{1f600}", b"");
+        for args in [
+            &["--json"][..],
+            &["-l", "--json"],
+            &["-l", "--json", "--json-schema", "v2"],
+        ] {
+            let pretty = listing(&fixture, args);
+            let compact = listing(&fixture, &[args, &["--compact"]].concat());
+            assert_eq!(compact.lines().count(), 1, "{:?}", args);
+            assert_eq!(squeezed(&pretty), squeezed(&compact), "{:?}", args);
+            let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+            let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+            assert_eq!(pretty, compact);
+            let names: Vec<_> = compact
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|e| e["name"].as_str().unwrap())
+                .collect();
+            assert!(names.contains(&"say \"hi\".txt"));
+            assert!(names.contains(&"back\\slash"));
+            assert!(names.contains(&"line\nbreak"));
+            assert!(names.contains(&"tab\there"));
+            assert!(names.contains(&"caf\u{e9} \u{1f600}"));
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use crate::cli::Cli;
+use crate::{color, failures};
+use owo_colors::OwoColorize;
+use std::io;
+use std::path::Path;
+
+/// Reports an error that leaves nothing to list and exits with status 2.
+pub fn fail(cli: &Cli, message: String) -> ! {
+    report_error(cli, message);
+    std::process::exit(2);
+}
+
+/// Prints a non-fatal note or warning to stderr unless -q was given.
+pub fn warn(cli: &Cli, message: impl std::fmt::Display) {
+    if cli.quiet == 0 {
+        eprintln!("{}", color::for_stderr(message));
+    }
+}
+
+/// Prints an error to stderr unless -qq was given, the exit status is up to the caller.
+pub fn report_error(cli: &Cli, message: String) {
+    if cli.quiet < 2 {
+        eprintln!("{}", color::for_stderr(message.red()));
+    }
+}
+
+pub fn unreadable(cli: &Cli, path: &Path, e: &io::Error) {
+    failures::record();
+    report_error(cli, format!("cannot read {}: {}", path.display(), e));
+}
@@ -0,0 +1,166 @@
+const KILO: f64 = 1000.0;
+const KIBI: f64 = 1024.0;
+
+/// Parses sizes like `512`, `100K`, `1.5M`, `2Gi` or `10MiB`. Plain suffixes are
+/// decimal (K = 1000), the `i` suffixes binary (Ki = 1024), a trailing `B` is optional.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let digits = input
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(digits);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}', expected e.g. 100M or 4Ki", input))?;
+
+    let unit = unit.trim_start();
+    let unit = unit.strip_suffix('B').unwrap_or(unit);
+    let (prefix, base) = match unit.strip_suffix('i') {
+        Some(prefix) => (prefix, KIBI),
+        None => (unit, KILO),
+    };
+    let exponent = match prefix.to_ascii_uppercase().as_str() {
+        "" if base == KILO => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => {
+            return Err(format!(
+                "unknown size unit '{}' (use K, M, G, T, Ki, Mi, Gi, Ti)",
+                unit
+            ));
+        }
+    };
+
+    let bytes = value * base.powi(exponent);
+    if bytes > u64::MAX as f64 {
+        return Err(format!("size '{}' is too large", input));
+    }
+    Ok(bytes.round() as u64)
+}
+
+/// How sizes are written for people to read. JSON, YAML and CSV always carry the plain
+/// byte count whatever the style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeStyle {
+    /// `1234567`
+    Plain,
+    /// `1,234,567`, under --bytes
+    Grouped,
+    /// `1.2 MiB`, under -H
+    Binary,
+    /// `1.2 MB`, under --si
+    Si,
+}
+
+impl SizeStyle {
+    /// The style of totals and other sizes outside of the size column, which are never
+    /// left as plain numbers.
+    pub fn for_totals(self) -> SizeStyle {
+        match self {
+            SizeStyle::Plain => SizeStyle::Binary,
+            other => other,
+        }
+    }
+}
+
+/// Writes `bytes` in `style`. Binary units are powers of 1024 (KiB, MiB, ...), SI units
+/// powers of 1000 (KB, MB, ...), both with one decimal.
+pub fn format_size(bytes: u64, style: SizeStyle) -> String {
+    match style {
+        SizeStyle::Plain => bytes.to_string(),
+        SizeStyle::Grouped => grouped(bytes),
+        SizeStyle::Binary => scaled(bytes, KIBI, &["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"]),
+        SizeStyle::Si => scaled(bytes, KILO, &["KB", "MB", "GB", "TB", "PB", "EB"]),
+    }
+}
+
+fn scaled(bytes: u64, base: f64, units: &[&str]) -> String {
+    if (bytes as f64) < base {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / base;
+    let mut unit = 0;
+    while size >= base && unit < units.len() - 1 {
+        size /= base;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, units[unit])
+}
+
+// Thousands separated by commas, e.g. `1,234,567`
+fn grouped(bytes: u64) -> String {
+    let digits = bytes.to_string();
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_byte_counts() {
+        assert_eq!(parse_size("0"), Ok(0));
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size(" 512B "), Ok(512));
+    }
+
+    #[test]
+    fn decimal_and_binary_units() {
+        assert_eq!(parse_size("100K"), Ok(100_000));
+        assert_eq!(parse_size("100k"), Ok(100_000));
+        assert_eq!(parse_size("1.5M"), Ok(1_500_000));
+        assert_eq!(parse_size("2G"), Ok(2_000_000_000));
+        assert_eq!(parse_size("4Ki"), Ok(4096));
+        assert_eq!(parse_size("10MiB"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1 Gi"), Ok(1 << 30));
+        assert_eq!(parse_size("1Ti"), Ok(1 << 40));
+        assert_eq!(parse_size("0.5Ki"), Ok(512));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        assert!(parse_size("").unwrap_err().starts_with("invalid size"));
+        assert!(parse_size("M").unwrap_err().starts_with("invalid size"));
+        assert!(parse_size("1.2.3K").is_err());
+        assert!(parse_size("-5").is_err());
+        assert!(
+            parse_size("10X")
+                .unwrap_err()
+                .contains("unknown size unit 'X'")
+        );
+        // A bare `i` would be a binary byte
+        assert!(parse_size("10i").is_err());
+        assert!(parse_size("99999999P").unwrap_err().contains("too large"));
+    }
+
+    #[test]
+    fn invalid_sizes_are_usage_errors() {
+        use clap::Parser;
+        let error = crate::Cli::try_parse_from(["best-ls", "--min-size", "10X"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn sizes_are_formatted() {
+        assert_eq!(format_size(1_234_567, SizeStyle::Plain), "1234567");
+        assert_eq!(format_size(1_234_567, SizeStyle::Grouped), "1,234,567");
+        assert_eq!(format_size(123, SizeStyle::Grouped), "123");
+        assert_eq!(format_size(1023, SizeStyle::Binary), "1023 B");
+        assert_eq!(format_size(1536, SizeStyle::Binary), "1.5 KiB");
+        assert_eq!(format_size(1_500_000, SizeStyle::Si), "1.5 MB");
+        assert_eq!(format_size(u64::MAX, SizeStyle::Binary), "16.0 EiB");
+        assert_eq!(SizeStyle::Plain.for_totals(), SizeStyle::Binary);
+    }
+}
@@ -0,0 +1,386 @@
+use crate::{RawEntry, time_style::TimeKind};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::cmp::Ordering;
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Keep the order the filesystem returned
+    None,
+    /// Alphabetically by name, ignoring case unless --sort-case says otherwise
+    Name,
+    /// Largest first
+    Size,
+    /// Newest first, by the timestamp picked with --time
+    Time,
+    /// Most recently read first, by atime whatever --time picks
+    #[value(name = "atime")]
+    #[serde(rename = "atime")]
+    Accessed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortCase {
+    /// Uppercase names before all lowercase ones, by their bytes
+    Sensitive,
+    /// `apple`, `Ärger`, `main.rs`, `README.md`, ignoring case
+    Insensitive,
+}
+
+/// Orders names by `case`. Insensitive ordering also looks past accents first, so `Ärger`
+/// sorts among the names starting with `a`. Names equal but for their case are ordered by
+/// their bytes, so no two names are ever considered equal.
+pub fn compare_names(a: &RawEntry, b: &RawEntry, case: SortCase) -> Ordering {
+    let by_bytes = || a.name_bytes().cmp(b.name_bytes());
+    match case {
+        SortCase::Sensitive => by_bytes(),
+        SortCase::Insensitive => base_letters(&a.name)
+            .cmp(&base_letters(&b.name))
+            .then_with(|| folded(&a.name).cmp(&folded(&b.name)))
+            .then_with(by_bytes),
+    }
+}
+
+fn folded(name: &str) -> Vec<char> {
+    name.chars().flat_map(char::to_lowercase).collect()
+}
+
+// The lowercase letters without their accents, which decompose into combining marks
+fn base_letters(name: &str) -> Vec<char> {
+    name.nfd()
+        .filter(|&c| !is_combining_mark(c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The order of trees: directories first, then files, both by name.
+pub fn dirs_first(a: &RawEntry, b: &RawEntry, case: SortCase) -> Ordering {
+    b.is_dir
+        .cmp(&a.is_dir)
+        .then_with(|| compare_names(a, b, case))
+}
+
+/// Orders entries by `key`. Entries the key considers equal are ordered by name, so the
+/// result never depends on the order the filesystem returned them in.
+pub fn sort_entries(entries: &mut [RawEntry], key: SortKey, time: TimeKind, case: SortCase) {
+    let by_name = |a: &RawEntry, b: &RawEntry| compare_names(a, b, case);
+    let time = match key {
+        SortKey::Accessed => TimeKind::Accessed,
+        _ => time,
+    };
+    match key {
+        SortKey::None => {}
+        SortKey::Name => entries.sort_by(by_name),
+        SortKey::Size => entries.sort_by(|a, b| {
+            b.ranked_len()
+                .cmp(&a.ranked_len())
+                .then_with(|| by_name(a, b))
+        }),
+        SortKey::Time | SortKey::Accessed => {
+            entries.sort_by(|a, b| b.time(time).cmp(&a.time(time)).then_with(|| by_name(a, b)))
+        }
+    }
+    // `.` and `..` lead whatever the order
+    entries.sort_by_key(|raw| match raw.name.as_str() {
+        "." => 0,
+        ".." => 1,
+        _ => 2,
+    });
+}
+
+/// Turns sorted `entries` around for --reverse, leaving `.` and `..` in front.
+pub fn reverse_entries(entries: &mut [RawEntry]) {
+    let dots = entries.iter().take_while(|raw| raw.is_dot_entry()).count();
+    entries[dots..].reverse();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::EntryType;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn entry(name: &str, e_type: EntryType, len: u64, modified: u64) -> RawEntry {
+        let mut raw = RawEntry::member(name.to_string(), e_type, len, 0o644);
+        raw.modified = Some(UNIX_EPOCH + Duration::from_secs(modified));
+        raw
+    }
+
+    // Plenty of ties on size and time, as a build output has
+    fn entries() -> Vec<RawEntry> {
+        vec![
+            entry("target", EntryType::Dir, 4096, 100),
+            entry("src", EntryType::Dir, 4096, 100),
+            entry(".git", EntryType::Dir, 4096, 200),
+            entry("Cargo.toml", EntryType::File, 512, 100),
+            entry("cargo.lock", EntryType::File, 512, 100),
+            entry("README.md", EntryType::File, 512, 300),
+            entry("readme.md", EntryType::File, 512, 300),
+            entry("build.rs", EntryType::File, 0, 200),
+            entry("LICENSE", EntryType::File, 0, 200),
+            entry("..", EntryType::Dir, 4096, 50),
+            entry(".", EntryType::Dir, 4096, 400),
+        ]
+    }
+
+    fn names(entries: &[RawEntry]) -> Vec<&str> {
+        entries.iter().map(|raw| raw.name.as_str()).collect()
+    }
+
+    // Deterministic orders that are unlike each other and unlike the original
+    fn shuffled(entries: &[RawEntry], seed: u64) -> Vec<RawEntry> {
+        let mut state = seed;
+        let mut shuffled = entries.to_vec();
+        for i in (1..shuffled.len()).rev() {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            shuffled.swap(i, (state >> 33) as usize % (i + 1));
+        }
+        shuffled
+    }
+
+    #[test]
+    fn input_order_never_shows_through() {
+        for key in [
+            SortKey::Name,
+            SortKey::Size,
+            SortKey::Time,
+            SortKey::Accessed,
+        ] {
+            for case in [SortCase::Sensitive, SortCase::Insensitive] {
+                let mut expected = entries();
+                sort_entries(&mut expected, key, TimeKind::Modified, case);
+                for seed in 0..50 {
+                    let mut sorted = shuffled(&entries(), seed);
+                    sort_entries(&mut sorted, key, TimeKind::Modified, case);
+                    assert_eq!(names(&sorted), names(&expected), "{:?} {:?}", key, case);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ties_are_broken_by_name() {
+        let mut sorted = entries();
+        sort_entries(
+            &mut sorted,
+            SortKey::Size,
+            TimeKind::Modified,
+            SortCase::Insensitive,
+        );
+        assert_eq!(
+            names(&sorted),
+            [
+                ".",
+                "..",
+                ".git",
+                "src",
+                "target",
+                "cargo.lock",
+                "Cargo.toml",
+                "README.md",
+                "readme.md",
+                "build.rs",
+                "LICENSE"
+            ]
+        );
+
+        sort_entries(
+            &mut sorted,
+            SortKey::Time,
+            TimeKind::Modified,
+            SortCase::Sensitive,
+        );
+        assert_eq!(
+            names(&sorted),
+            [
+                ".",
+                "..",
+                "README.md",
+                "readme.md",
+                ".git",
+                "LICENSE",
+                "build.rs",
+                "Cargo.toml",
+                "cargo.lock",
+                "src",
+                "target"
+            ]
+        );
+    }
+
+    #[test]
+    fn atime_ignores_the_time_picked() {
+        let read = |name: &str, modified: u64, accessed: u64| {
+            let mut raw = entry(name, EntryType::File, 0, modified);
+            raw.accessed = Some(UNIX_EPOCH + Duration::from_secs(accessed));
+            raw
+        };
+        let mut sorted = vec![
+            read("old", 100, 300),
+            read("new", 300, 100),
+            read("mid", 200, 200),
+        ];
+        sort_entries(
+            &mut sorted,
+            SortKey::Accessed,
+            TimeKind::Modified,
+            SortCase::Insensitive,
+        );
+        assert_eq!(names(&sorted), ["old", "mid", "new"]);
+        sort_entries(
+            &mut sorted,
+            SortKey::Time,
+            TimeKind::Modified,
+            SortCase::Insensitive,
+        );
+        assert_eq!(names(&sorted), ["new", "mid", "old"]);
+    }
+
+    #[test]
+    fn none_keeps_the_order_but_leads_with_dots() {
+        let mut sorted = entries();
+        sort_entries(
+            &mut sorted,
+            SortKey::None,
+            TimeKind::Modified,
+            SortCase::Insensitive,
+        );
+        assert_eq!(names(&sorted)[..4], [".", "..", "target", "src"]);
+    }
+
+    #[test]
+    fn reversing_keeps_the_dots_in_front() {
+        let mut sorted = entries();
+        sort_entries(
+            &mut sorted,
+            SortKey::Size,
+            TimeKind::Modified,
+            SortCase::Insensitive,
+        );
+        let mut reversed = sorted.clone();
+        reverse_entries(&mut reversed);
+        assert_eq!(names(&reversed)[..3], [".", "..", "LICENSE"]);
+        let mut back = names(&sorted)[2..].to_vec();
+        back.reverse();
+        assert_eq!(names(&reversed)[2..], back);
+
+        let mut no_dots = vec![
+            entry("a", EntryType::File, 0, 0),
+            entry("b", EntryType::File, 0, 0),
+        ];
+        reverse_entries(&mut no_dots);
+        assert_eq!(names(&no_dots), ["b", "a"]);
+    }
+
+    #[test]
+    fn names_differing_in_case_are_never_equal() {
+        let upper = entry("README.md", EntryType::File, 0, 0);
+        let lower = entry("readme.md", EntryType::File, 0, 0);
+        assert_eq!(
+            compare_names(&upper, &lower, SortCase::Insensitive),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_names(&lower, &upper, SortCase::Insensitive),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_names(&upper, &upper, SortCase::Insensitive),
+            Ordering::Equal
+        );
+    }
+
+    fn by_name(list: &[&str], case: SortCase) -> Vec<String> {
+        let mut entries: Vec<_> = list
+            .iter()
+            .map(|name| entry(name, EntryType::File, 0, 0))
+            .collect();
+        sort_entries(&mut entries, SortKey::Name, TimeKind::Modified, case);
+        entries.into_iter().map(|raw| raw.name).collect()
+    }
+
+    #[test]
+    fn insensitive_looks_past_case_and_accents() {
+        let list = [
+            "README.md",
+            "Ärger",
+            "main.rs",
+            "apple",
+            "Zebra",
+            "ärger",
+            "éclair",
+            "Eagle",
+        ];
+        assert_eq!(
+            by_name(&list, SortCase::Insensitive),
+            [
+                "apple",
+                "Ärger",
+                "ärger",
+                "Eagle",
+                "éclair",
+                "main.rs",
+                "README.md",
+                "Zebra"
+            ]
+        );
+    }
+
+    #[test]
+    fn sensitive_goes_by_bytes() {
+        let list = ["README.md", "Ärger", "main.rs", "apple", "Zebra"];
+        assert_eq!(
+            by_name(&list, SortCase::Sensitive),
+            ["README.md", "Zebra", "apple", "main.rs", "Ärger"]
+        );
+    }
+
+    #[test]
+    fn case_folding_is_unicode() {
+        // Not just ASCII: Greek and Cyrillic capitals sort with their lowercase forms
+        let list = ["Ωmega", "αlpha", "Δelta", "Жук", "жаба"];
+        assert_eq!(
+            by_name(&list, SortCase::Insensitive),
+            ["αlpha", "Δelta", "Ωmega", "жаба", "Жук"]
+        );
+    }
+
+    #[test]
+    fn accents_only_decide_between_equal_letters() {
+        let list = ["resume", "résumé", "Resume", "resumes"];
+        assert_eq!(
+            by_name(&list, SortCase::Insensitive),
+            ["Resume", "resume", "résumé", "resumes"]
+        );
+    }
+
+    #[test]
+    fn trees_put_directories_first_in_either_case() {
+        let dir = entry("zoo", EntryType::Dir, 0, 0);
+        let upper = entry("Apple", EntryType::File, 0, 0);
+        let lower = entry("apple", EntryType::File, 0, 0);
+        for case in [SortCase::Sensitive, SortCase::Insensitive] {
+            assert_eq!(dirs_first(&dir, &upper, case), Ordering::Less);
+            assert_eq!(dirs_first(&upper, &lower, case), Ordering::Less);
+        }
+    }
+
+    // Large directories are read in parallel, which must not change the order either
+    #[test]
+    fn parallel_reads_sort_the_same() {
+        let fixture = crate::fixture::Fixture::new();
+        for i in 0..1500 {
+            fixture.file(&format!("f{}", (i * 7919) % 1500), &vec![b'x'; i % 3]);
+        }
+        let cli = crate::fixture::cli(&["--sort", "size"]);
+        let listing = crate::collect::collect_entries(fixture.path(), &cli, |raw, _| raw);
+        let mut expected = listing.entries.clone();
+        expected.sort_by(|a, b| b.len.cmp(&a.len).then_with(|| a.name.cmp(&b.name)));
+        assert_eq!(listing.entries.len(), 1500);
+        assert_eq!(names(&listing.entries), names(&expected));
+    }
+}
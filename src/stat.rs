@@ -0,0 +1,89 @@
+use crate::cli::Cli;
+use crate::entry::{EntryType, RawEntry, UNIX_METADATA, device_numbers};
+use crate::{owners, permissions, time_style, xattrs};
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+use std::{fs, io};
+
+/// Everything --stat knows about one path. Symlinks are described the way listings show
+/// them, with the path they point to as `target`.
+#[derive(Debug, Serialize)]
+pub struct StatEntry {
+    /// Absolute, but with symlinks in it left as they are
+    pub path: String,
+    pub e_type: String,
+    #[serde(skip)]
+    pub entry_type: EntryType,
+    pub len_bytes: u64,
+    pub allocated_bytes: u64,
+    /// Like `-rw-r--r--`
+    pub permissions: String,
+    /// The permission bits in octal, like `0644`
+    pub mode: String,
+    pub owner: Option<String>,
+    pub group: Option<String>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub nlink: Option<u64>,
+    pub ino: Option<u64>,
+    pub dev: Option<u64>,
+    /// `major, minor` of device files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_numbers: Option<String>,
+    pub modified: Option<String>,
+    pub accessed: Option<String>,
+    pub changed: Option<String>,
+    pub created: Option<String>,
+    #[serde(skip)]
+    pub times: [Option<SystemTime>; 4],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Names only, never the values
+    pub xattrs: Option<Vec<String>>,
+}
+
+/// Reads what --stat shows about `path`, a symlink is only followed under --dereference.
+pub fn of(path: &Path, cli: &Cli) -> io::Result<StatEntry> {
+    let name = path.to_string_lossy().to_string();
+    let mut raw = RawEntry::from_metadata(path, name, fs::symlink_metadata(path)?);
+    if cli.dereference {
+        raw.dereference(path);
+    }
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let times = [raw.modified, raw.accessed, raw.changed, raw.created];
+    let [modified, accessed, changed, created] =
+        times.map(|time| time.map(|t| time_style::rfc3339(t, cli.utc)));
+    Ok(StatEntry {
+        path: absolute.to_string_lossy().to_string(),
+        e_type: raw.e_type.json_name(cli.json_schema),
+        entry_type: raw.e_type,
+        len_bytes: raw.len,
+        allocated_bytes: raw.allocated,
+        permissions: permissions::symbolic(raw.e_type, raw.mode),
+        mode: format!("{:04o}", raw.mode & 0o7777),
+        owner: unix(owners::owner_name(raw.uid, cli.numeric_ids)),
+        group: unix(owners::group_name(raw.gid, cli.numeric_ids)),
+        uid: unix(raw.uid),
+        gid: unix(raw.gid),
+        nlink: unix(raw.nlink),
+        ino: unix(raw.ino),
+        dev: unix(raw.dev),
+        device_numbers: raw.e_type.is_device().then(|| device_numbers(raw.rdev)),
+        modified,
+        accessed,
+        changed,
+        created,
+        times,
+        target: (raw.e_type == EntryType::Symlink)
+            .then(|| fs::read_link(path).ok())
+            .flatten()
+            .map(|target| target.to_string_lossy().to_string()),
+        xattrs: xattrs::names(path),
+    })
+}
+
+// Owners, link counts, device and inode numbers are null outside of Unix
+fn unix<T>(value: T) -> Option<T> {
+    UNIX_METADATA.then_some(value)
+}
@@ -0,0 +1,37 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use tabled::{Table, settings::Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// Box drawing characters with rounded corners
+    Rounded,
+    /// Plain ASCII borders that survive any terminal or paste target
+    Ascii,
+    /// Box drawing characters with square corners
+    Modern,
+    /// No borders, just aligned columns like classic `ls -l`
+    Blank,
+    /// Pipe table as used by Markdown
+    Markdown,
+    /// The look of PostgreSQL's psql client
+    Psql,
+}
+
+/// Draws the borders of `table`. Without a header the line separating it from the rows is dropped too.
+pub fn apply(table: &mut Table, style: TableStyle, header: bool) {
+    match (style, header) {
+        (TableStyle::Rounded, true) => table.with(Style::rounded()),
+        (TableStyle::Rounded, false) => table.with(Style::rounded().remove_horizontals()),
+        (TableStyle::Ascii, true) => table.with(Style::ascii()),
+        (TableStyle::Ascii, false) => table.with(Style::ascii().remove_horizontals()),
+        (TableStyle::Modern, true) => table.with(Style::modern()),
+        (TableStyle::Modern, false) => table.with(Style::modern().remove_horizontals()),
+        (TableStyle::Blank, _) => table.with(Style::blank()),
+        (TableStyle::Markdown, true) => table.with(Style::markdown()),
+        (TableStyle::Markdown, false) => table.with(Style::markdown().remove_horizontals()),
+        (TableStyle::Psql, true) => table.with(Style::psql()),
+        (TableStyle::Psql, false) => table.with(Style::psql().remove_horizontals()),
+    };
+}
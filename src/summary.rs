@@ -0,0 +1,102 @@
+use crate::entry::{EntryType, RawEntry};
+use crate::time_style;
+use serde::Serialize;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Totals of the entries a listing showed, after hidden files and filters were left out.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct Summary {
+    pub directories: u64,
+    pub files: u64,
+    pub total_bytes: u64,
+    /// Directories a tree collapsed, their contents aren't in the totals
+    #[serde(skip)]
+    pub skipped: u64,
+    /// Directories that couldn't be read, counted as directories but empty of contents
+    #[serde(skip)]
+    pub unreadable: u64,
+}
+
+impl Summary {
+    pub fn add(&mut self, is_dir: bool, len_bytes: u64) {
+        if is_dir {
+            self.directories += 1;
+        } else {
+            self.files += 1;
+        }
+        self.total_bytes += len_bytes;
+    }
+}
+
+/// `3 directories, 17 files`, with singulars where the count is one.
+pub fn counts(summary: &Summary) -> String {
+    format!(
+        "{} {}, {} {}",
+        summary.directories,
+        if summary.directories == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+        summary.files,
+        if summary.files == 1 { "file" } else { "files" },
+    )
+}
+
+/// The aggregate printed by `--summary-only`.
+#[derive(Debug, Default, Serialize)]
+pub struct Overview {
+    pub directories: u64,
+    pub files: u64,
+    pub symlinks: u64,
+    pub total_bytes: u64,
+    pub largest: Option<Extreme>,
+    pub newest: Option<Extreme>,
+}
+
+/// The entry that stands out in an `Overview`, with its path from the listed directory.
+#[derive(Debug, Serialize)]
+pub struct Extreme {
+    pub path: String,
+    pub len_bytes: u64,
+    #[serde(skip)]
+    pub modified_at: Option<SystemTime>,
+    pub modified: Option<String>,
+}
+
+impl Overview {
+    /// Counts `raw`, found at `path`. Symlinks never compete for the largest entry,
+    /// directories only when `du` gave them the size of their contents.
+    pub fn add(&mut self, path: &Path, raw: &RawEntry, du: bool, utc: bool) {
+        match raw.e_type {
+            EntryType::Dir => self.directories += 1,
+            EntryType::Symlink => self.symlinks += 1,
+            _ => self.files += 1,
+        }
+        self.total_bytes += raw.len;
+
+        let extreme = || Extreme {
+            path: path.to_string_lossy().to_string(),
+            len_bytes: raw.len,
+            modified_at: raw.modified,
+            modified: raw.modified.map(|t| time_style::rfc3339(t, utc)),
+        };
+        let sized = match raw.e_type {
+            EntryType::Dir => du,
+            EntryType::Symlink => false,
+            _ => true,
+        };
+        if sized && self.largest.as_ref().is_none_or(|l| raw.len > l.len_bytes) {
+            self.largest = Some(extreme());
+        }
+        if raw.modified.is_some()
+            && self
+                .newest
+                .as_ref()
+                .is_none_or(|n| raw.modified > n.modified_at)
+        {
+            self.newest = Some(extreme());
+        }
+    }
+}
@@ -0,0 +1,24 @@
+/// Lines of the terminal on stdout, None when stdout isn't one.
+pub fn rows() -> Option<usize> {
+    window_size().map(|(rows, _)| rows).filter(|&rows| rows > 0)
+}
+
+/// Columns of the terminal on stdout, None when stdout isn't one.
+pub fn columns() -> Option<usize> {
+    window_size()
+        .map(|(_, columns)| columns)
+        .filter(|&columns| columns > 0)
+}
+
+#[cfg(unix)]
+fn window_size() -> Option<(usize, usize)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+    ok.then_some((size.ws_row as usize, size.ws_col as usize))
+}
+
+// Unknown, output is never fitted to the terminal
+#[cfg(not(unix))]
+fn window_size() -> Option<(usize, usize)> {
+    None
+}
@@ -0,0 +1,200 @@
+use chrono::{
+    DateTime, Local, SecondsFormat, Utc,
+    format::{Item, StrftimeItems},
+};
+use clap::ValueEnum;
+use std::time::SystemTime;
+
+// What tables showed before --time-style existed
+const DEFAULT_FORMAT: &str = "%a %b %e %Y";
+
+/// Which timestamp `--time` shows in the time column, `--sort time` orders by and the age
+/// filters compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimeKind {
+    /// Last change of the contents (mtime)
+    Modified,
+    /// Last read (atime)
+    Accessed,
+    /// Last change of the contents or metadata (ctime)
+    Changed,
+    /// Creation, where the filesystem records it (birth time)
+    Created,
+}
+
+impl TimeKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeKind::Modified => "Modified",
+            TimeKind::Accessed => "Accessed",
+            TimeKind::Changed => "Changed",
+            TimeKind::Created => "Created",
+        }
+    }
+}
+
+/// How `--time-style` renders timestamps in tables and CSV, following GNU ls where it can.
+#[derive(Debug, Clone)]
+pub enum TimeStyle {
+    /// `2024-05-01 13:37`
+    Iso,
+    /// `2024-05-01 13:37:42`
+    LongIso,
+    /// RFC 3339 with seconds and offset, `2024-05-01T13:37:42+02:00`
+    FullIso,
+    /// A strftime format given as `+FORMAT`
+    Custom(String),
+}
+
+impl TimeStyle {
+    fn pattern(&self) -> &str {
+        match self {
+            TimeStyle::Iso => "%Y-%m-%d %H:%M",
+            TimeStyle::LongIso => "%Y-%m-%d %H:%M:%S",
+            TimeStyle::FullIso => "%Y-%m-%dT%H:%M:%S%:z",
+            TimeStyle::Custom(format) => format,
+        }
+    }
+}
+
+/// Parses `iso`, `long-iso`, `full-iso` or `+FORMAT`, rejecting formats chrono can't render.
+pub fn parse_time_style(input: &str) -> Result<TimeStyle, String> {
+    match input {
+        "iso" => Ok(TimeStyle::Iso),
+        "long-iso" => Ok(TimeStyle::LongIso),
+        "full-iso" => Ok(TimeStyle::FullIso),
+        _ => {
+            let Some(format) = input.strip_prefix('+') else {
+                return Err(format!(
+                    "unknown time style '{}', expected iso, long-iso, full-iso or +FORMAT",
+                    input
+                ));
+            };
+            if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                return Err(format!("invalid time format '{}'", format));
+            }
+            Ok(TimeStyle::Custom(format.to_string()))
+        }
+    }
+}
+
+/// Renders `time` with the given style, or the default date only format without one.
+/// Local time unless `utc` is set.
+pub fn format_time(time: SystemTime, style: Option<&TimeStyle>, utc: bool) -> String {
+    let pattern = style.map(TimeStyle::pattern).unwrap_or(DEFAULT_FORMAT);
+    if utc {
+        DateTime::<Utc>::from(time).format(pattern).to_string()
+    } else {
+        DateTime::<Local>::from(time).format(pattern).to_string()
+    }
+}
+
+/// Whole seconds since the Unix epoch, negative before it.
+pub fn epoch_seconds(time: SystemTime) -> i64 {
+    DateTime::<Utc>::from(time).timestamp()
+}
+
+/// RFC 3339 with whole seconds, carrying the local offset or `Z` for UTC.
+pub fn rfc3339(time: SystemTime, utc: bool) -> String {
+    if utc {
+        DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+    } else {
+        DateTime::<Local>::from(time).to_rfc3339_opts(SecondsFormat::Secs, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(unix)]
+    use std::env;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    // 2023-11-14 22:13:20 UTC, already the 15th east of UTC+02
+    fn time() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    #[test]
+    fn utc_ignores_the_time_zone() {
+        let style = parse_time_style("long-iso").unwrap();
+        assert_eq!(
+            format_time(time(), Some(&style), true),
+            "2023-11-14 22:13:20"
+        );
+        assert_eq!(format_time(time(), None, true), "Tue Nov 14 2023");
+        assert_eq!(rfc3339(time(), true), "2023-11-14T22:13:20Z");
+    }
+
+    // Only this test changes TZ. chrono reads it once per thread and then at most every
+    // second, so each zone gets a thread of its own. The zones are POSIX rules, which need
+    // no time zone database
+    #[cfg(unix)]
+    #[test]
+    fn local_time_follows_tz() {
+        let full = parse_time_style("full-iso").unwrap();
+        let original = env::var_os("TZ");
+        let local = |tz: &str| {
+            // SAFETY: no other test reads or writes the environment while this one runs
+            unsafe { env::set_var("TZ", tz) };
+            std::thread::scope(|scope| {
+                scope
+                    .spawn(|| {
+                        (
+                            format_time(time(), Some(&full), false),
+                            format_time(time(), None, false),
+                            rfc3339(time(), false),
+                        )
+                    })
+                    .join()
+                    .unwrap()
+            })
+        };
+
+        let (full_iso, date, json) = local("<+0530>-5:30");
+        assert_eq!(full_iso, "2023-11-15T03:43:20+05:30");
+        assert_eq!(date, "Wed Nov 15 2023");
+        assert_eq!(json, "2023-11-15T03:43:20+05:30");
+
+        let (full_iso, date, json) = local("<-08>8");
+        assert_eq!(full_iso, "2023-11-14T14:13:20-08:00");
+        assert_eq!(date, "Tue Nov 14 2023");
+        assert_eq!(json, "2023-11-14T14:13:20-08:00");
+
+        // An offset of zero is still spelled out, unlike under --utc
+        assert_eq!(local("<+00>0").2, "2023-11-14T22:13:20+00:00");
+        // SAFETY: as above
+        unsafe {
+            match original {
+                Some(tz) => env::set_var("TZ", tz),
+                None => env::remove_var("TZ"),
+            }
+        }
+    }
+
+    #[test]
+    fn styles_are_parsed() {
+        assert_eq!(
+            format_time(time(), Some(&parse_time_style("iso").unwrap()), true),
+            "2023-11-14 22:13"
+        );
+        let custom = parse_time_style("+%d.%m.%y").unwrap();
+        assert_eq!(format_time(time(), Some(&custom), true), "14.11.23");
+        assert!(
+            parse_time_style("locale")
+                .unwrap_err()
+                .contains("unknown time style")
+        );
+        assert!(
+            parse_time_style("+%Q")
+                .unwrap_err()
+                .contains("invalid time format")
+        );
+    }
+
+    #[test]
+    fn epoch_seconds_before_the_epoch() {
+        assert_eq!(epoch_seconds(time()), 1_700_000_000);
+        assert_eq!(epoch_seconds(UNIX_EPOCH - Duration::from_secs(90)), -90);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::cli::Cli;
+use crate::entry::RawEntry;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::UNIX_EPOCH;
+
+/// What --newest and --largest rank entries by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    /// Latest modification time first
+    Newest,
+    /// Largest size first, --du sums for directories and targets under --link-sizes
+    Largest,
+}
+
+impl Ranking {
+    /// The ranking the command line asks for and how many entries it keeps.
+    pub fn of(cli: &Cli) -> Option<(Ranking, usize)> {
+        cli.newest
+            .map(|count| (Ranking::Newest, count))
+            .or_else(|| cli.largest.map(|count| (Ranking::Largest, count)))
+    }
+
+    // Higher is better, entries without a modification time rank last
+    fn key(self, raw: &RawEntry) -> u128 {
+        match self {
+            Ranking::Newest => raw
+                .modified
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos()),
+            Ranking::Largest => raw.ranked_len() as u128,
+        }
+    }
+}
+
+/// The best ranked entries among those offered, at most `count` of them. Only those are
+/// kept, in a heap with the worst of them on top, so a huge directory is never sorted.
+pub struct Top {
+    ranking: Ranking,
+    count: usize,
+    heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+struct Ranked {
+    key: u128,
+    // Equal keys go by path, so the answer doesn't depend on the order entries are read in
+    path: String,
+    raw: RawEntry,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Ranked) -> Ordering {
+        self.key
+            .cmp(&other.key)
+            .then_with(|| other.path.cmp(&self.path))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Ranked) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Ranked) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+impl Top {
+    pub fn new(ranking: Ranking, count: usize) -> Top {
+        Top {
+            ranking,
+            count,
+            heap: BinaryHeap::with_capacity(count.saturating_add(1).min(1024)),
+        }
+    }
+
+    pub fn offer(&mut self, raw: RawEntry) {
+        if self.count == 0 {
+            return;
+        }
+        let ranked = Ranked {
+            key: self.ranking.key(&raw),
+            path: raw.path.clone().unwrap_or_else(|| raw.name.clone()),
+            raw,
+        };
+        if self.heap.len() < self.count {
+            self.heap.push(Reverse(ranked));
+        } else if let Some(mut worst) = self.heap.peek_mut()
+            && ranked > worst.0
+        {
+            *worst = Reverse(ranked);
+        }
+    }
+
+    /// The kept entries, best first.
+    pub fn into_entries(self) -> Vec<RawEntry> {
+        // Ascending order of the reversed ranks
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(ranked)| ranked.raw)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::{EntryType, map_short_data};
+    use crate::fixture::{Fixture, cli, output};
+    use std::time::Duration;
+
+    fn entry(name: &str, len: u64, modified: Option<u64>) -> RawEntry {
+        let mut raw = RawEntry::member(name.to_string(), EntryType::File, len, 0o644);
+        raw.modified = modified.map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+        raw
+    }
+
+    fn ranked(ranking: Ranking, count: usize, entries: Vec<RawEntry>) -> Vec<String> {
+        let mut top = Top::new(ranking, count);
+        for raw in entries {
+            top.offer(raw);
+        }
+        top.into_entries().into_iter().map(|raw| raw.name).collect()
+    }
+
+    #[test]
+    fn only_the_best_are_kept_best_first() {
+        let entries = || {
+            [7, 3, 9, 1, 8, 2, 6]
+                .iter()
+                .map(|&len| entry(&format!("f{}", len), len, Some(len)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ranked(Ranking::Largest, 3, entries()), ["f9", "f8", "f7"]);
+        assert_eq!(ranked(Ranking::Newest, 2, entries()), ["f9", "f8"]);
+        // Asking for more than there are gives them all
+        assert_eq!(ranked(Ranking::Largest, 100, entries()).len(), 7);
+    }
+
+    #[test]
+    fn ties_go_by_path_whatever_the_order() {
+        let names = ["c", "a", "d", "b"];
+        for rotation in 0..names.len() {
+            let mut order = names.to_vec();
+            order.rotate_left(rotation);
+            let entries = order.iter().map(|name| entry(name, 5, Some(100))).collect();
+            assert_eq!(ranked(Ranking::Largest, 2, entries), ["a", "b"]);
+        }
+        // Paths under -R take the place of names
+        let mut nested = entry("a", 5, None);
+        nested.path = Some("z/a".to_string());
+        let entries = vec![nested, entry("b", 5, None)];
+        assert_eq!(ranked(Ranking::Largest, 1, entries), ["b"]);
+    }
+
+    #[test]
+    fn entries_without_a_time_are_the_oldest() {
+        let entries = vec![
+            entry("unknown", 0, None),
+            entry("epoch", 0, Some(0)),
+            entry("late", 0, Some(5)),
+        ];
+        assert_eq!(
+            ranked(Ranking::Newest, 3, entries),
+            ["late", "epoch", "unknown"]
+        );
+    }
+
+    #[test]
+    fn a_count_of_zero_keeps_nothing() {
+        assert!(ranked(Ranking::Largest, 0, vec![entry("a", 1, None)]).is_empty());
+    }
+
+    // main exits with 1 when print_top says it found nothing
+    #[test]
+    fn nothing_to_rank_prints_nothing() {
+        let fixture = Fixture::new();
+        let run = |args: &[&str]| {
+            let cli = cli(args);
+            let top = Ranking::of(&cli).expect("a ranking is asked for");
+            let mut found = None;
+            let text = output(|out| {
+                found = Some(crate::render::print_top(
+                    out,
+                    fixture.path(),
+                    top,
+                    &cli,
+                    map_short_data,
+                )?);
+                Ok(())
+            });
+            (found.unwrap(), text)
+        };
+        assert_eq!(run(&["--largest=2", "-1"]), (false, String::new()));
+        fixture.file("big", b"12345").file("small", b"1");
+        assert_eq!(run(&["--largest=1", "-1"]), (true, "big\n".to_string()));
+        // Filters can leave nothing to rank as well
+        assert_eq!(
+            run(&["--newest", "--match", "*.rs", "-1"]),
+            (false, String::new())
+        );
+    }
+}
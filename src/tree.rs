@@ -0,0 +1,753 @@
+use crate::cli::{
+    Cli, hyperlinks, mounted_elsewhere, needs_long_entries, shows_hidden, skipped_in_tree,
+};
+use crate::collect::{
+    Probes, filters_metadata, full_path_parent, ids_match, name_matches, size_matches,
+    stat_entries, time_matches, type_matches,
+};
+use crate::columns::{self, Field};
+use crate::config::ThemeColor;
+use crate::entry::{
+    FieldSource, FileEntryLong, FileEntryShort, RawEntry, UNIX_METADATA, classify_suffix,
+    is_executable, linked, map_long_data, map_short_data, name_cell,
+};
+use crate::render::{print_json, print_yaml, summary_footer};
+use crate::report::{fail, unreadable};
+use crate::sort::dirs_first;
+use crate::style::TableStyle;
+use crate::summary::Summary;
+use crate::{archive, counts, deadline, du, failures, ignore};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tabled::builder::Builder;
+use tabled::settings::{Alignment, Style, object::Columns};
+
+/// A node of `--tree --json`, only the root carries the `path` it was listed from.
+#[derive(Debug, Serialize)]
+pub struct TreeNode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(flatten)]
+    pub entry: FileEntryShort,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<TreeNode>>,
+    /// Collapsed like node_modules, its children weren't read
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// On another file system under --one-file-system, its children weren't read
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub mounted: bool,
+}
+
+// Where the entries of a tree come from: directories on disk, or the members of an
+// archive grouped by the directory they are in
+enum Source {
+    Disk,
+    Archive(HashMap<PathBuf, Vec<RawEntry>>),
+}
+
+impl Source {
+    fn of(path: &Path, cli: &Cli) -> Source {
+        if !archive::opens(path, cli) {
+            return Source::Disk;
+        }
+        Source::Archive(archive::directories(path, cli).unwrap_or_else(|e| {
+            unreadable(cli, path, &e);
+            HashMap::new()
+        }))
+    }
+
+    fn entries(&self, path: &Path, cli: &Cli) -> Vec<RawEntry> {
+        let Source::Archive(directories) = self else {
+            return tree_entries(path, cli);
+        };
+        let mut entries = directories.get(path).cloned().unwrap_or_default();
+        entries.sort_by(|a, b| dirs_first(a, b, cli.sort_case));
+        entries.retain(|raw| tree_shows(path, raw, cli));
+        entries
+    }
+
+    fn descends(&self, dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+        match self {
+            Source::Disk => descends(dir, raw, cli),
+            Source::Archive(_) => raw.is_dir,
+        }
+    }
+
+    // Why `dir` can't be listed, archive members always can
+    fn read_error(&self, dir: &Path) -> Option<io::Error> {
+        match self {
+            Source::Disk => read_error(dir),
+            Source::Archive(_) => None,
+        }
+    }
+
+    fn entry_count(&self, dir: &Path, cli: &Cli) -> io::Result<u64> {
+        match self {
+            Source::Disk => counts::entry_count(dir, shows_hidden(cli)),
+            Source::Archive(directories) => Ok(directories.get(dir).map_or(0, |entries| {
+                entries
+                    .iter()
+                    .filter(|raw| shows_hidden(cli) || !raw.name.starts_with('.'))
+                    .count() as u64
+            })),
+        }
+    }
+}
+
+// The root is shown the way it was typed, or canonicalized under --absolute
+fn tree_root_label(path: &Path, cli: &Cli) -> String {
+    if cli.absolute
+        && let Ok(absolute) = fs::canonicalize(path)
+    {
+        return absolute.to_string_lossy().to_string();
+    }
+    path.to_string_lossy().to_string()
+}
+
+pub fn print_tree(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<()> {
+    // Print the root directory name
+    let root_name = tree_root_label(path, cli);
+
+    let root_line = root_name.bright_blue().bold().to_string();
+
+    let mut summary = Summary::default();
+    let source = Source::of(path, cli);
+    if needs_long_entries(cli) {
+        // Columns can only be aligned once every row is known
+        let root = RawEntry::from_path(path, root_name).ok();
+        let mut rows = vec![(root_line, root.map(|raw| map_long_data(raw, cli)))];
+        let mut collect = |line, raw: &RawEntry| {
+            rows.push((line, Some(map_long_data(raw.clone(), cli))));
+            Ok(())
+        };
+        print_tree_recursive(&mut collect, &source, path, "", cli, 0, &mut summary)?;
+        print_long_tree(out, &rows, cli)?;
+    } else {
+        writeln!(out, "{}", root_line)?;
+        let mut write = |line, _: &RawEntry| writeln!(out, "{}", line);
+        print_tree_recursive(&mut write, &source, path, "", cli, 0, &mut summary)?;
+    }
+
+    if !cli.no_summary {
+        writeln!(out)?;
+        writeln!(out, "{}", summary_footer(&summary, cli))?;
+    }
+    Ok(())
+}
+
+// The columns next to a --long tree, unless --fields picks others
+const LONG_TREE_FIELDS: &[Field] = &[
+    Field::Permissions,
+    Field::Owner,
+    Field::Size,
+    Field::Modified,
+];
+
+// The tree in the first column, the long columns aligned to its right
+fn print_long_tree(
+    out: &mut impl Write,
+    rows: &[(String, Option<FileEntryLong>)],
+    cli: &Cli,
+) -> io::Result<()> {
+    let fields: Vec<Field> = if cli.fields.is_empty() {
+        LONG_TREE_FIELDS.to_vec()
+    } else {
+        cli.fields
+            .iter()
+            .copied()
+            .filter(|&f| f != Field::Name)
+            .collect()
+    };
+    let mut builder = Builder::default();
+    for (line, entry) in rows {
+        let cells = fields.iter().map(|&f| match entry {
+            Some(entry) => entry.cell(f, cli),
+            None => String::default(),
+        });
+        builder.push_record(std::iter::once(line.clone()).chain(cells));
+    }
+    let mut table = builder.build();
+    table.with(Style::blank());
+    for (index, field) in fields.iter().enumerate() {
+        let spec = columns::spec(*field);
+        let column = Columns::one(index + 1);
+        table.modify(column, cli.theme.column(&field.to_string(), &spec.color));
+        if spec.right_aligned {
+            table.modify(column, Alignment::right());
+        }
+    }
+    writeln!(out, "{}", table)
+}
+
+pub fn print_tree_data(out: &mut impl Write, path: &Path, cli: &Cli) -> io::Result<()> {
+    let root = tree_data(path, cli);
+    if cli.yaml {
+        print_yaml(out, &root)
+    } else {
+        print_json(out, &root, cli)
+    }
+}
+
+/// The tree of `path` as nested nodes, for --json and --yaml.
+pub fn tree_data(path: &Path, cli: &Cli) -> TreeNode {
+    let root_name = tree_root_label(path, cli);
+    let mut raw = match RawEntry::from_path(path, root_name.clone()) {
+        Ok(raw) => raw,
+        Err(e) => fail(cli, format!("cannot access {}: {}", path.display(), e)),
+    };
+    let source = Source::of(path, cli);
+    let is_archive = matches!(source, Source::Archive(_));
+    // Walking the root first leaves every directory below it cached for build_tree
+    if cli.du && raw.is_dir {
+        raw.len = du::total(path, cli.one_file_system);
+    }
+    let children = (raw.is_dir || is_archive).then(|| build_tree(&source, path, cli, 0, cli.depth));
+
+    TreeNode {
+        path: Some(root_name),
+        entry: map_short_data(raw, cli),
+        children,
+        skipped: false,
+        mounted: false,
+    }
+}
+
+fn build_tree(
+    source: &Source,
+    path: &Path,
+    cli: &Cli,
+    current_depth: usize,
+    max_depth: usize,
+) -> Vec<TreeNode> {
+    if current_depth >= max_depth {
+        return Vec::new();
+    }
+
+    let parent = full_path_parent(path, cli);
+    let on_disk = matches!(source, Source::Disk);
+    source
+        .entries(path, cli)
+        .into_iter()
+        .take_while(|_| !deadline::expired())
+        .map(|mut raw| {
+            let entry_path = raw.path_in(path);
+            raw.path = parent
+                .as_ref()
+                .map(|parent| format!("{}{}", parent, raw.name));
+            if skipped_in_tree(&raw, cli) {
+                return TreeNode {
+                    path: None,
+                    entry: map_short_data(raw, cli),
+                    children: None,
+                    skipped: true,
+                    mounted: false,
+                };
+            }
+            if on_disk && mounted_elsewhere(path, &raw, cli) {
+                return TreeNode {
+                    path: None,
+                    entry: map_short_data(raw, cli),
+                    children: None,
+                    skipped: false,
+                    mounted: true,
+                };
+            }
+            if cli.du && on_disk && raw.is_real_dir() {
+                raw.len = du::total(&entry_path, cli.one_file_system);
+            }
+            if cli.dir_counts && raw.is_dir {
+                raw.entry_count = Some(
+                    source
+                        .entry_count(&entry_path, cli)
+                        .map_err(|e| e.to_string()),
+                );
+            }
+            let children = raw.is_dir.then(|| {
+                if source.descends(path, &raw, cli) {
+                    build_tree(source, &entry_path, cli, current_depth + 1, max_depth)
+                } else {
+                    Vec::new()
+                }
+            });
+            TreeNode {
+                path: None,
+                entry: map_short_data(raw, cli),
+                children,
+                skipped: false,
+                mounted: false,
+            }
+        })
+        .collect()
+}
+
+/// Why the directory `path` can't be read, checked before descending so the directory can
+/// be marked rather than shown as empty.
+pub fn read_error(path: &Path) -> Option<io::Error> {
+    fs::read_dir(path).err()
+}
+
+/// The visible children of a tree directory: directories first, then files, both alphabetically.
+/// Only children are filtered, so a hidden root is still listed. Each child is stat-ed once,
+/// sorting, filtering and drawing all work from that.
+pub fn tree_entries(path: &Path, cli: &Cli) -> Vec<RawEntry> {
+    if deadline::expired() {
+        return Vec::new();
+    }
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            unreadable(cli, path, &e);
+            return Vec::new();
+        }
+    };
+
+    let files: Vec<_> = entries
+        .filter_map(|entry| entry.inspect_err(|e| unreadable(cli, path, e)).ok())
+        .collect();
+    let mut entries = Vec::new();
+    let probes = Probes {
+        dereference: cli.dereference,
+        urls: hyperlinks(cli),
+        link_sizes: cli.link_sizes,
+        ..Probes::default()
+    };
+    let _ = stat_entries(&files, probes, |raw| {
+        entries.push(raw);
+        ControlFlow::Continue(())
+    });
+
+    entries.sort_by(|a, b| dirs_first(a, b, cli.sort_case));
+    entries
+        .into_iter()
+        .filter(|raw| tree_shows(path, raw, cli))
+        .inspect(|raw| {
+            if raw.error.is_some() {
+                failures::record_entry();
+            }
+        })
+        .collect()
+}
+
+// Filter out hidden files if needed, directories are kept for --regex/--match
+// and --files-only so the structure leading to matches stays visible
+fn tree_shows(dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+    (shows_hidden(cli) || !raw.name.starts_with("."))
+        && (name_matches(&raw.name, cli) || raw.is_dir)
+        && (type_matches(raw.is_dir, cli) || cli.files_only)
+        && !(raw.error.is_some() && filters_metadata(cli))
+        && tree_metadata_matches(dir, raw, cli)
+        && !ignore::ignores(dir, raw, cli)
+}
+
+/// The entries of `path` a tree would show, paired with their paths from `path`. With
+/// `recursive` the subdirectories follow without a depth limit, symlinked ones only
+/// under --dereference.
+pub fn tree_walk(path: &Path, cli: &Cli, recursive: bool) -> Vec<(PathBuf, RawEntry)> {
+    let mut found = Vec::new();
+    tree_walk_into(path, Path::new(""), cli, recursive, &mut found);
+    found
+}
+
+fn tree_walk_into(
+    dir: &Path,
+    relative: &Path,
+    cli: &Cli,
+    recursive: bool,
+    found: &mut Vec<(PathBuf, RawEntry)>,
+) {
+    for raw in tree_entries(dir, cli) {
+        if deadline::expired() {
+            return;
+        }
+        let path = raw.path_in(relative);
+        if recursive && descends(dir, &raw, cli) {
+            tree_walk_into(&raw.path_in(dir), &path, cli, recursive, found);
+        }
+        found.push((path, raw));
+    }
+}
+
+// Symlinked directories are only entered under --dereference, and never when they lead
+// back to the directory being listed or one of its ancestors. Nor are other file systems
+// under --one-file-system.
+pub fn descends(dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+    if !raw.is_real_dir() || mounted_elsewhere(dir, raw, cli) {
+        return false;
+    }
+    if !cli.dereference {
+        return true;
+    }
+    match (fs::canonicalize(raw.path_in(dir)), fs::canonicalize(dir)) {
+        (Ok(target), Ok(dir)) => !dir.starts_with(target),
+        _ => false,
+    }
+}
+
+// Size, time and owner filters only prune files, directories stay as structure
+// unless --du gives them a size to compare
+fn tree_metadata_matches(dir: &Path, raw: &RawEntry, cli: &Cli) -> bool {
+    if raw.is_dir {
+        let size_filtered = cli.min_size.is_some() || cli.max_size.is_some();
+        return !(cli.du && size_filtered)
+            || size_matches(true, du::total(&raw.path_in(dir), cli.one_file_system), cli);
+    }
+    size_matches(false, raw.len, cli) && time_matches(raw, cli) && ids_match(raw.uid, raw.gid, cli)
+}
+
+// Each line goes to `emit` without its line break, along with the entry it shows
+fn print_tree_recursive(
+    emit: &mut impl FnMut(String, &RawEntry) -> io::Result<()>,
+    source: &Source,
+    path: &Path,
+    prefix: &str,
+    cli: &Cli,
+    current_depth: usize,
+    summary: &mut Summary,
+) -> io::Result<()> {
+    if current_depth >= cli.depth {
+        return Ok(());
+    }
+
+    let visible_entries = source.entries(path, cli);
+    let parent = full_path_parent(path, cli).unwrap_or_default();
+
+    for (index, raw) in visible_entries.iter().enumerate() {
+        if deadline::expired() {
+            break;
+        }
+        let is_last = index == visible_entries.len() - 1;
+        let file_name_str = &raw.name;
+        let is_directory = raw.is_dir;
+
+        // Choose the appropriate tree characters
+        let (connector, next_prefix) = match (is_last, cli.style == TableStyle::Ascii) {
+            (true, false) => ("└── ", format!("{}    ", prefix)),
+            (false, false) => ("├── ", format!("{}│   ", prefix)),
+            (true, true) => ("`-- ", format!("{}    ", prefix)),
+            (false, true) => ("|-- ", format!("{}|   ", prefix)),
+        };
+
+        let display_name = name_cell(file_name_str, &parent, is_directory, cli);
+
+        // Left out of the totals, their contents would be all that's left of them
+        if skipped_in_tree(raw, cli) {
+            summary.skipped += 1;
+            let collapsed = format!("{}/ …", display_name).dimmed().to_string();
+            let collapsed = linked(collapsed, raw.url.as_deref());
+            emit(format!("{}{}{}", prefix, connector, collapsed), raw)?;
+            continue;
+        }
+        if matches!(source, Source::Disk) && mounted_elsewhere(path, raw, cli) {
+            summary.add(true, raw.len);
+            let mounted = format!("{} [mounted]", display_name).dimmed().to_string();
+            let mounted = linked(mounted, raw.url.as_deref());
+            emit(format!("{}{}{}", prefix, connector, mounted), raw)?;
+            continue;
+        }
+
+        let e_type = raw.e_type;
+        let executable = is_executable(e_type, raw.mode);
+        let special_kind = if executable {
+            Some(("executable", ThemeColor::BrightGreen))
+        } else {
+            e_type.theme_kind()
+        };
+
+        // Color the file name based on type, executables win over extensions
+        let colored_name = if let Some((kind, default)) = special_kind {
+            display_name
+                .color(cli.theme.kind(kind, default).ansi())
+                .to_string()
+        } else if is_directory {
+            let color = cli.theme.kind("directory", ThemeColor::BrightBlue);
+            if cli.files_only {
+                display_name.color(color.ansi()).dimmed().to_string()
+            } else {
+                display_name.color(color.ansi()).bold().to_string()
+            }
+        } else {
+            // Check file extension for different colors
+            let extension = Path::new(file_name_str)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or("");
+
+            let (kind, default) = match extension {
+                "rs" | "py" | "js" | "ts" | "go" | "cpp" | "c" | "java" => {
+                    ("source", ThemeColor::BrightGreen)
+                }
+                "txt" | "md" | "readme" => ("docs", ThemeColor::BrightYellow),
+                "json" | "yaml" | "yml" | "toml" | "xml" => ("data", ThemeColor::BrightCyan),
+                "png" | "jpg" | "jpeg" | "gif" | "svg" | "webp" => {
+                    ("image", ThemeColor::BrightMagenta)
+                }
+                _ => ("other", ThemeColor::White),
+            };
+            display_name
+                .color(cli.theme.kind(kind, default).ansi())
+                .to_string()
+        };
+
+        let descends = source.descends(path, raw, cli);
+        // Only directories the tree would go into are tried, not those at the depth limit
+        let read_error = (descends && current_depth + 1 < cli.depth)
+            .then(|| source.read_error(&raw.path_in(path)))
+            .flatten();
+
+        let inode = if cli.inode && raw.error.is_none() && UNIX_METADATA {
+            format!(" [{}]", raw.ino).bright_black().to_string()
+        } else {
+            String::default()
+        };
+        // Tells an empty directory apart from one whose entries were all filtered out
+        let count = if let Some(e) = &read_error {
+            summary.unreadable += 1;
+            failures::record_unreadable_dir();
+            format!(" [{}]", e.kind()).red().to_string()
+        } else if cli.dir_counts && is_directory {
+            match source.entry_count(&raw.path_in(path), cli) {
+                Ok(0) => " empty".bright_black().to_string(),
+                Ok(_) => String::default(),
+                Err(_) => " ?".red().to_string(),
+            }
+        } else {
+            String::default()
+        };
+        // Directories shown only as structure aren't part of the totals
+        if !(is_directory && cli.files_only) {
+            summary.add(is_directory, raw.len);
+        }
+
+        // Print the current entry
+        let line = format!(
+            "{}{}{}{}{}{}",
+            prefix,
+            connector,
+            linked(colored_name, raw.url.as_deref()),
+            classify_suffix(e_type, executable, cli),
+            inode,
+            count
+        );
+        emit(line, raw)?;
+
+        // Recursively print subdirectories
+        if descends && read_error.is_none() {
+            print_tree_recursive(
+                emit,
+                source,
+                &raw.path_in(path),
+                &next_prefix,
+                cli,
+                current_depth + 1,
+                summary,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, cli, output};
+
+    fn fixture() -> Fixture {
+        let fixture = Fixture::new();
+        fixture
+            .file("src/main.rs", b"fn main() {}\n")
+            .file("src/lib/mod.rs", b"")
+            .file("README.md", b"# readme\n")
+            .file(".git/HEAD", b"ref: refs/heads/main\n");
+        fixture
+    }
+
+    // The fixture's own path as the root line would be different every time
+    fn tree(fixture: &Fixture, args: &[&str]) -> String {
+        let cli = cli(&[&["--no-summary"], args].concat());
+        output(|out| print_tree(out, fixture.path(), &cli)).replacen(
+            &fixture.path().to_string_lossy().to_string(),
+            "ROOT",
+            1,
+        )
+    }
+
+    #[test]
+    fn directories_first_then_files() {
+        assert_eq!(
+            tree(&fixture(), &[]),
+            "\
+ROOT
+├── src
+│   ├── lib
+│   │   └── mod.rs
+│   └── main.rs
+└── README.md
+"
+        );
+    }
+
+    #[test]
+    fn depth_limits_the_levels() {
+        assert_eq!(
+            tree(&fixture(), &["--depth", "1"]),
+            "\
+ROOT
+├── src
+└── README.md
+"
+        );
+    }
+
+    #[test]
+    fn tree_names_are_made_safe_to_print() {
+        let fixture = Fixture::new();
+        fixture.file("bell\x07", b"").file("a b", b"");
+        assert_eq!(tree(&fixture, &[]), "ROOT\n├── a b\n└── bell?\n");
+        assert_eq!(
+            tree(&fixture, &["--escape", "--quote-names"]),
+            "ROOT\n├── 'a b'\n└── 'bell\\x07'\n"
+        );
+    }
+
+    #[test]
+    fn trees_follow_sort_case() {
+        let fixture = Fixture::new();
+        fixture
+            .file("Banana", b"")
+            .file("apple", b"")
+            .file("Ärger", b"");
+        assert_eq!(
+            tree(&fixture, &[]),
+            "ROOT\n├── apple\n├── Ärger\n└── Banana\n"
+        );
+        assert_eq!(
+            tree(&fixture, &["--sort-case", "sensitive"]),
+            "ROOT\n├── Banana\n├── apple\n└── Ärger\n"
+        );
+    }
+
+    #[test]
+    fn tree_data_nests_children() {
+        let fixture = fixture();
+        let root = tree_data(fixture.path(), &cli(&["--tree"]));
+        let children = root.children.expect("the root has children");
+        let names: Vec<_> = children
+            .iter()
+            .map(|child| child.entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["src", "README.md"]);
+        assert_eq!(children[0].children.as_ref().map(Vec::len), Some(2));
+        assert!(children[1].children.is_none());
+    }
+
+    #[test]
+    fn root_label_is_the_path_as_typed() {
+        let cli = cli(&[]);
+        for typed in [".", "..", "src/", "./src", "/usr/lib"] {
+            assert_eq!(tree_root_label(Path::new(typed), &cli), typed);
+        }
+    }
+
+    #[test]
+    fn absolute_canonicalizes_the_root() {
+        let cli = cli(&["--absolute"]);
+        let here = fs::canonicalize(".").unwrap();
+        assert_eq!(
+            tree_root_label(Path::new("."), &cli),
+            here.to_string_lossy()
+        );
+        assert_eq!(
+            tree_root_label(Path::new(".."), &cli),
+            here.parent().unwrap().to_string_lossy()
+        );
+        let fixture = fixture();
+        let typed = format!("{}/src/", fixture.path().display());
+        let expected = fs::canonicalize(fixture.join("src")).unwrap();
+        assert_eq!(
+            tree_root_label(Path::new(&typed), &cli),
+            expected.to_string_lossy()
+        );
+        // Nothing to canonicalize, so it stays as typed
+        let gone = fixture.join("gone");
+        assert_eq!(tree_root_label(&gone, &cli), gone.to_string_lossy());
+    }
+
+    #[test]
+    fn json_root_carries_the_path() {
+        let fixture = fixture();
+        let typed = format!("{}/src/", fixture.path().display());
+        let root = tree_data(Path::new(&typed), &cli(&["--tree"]));
+        assert_eq!(root.path.as_deref(), Some(typed.as_str()));
+        let children = root.children.expect("the root has children");
+        assert!(children.iter().all(|child| child.path.is_none()));
+
+        let json = serde_json::to_value(tree_data(fixture.path(), &cli(&["--tree"]))).unwrap();
+        assert_eq!(json["path"], fixture.path().to_string_lossy().as_ref());
+        assert!(json["children"][0].get("path").is_none());
+    }
+
+    #[test]
+    fn a_hidden_root_is_still_walked() {
+        let fixture = fixture();
+        let root = tree_data(&fixture.join(".git"), &cli(&["--tree"]));
+        let children = root.children.expect("the root has children");
+        assert_eq!(children[0].entry.name, "HEAD");
+        // While below another root it is hidden as usual
+        assert_eq!(
+            tree(&fixture, &[])
+                .lines()
+                .filter(|l| l.contains(".git"))
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn read_errors_are_found_before_descending() {
+        let fixture = fixture();
+        assert!(read_error(&fixture.join("src")).is_none());
+        assert_eq!(
+            read_error(&fixture.join("gone")).map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+    }
+
+    // Root reads any directory, so there is nothing to see then
+    #[cfg(unix)]
+    #[test]
+    fn unreadable_directories_are_marked_not_empty() {
+        use std::os::unix::fs::PermissionsExt;
+        if users::get_effective_uid() == 0 {
+            return;
+        }
+        let fixture = fixture();
+        fixture.file("locked/secret.txt", b"").dir("locked");
+        let locked = fixture.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        let cli = cli(&[]);
+        let before = failures::unreadable_dirs();
+        let text = output(|out| print_tree(out, fixture.path(), &cli));
+        // Counted across the tests running alongside, so only ever more
+        let after = failures::unreadable_dirs();
+        // Or the fixture can't be removed
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(
+            text.contains("├── locked [permission denied]\n"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("secret.txt"));
+        assert!(text.contains(", 1 unreadable"), "{}", text);
+        // Which makes the exit status non-zero
+        assert!(after > before && failures::any());
+    }
+}
@@ -0,0 +1,132 @@
+use unicode_width::UnicodeWidthChar;
+
+const ELLIPSIS: char = '…';
+
+// Part of a cell: an escape sequence taking no room, or a character shown on screen
+enum Piece<'a> {
+    Escape(&'a str),
+    Char(char),
+}
+
+/// Columns `text` takes on screen: its widest line, leaving out colors and hyperlinks.
+/// Wide characters like CJK count twice.
+pub fn display_width(text: &str) -> usize {
+    text.split('\n').map(line_width).max().unwrap_or(0)
+}
+
+/// Shortens each line of `text` to `max` columns by cutting out its middle, e.g.
+/// `very-long-…-name.tar.gz`. The end of the name is favoured so the extension stays
+/// visible, and escape sequences are all kept so colors and hyperlinks stay balanced.
+pub fn middle(text: &str, max: usize) -> String {
+    text.split('\n')
+        .map(|line| middle_line(line, max))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn middle_line(line: &str, max: usize) -> String {
+    let width = line_width(line);
+    if width <= max {
+        return line.to_string();
+    }
+    let plain: String = pieces(line)
+        .filter_map(|piece| match piece {
+            Piece::Char(c) => Some(c),
+            Piece::Escape(_) => None,
+        })
+        .collect();
+    let budget = max.saturating_sub(1);
+    let extension = extension(&plain).chars().map(char_width).sum::<usize>();
+    let tail = (budget / 2).max(extension.min(budget * 2 / 3));
+    let head = budget - tail;
+
+    let mut out = String::with_capacity(line.len());
+    let mut seen = 0;
+    let mut cut = false;
+    for piece in pieces(line) {
+        match piece {
+            Piece::Escape(escape) => out.push_str(escape),
+            Piece::Char(c) => {
+                let before = seen;
+                seen += char_width(c);
+                if !cut && seen <= head {
+                    out.push(c);
+                    continue;
+                }
+                if !cut {
+                    out.push(ELLIPSIS);
+                    cut = true;
+                }
+                if width - before <= tail {
+                    out.push(c);
+                }
+            }
+        }
+    }
+    out
+}
+
+// `.gz` of `notes.gz`, both parts of short double extensions like `.tar.gz`, nothing
+// for dotfiles
+fn extension(name: &str) -> &str {
+    let name = name.trim_end_matches(['/', '*', '@', '|', '=']);
+    let name = &name[name.rfind('/').map_or(0, |i| i + 1)..];
+    let dot = |s: &str| s.rfind('.').filter(|&i| i > 0);
+    let Some(last) = dot(name) else {
+        return "";
+    };
+    match dot(&name[..last]) {
+        Some(first) if last - first <= 4 => &name[first..],
+        _ => &name[last..],
+    }
+}
+
+fn line_width(line: &str) -> usize {
+    pieces(line)
+        .map(|piece| match piece {
+            Piece::Char(c) => char_width(c),
+            Piece::Escape(_) => 0,
+        })
+        .sum()
+}
+
+fn char_width(c: char) -> usize {
+    c.width().unwrap_or(0)
+}
+
+// Splits out CSI sequences like colors (`ESC [ … m`) and OSC sequences like hyperlinks
+// (`ESC ] … ESC \`), which end in BEL in some terminals
+fn pieces(line: &str) -> impl Iterator<Item = Piece<'_>> {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        let mut chars = rest.chars();
+        let c = chars.next()?;
+        let len = if c != '\x1b' {
+            c.len_utf8()
+        } else {
+            match chars.next() {
+                Some('[') => rest[2..]
+                    .find(|c: char| ('\x40'..='\x7e').contains(&c))
+                    .map_or(rest.len(), |i| i + 3),
+                Some(']') => {
+                    let body = &rest[2..];
+                    match (body.find('\x07'), body.find("\x1b\\")) {
+                        (Some(bel), Some(st)) if bel < st => bel + 3,
+                        (_, Some(st)) => st + 4,
+                        (Some(bel), None) => bel + 3,
+                        (None, None) => rest.len(),
+                    }
+                }
+                Some(next) => 1 + next.len_utf8(),
+                None => 1,
+            }
+        };
+        let (piece, tail) = rest.split_at(len);
+        rest = tail;
+        Some(if c == '\x1b' {
+            Piece::Escape(piece)
+        } else {
+            Piece::Char(c)
+        })
+    })
+}
@@ -0,0 +1,83 @@
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// A burst of changes, e.g. a download writing its chunks, becomes a single redraw
+const SETTLE: Duration = Duration::from_millis(200);
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+#[cfg(unix)]
+const HIDE_CURSOR: &str = "\x1b[?25l";
+#[cfg(unix)]
+const SHOW_CURSOR: &str = "\x1b[?25h";
+
+/// Redraws the listing with `render` every time entries below `path` are created, removed
+/// or modified, until interrupted. `recursive` also watches subdirectories, for trees.
+pub fn watch<W: Write>(
+    out: &mut W,
+    path: &Path,
+    recursive: bool,
+    mut render: impl FnMut(&mut W) -> io::Result<()>,
+) -> notify::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(path, mode)?;
+    // Without a signal handler to show it again Ctrl-C would leave the cursor hidden
+    #[cfg(unix)]
+    {
+        restore_cursor_on_interrupt();
+        write!(out, "{}", HIDE_CURSOR)?;
+    }
+    loop {
+        write!(out, "{}", CLEAR_SCREEN)?;
+        render(out)?;
+        out.flush()?;
+
+        // Block until something changed, then wait for the changes to settle
+        loop {
+            match rx.recv() {
+                Ok(event) if changes_entries(&event) => break,
+                Ok(_) => {}
+                Err(_) => return Ok(()),
+            }
+        }
+        while rx.recv_timeout(SETTLE).is_ok() {}
+    }
+}
+
+// Reading a directory to redraw it causes access events, which must not trigger a redraw
+fn changes_entries(event: &notify::Result<Event>) -> bool {
+    match event {
+        Ok(event) => matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(_)
+        ),
+        Err(_) => false,
+    }
+}
+
+#[cfg(unix)]
+fn restore_cursor_on_interrupt() {
+    extern "C" fn on_interrupt(_: libc::c_int) {
+        // Only async-signal-safe calls in here
+        unsafe {
+            libc::write(
+                libc::STDOUT_FILENO,
+                SHOW_CURSOR.as_ptr().cast(),
+                SHOW_CURSOR.len(),
+            );
+            libc::_exit(130);
+        }
+    }
+    let handler = on_interrupt as extern "C" fn(libc::c_int);
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
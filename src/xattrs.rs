@@ -0,0 +1,49 @@
+use std::path::Path;
+
+// Where Linux keeps POSIX ACLs, a default ACL only exists on directories
+const ACL_NAMES: &[&str] = &["system.posix_acl_access", "system.posix_acl_default"];
+// Set on every file of an SELinux system, ls shows it as `.` rather than `@`
+const SELINUX_LABEL: &str = "security.selinux";
+
+/// The names of the extended attributes of `path` itself, symlinks aren't followed.
+/// Only the names are listed, never the values. None when they can't be listed.
+#[cfg(unix)]
+pub fn names(path: &Path) -> Option<Vec<String>> {
+    let mut names: Vec<String> = xattr::list(path)
+        .ok()?
+        .map(|name| name.to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    Some(names)
+}
+
+#[cfg(not(unix))]
+pub fn names(_: &Path) -> Option<Vec<String>> {
+    None
+}
+
+/// The SELinux security context of `path` itself, without following symlinks. None when
+/// it has none, SELinux isn't enabled or the attribute can't be read.
+#[cfg(unix)]
+pub fn selinux_context(path: &Path) -> Option<String> {
+    let value = xattr::get(path, SELINUX_LABEL).ok()??;
+    // The kernel hands the context over NUL terminated
+    let value = value.strip_suffix(b"\0").unwrap_or(&value);
+    (!value.is_empty()).then(|| String::from_utf8_lossy(value).to_string())
+}
+
+#[cfg(not(unix))]
+pub fn selinux_context(_: &Path) -> Option<String> {
+    None
+}
+
+/// The marker `ls -l` appends to the permissions: `+` for an ACL, `@` for other attributes.
+pub fn marker(names: &[String]) -> &'static str {
+    if names.iter().any(|name| ACL_NAMES.contains(&name.as_str())) {
+        "+"
+    } else if names.iter().any(|name| name != SELINUX_LABEL) {
+        "@"
+    } else {
+        ""
+    }
+}
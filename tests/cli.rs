@@ -0,0 +1,170 @@
+//! The binary as scripts see it: what ends up on stdout and stderr, and the exit status.
+
+use assert_cmd::Command;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+// A directory with an old and a new file, the old one modified a year ago
+fn fixture() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("new.txt"), b"new").unwrap();
+    let old = File::create(dir.path().join("old.txt")).unwrap();
+    old.set_modified(SystemTime::now() - Duration::from_secs(365 * 24 * 3600))
+        .unwrap();
+    dir
+}
+
+// Run from `dir` without the user's config file and $BETTER_LS_OPTS
+fn best_ls(dir: &Path) -> Command {
+    let mut command = assert_cmd::cargo::cargo_bin_cmd!("best-ls");
+    command
+        .current_dir(dir)
+        .env_remove("BETTER_LS_OPTS")
+        .env("HOME", dir)
+        .env_remove("XDG_CONFIG_HOME")
+        .env_remove("XDG_CACHE_HOME")
+        .arg("--no-config");
+    command
+}
+
+fn stderr(assert: &assert_cmd::assert::Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).to_string()
+}
+
+#[test]
+fn a_full_listing_exits_with_zero() {
+    let dir = fixture();
+    best_ls(dir.path())
+        .arg("-1")
+        .assert()
+        .code(0)
+        .stdout("new.txt\nold.txt\n")
+        .stderr("");
+}
+
+#[test]
+fn nothing_to_list_exits_with_two() {
+    let dir = fixture();
+    let assert = best_ls(dir.path())
+        .args(["-1", "missing"])
+        .assert()
+        .code(2)
+        .stdout("");
+    assert!(stderr(&assert).contains("cannot access missing"));
+}
+
+#[test]
+fn one_missing_path_of_several_exits_with_one() {
+    let dir = fixture();
+    let assert = best_ls(dir.path())
+        .args(["-1", "-q", ".", "missing"])
+        .assert()
+        .code(1)
+        .stdout("new.txt\nold.txt\n");
+    assert!(stderr(&assert).contains("cannot access missing"));
+}
+
+#[test]
+fn a_truncated_listing_exits_with_one() {
+    let dir = fixture();
+    let assert = best_ls(dir.path())
+        .args(["-1", "--max-entries", "1"])
+        .assert()
+        .code(1)
+        .stdout("new.txt\n");
+    assert!(stderr(&assert).contains("the listing is incomplete"));
+}
+
+#[test]
+fn stale_entries_exit_with_four() {
+    let dir = fixture();
+    best_ls(dir.path())
+        .args(["-1", "--stale-after", "30d"])
+        .assert()
+        .code(4)
+        .stdout("new.txt\nold.txt\n");
+    best_ls(dir.path())
+        .args(["-1", "--stale-after", "2y"])
+        .assert()
+        .code(0);
+}
+
+#[test]
+fn usage_errors_exit_with_two() {
+    let dir = fixture();
+    let assert = best_ls(dir.path())
+        .args(["--timeout", "0"])
+        .assert()
+        .code(2)
+        .stdout("");
+    assert!(stderr(&assert).contains("--timeout"));
+}
+
+// Errors go to stderr, so what a program reads stays valid JSON
+#[test]
+fn errors_keep_out_of_json() {
+    let dir = fixture();
+    let assert = best_ls(dir.path())
+        .args(["--json", "--compact", "missing", "."])
+        .assert()
+        .code(1);
+    let json: serde_json::Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    let names: Vec<_> = json["."]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["new.txt", "old.txt"]);
+    assert!(stderr(&assert).contains("cannot access missing"));
+}
+
+#[test]
+fn piped_output_has_no_escape_codes() {
+    let dir = fixture();
+    let assert = best_ls(dir.path()).args(["missing", "."]).assert().code(1);
+    let output = assert.get_output();
+    assert!(!output.stdout.contains(&0x1b));
+    assert!(!output.stderr.contains(&0x1b));
+}
+
+#[cfg(unix)]
+#[test]
+fn unreadable_directories_exit_with_one() {
+    use std::os::unix::fs::PermissionsExt;
+    // Root reads any directory
+    if users::get_effective_uid() == 0 {
+        return;
+    }
+    let dir = fixture();
+    let locked = dir.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    let quiet = best_ls(dir.path()).args(["-1", "-R", "-q"]).assert();
+    let warned = best_ls(dir.path()).args(["-1", "-R"]).assert();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    quiet
+        .code(1)
+        .stdout("locked\nnew.txt\nold.txt\n\n")
+        .stderr("");
+    let warned = warned.code(1);
+    assert!(stderr(&warned).contains("1 directory could not be read"));
+}
+
+#[test]
+fn stderr_has_no_escape_codes_before_the_arguments_are_parsed() {
+    let dir = fixture();
+    let assert = assert_cmd::cargo::cargo_bin_cmd!("best-ls")
+        .current_dir(dir.path())
+        .env("HOME", dir.path())
+        .env("BETTER_LS_OPTS", "--match 'unterminated")
+        .args(["--color", "never"])
+        .assert()
+        .code(2)
+        .stdout("");
+    let stderr = stderr(&assert);
+    assert!(stderr.starts_with("BETTER_LS_OPTS: "), "{}", stderr);
+    assert!(!stderr.contains('\x1b'), "{}", stderr);
+}